@@ -3,8 +3,9 @@ use std::collections::HashMap;
 // use axum::response::Response;
 use axum::{extract, Json};
 use axum::{http::StatusCode, response::IntoResponse};
-use goap_runner::PlanConfig;
-use goap_runner::{Finder, Gaction, Goal};
+use goap_runner::{detect_version, load_value};
+use goap_runner::{Finder, Gaction, Goal, GoalMode, GoalSegment};
+use goap_runner::{ListedAction, PlanConfig, ValidationReport};
 
 use serde::{Deserialize, Serialize};
 
@@ -17,7 +18,7 @@ pub struct ParameterValue {
 #[derive(Debug, Deserialize, Clone)]
 pub struct BodyAction {
     pub name: String,
-    pub cost: usize,
+    pub cost: i64,
     pub pre_state: Vec<ParameterValue>,
     pub post_state: Vec<ParameterValue>,
 }
@@ -33,6 +34,8 @@ pub struct PlanBody {
     pub actions: Vec<BodyAction>,
     pub goals: Vec<BodyGoal>,
     pub worldState: Vec<ParameterValue>,
+    #[serde(default)]
+    pub goal_mode: GoalMode,
 }
 
 fn body_state_to_hash_map(p: &Vec<ParameterValue>) -> HashMap<String, bool> {
@@ -45,24 +48,34 @@ fn body_state_to_hash_map(p: &Vec<ParameterValue>) -> HashMap<String, bool> {
     return h;
 }
 
-pub fn to_plan_config(plan_config: PlanBody) -> Result<PlanConfig, String> {
+pub fn to_plan_config(plan_config: PlanBody) -> Result<PlanConfig, ValidationReport> {
     let PlanBody {
         actions,
         goals,
         worldState,
+        goal_mode,
     } = plan_config;
 
-    // exxtract action map
+    // exxtract action map, the list is kept so validation can point at its indexes
     let mut plan_actions: HashMap<String, Gaction> = HashMap::new();
+    let mut listed_actions: Vec<ListedAction> = vec![];
 
     for a in actions {
         let plan_action: Gaction = Gaction {
-            cost: a.cost,
+            cost: usize::try_from(a.cost).unwrap_or_default(),
             pre_state: body_state_to_hash_map(&a.pre_state),
             post_state: body_state_to_hash_map(&a.post_state),
+            ..Default::default()
         };
 
-        plan_actions.insert(a.name, plan_action);
+        plan_actions
+            .entry(a.name.clone())
+            .or_insert_with(|| plan_action.clone());
+        listed_actions.push(ListedAction {
+            name: a.name,
+            cost: a.cost,
+            action: plan_action,
+        });
     }
     // extract goals
     let mut plan_goal_list: Vec<Goal> = vec![];
@@ -79,35 +92,70 @@ pub fn to_plan_config(plan_config: PlanBody) -> Result<PlanConfig, String> {
     // extract world state
     let plan_world_state: HashMap<String, bool> = body_state_to_hash_map(&worldState);
 
-    // minor error/empty state check to show Errors on Client later
-    if plan_world_state.is_empty() || plan_goal_list.is_empty() || plan_actions.is_empty() {
-        Err("Actions/Goals/World State must not be empty".to_string())
+    let config = PlanConfig {
+        actions: plan_actions,
+        goals: plan_goal_list,
+        worldState: plan_world_state,
+        goal_mode,
+        ..Default::default()
+    };
+
+    let report = config.validate_listed(&listed_actions);
+    if report.has_errors() {
+        Err(report)
     } else {
-        Ok(PlanConfig {
-            actions: plan_actions,
-            goals: plan_goal_list,
-            worldState: plan_world_state,
-        })
+        Ok(config)
     }
 }
 
 #[derive(Serialize)]
 pub struct ListActionNames {
     pub action_names: Vec<String>,
+    pub cost: usize,
+    pub segments: Vec<GoalSegment>,
+}
+
+/// Config of a request body, list bodies point issues at list indexes,
+/// newer versions are migrated before they are validated
+fn body_to_plan_config(body: serde_json::Value) -> Result<PlanConfig, (StatusCode, String)> {
+    let bad_request = |message: String| (StatusCode::BAD_REQUEST, message);
+    let report_error =
+        |report: ValidationReport| bad_request(serde_json::to_string(&report).unwrap_or_default());
+
+    if detect_version(&body).map_err(|e| bad_request(e.to_string()))? == 1 {
+        let body: PlanBody =
+            serde_json::from_value(body).map_err(|e| bad_request(e.to_string()))?;
+        return to_plan_config(body).map_err(report_error);
+    }
+
+    let config = load_value(body).map_err(|e| bad_request(e.to_string()))?;
+    let report = config.validate();
+    if report.has_errors() {
+        Err(report_error(report))
+    } else {
+        Ok(config)
+    }
 }
 
 pub async fn goap_run(
-    extract::Json(payload): extract::Json<PlanBody>, // Important, need to EXTRACT
+    extract::Json(payload): extract::Json<serde_json::Value>, // Important, need to EXTRACT
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
-    match to_plan_config(payload) {
-        Ok(config) => {
-            let mut finder = Finder::new(&config);
-            let leaves = finder.execute();
-
-            Ok(Json(ListActionNames {
-                action_names: leaves,
-            }))
-        }
-        Err(reason) => Err((StatusCode::BAD_REQUEST, reason)),
-    }
+    let config = body_to_plan_config(payload)?;
+    let mut finder = Finder::new(&config);
+
+    // no plan found is still a valid answer, with nothing to do
+    let response = match finder.plan() {
+        Some(result) => ListActionNames {
+            action_names: result.actions,
+            cost: result.cost,
+            segments: result.segments,
+        },
+        None => ListActionNames {
+            action_names: vec![],
+            cost: 0,
+            segments: vec![],
+        },
+    };
+
+    Ok(Json(response))
 }
@@ -28,10 +28,10 @@ pub async fn get_table_eval() -> Result<impl IntoResponse, (StatusCode, String)>
 
             match res {
                 Ok(output) => Ok(Json(output)),
-                Err(e) => Err((StatusCode::BAD_REQUEST, e)),
+                Err(e) => Err((StatusCode::BAD_REQUEST, e.render(contents))),
             }
         }
-        Err(e) => Err((StatusCode::BAD_REQUEST, e)),
+        Err(e) => Err((StatusCode::BAD_REQUEST, e.render(contents))),
     }
 }
 
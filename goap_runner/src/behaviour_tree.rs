@@ -0,0 +1,450 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::configs::{hasmap_contains, PlanConfig, WorldState};
+use crate::plan::PlanResult;
+use crate::probabilistic::ContingentPlan;
+
+/// attempts of a `Retry` node exported from a contingent plan
+pub const MAX_BT_RETRIES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BtStatus {
+    Success,
+    Failure,
+    Running,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BtNode {
+    /// runs children in order until one fails
+    Sequence {
+        children: Vec<BtNode>,
+    },
+    /// runs children in order until one succeeds
+    Selector {
+        children: Vec<BtNode>,
+    },
+    /// succeeds when the world state contains `state`
+    Condition {
+        state: HashMap<String, bool>,
+    },
+    /// leaf performed by the engine
+    Action {
+        name: String,
+    },
+    /// runs `child` again after it failed, up to `attempts` times in total
+    Retry {
+        attempts: usize,
+        child: Box<BtNode>,
+    },
+    Succeed,
+    Fail,
+}
+
+impl BtNode {
+    /// Sequence of action leaves, each guarded by the action's preconditions
+    pub fn from_plan(config: &PlanConfig, plan: &PlanResult) -> BtNode {
+        BtNode::Sequence {
+            children: plan
+                .actions
+                .iter()
+                .flat_map(|name| guarded_action(config, name))
+                .collect(),
+        }
+    }
+
+    /// Like `from_plan`, after every action a selector picks the branch of the
+    /// outcome which happened, by the facts that tell it apart from the others.
+    /// Steps which may come back to an earlier state are wrapped into `Retry`,
+    /// the `Repeat` branch itself fails so the nearest retry starts over.
+    pub fn from_contingent(config: &PlanConfig, plan: &ContingentPlan) -> BtNode {
+        let (action, branches) = match plan {
+            ContingentPlan::Goal => return BtNode::Succeed,
+            ContingentPlan::DeadEnd | ContingentPlan::Repeat => return BtNode::Fail,
+            ContingentPlan::Step {
+                action, branches, ..
+            } => (action, branches),
+        };
+
+        let mut children = guarded_action(config, action);
+        match &branches[..] {
+            [only] => match BtNode::from_contingent(config, &only.plan) {
+                BtNode::Succeed => {}
+                BtNode::Sequence { children: rest } => children.extend(rest),
+                next => children.push(next),
+            },
+            _ => {
+                let outcomes: Vec<&WorldState> = config
+                    .actions
+                    .get(action)
+                    .map(|a| a.possible_outcomes())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(_, post_state)| post_state)
+                    .collect();
+
+                children.push(BtNode::Selector {
+                    children: outcome_conditions(&outcomes)
+                        .into_iter()
+                        .filter_map(|(index, state)| {
+                            let branch = branches.get(index)?;
+                            Some(BtNode::Sequence {
+                                children: vec![
+                                    BtNode::Condition { state },
+                                    BtNode::from_contingent(config, &branch.plan),
+                                ],
+                            })
+                        })
+                        .collect(),
+                });
+            }
+        }
+
+        let node = BtNode::Sequence { children };
+        if repeats(plan) {
+            BtNode::Retry {
+                attempts: MAX_BT_RETRIES,
+                child: Box::new(node),
+            }
+        } else {
+            node
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+fn guarded_action(config: &PlanConfig, name: &str) -> Vec<BtNode> {
+    vec![
+        BtNode::Condition {
+            state: config
+                .actions
+                .get(name)
+                .map(|a| a.pre_state.clone())
+                .unwrap_or_default(),
+        },
+        BtNode::Action {
+            name: name.to_string(),
+        },
+    ]
+}
+
+/// Condition of each outcome, by index: the facts it sets which not every
+/// other outcome sets the same way. Facts all outcomes share may have held
+/// before, so they tell nothing. Outcomes with fewer facts are checked later,
+/// one which changes nothing last as its empty condition always holds.
+fn outcome_conditions(outcomes: &[&WorldState]) -> Vec<(usize, WorldState)> {
+    let mut conditions: Vec<(usize, WorldState)> = outcomes
+        .iter()
+        .enumerate()
+        .map(|(index, post_state)| {
+            let distinct = post_state
+                .iter()
+                .filter(|(fact, value)| outcomes.iter().any(|o| o.get(*fact) != Some(value)))
+                .map(|(fact, value)| (fact.clone(), *value))
+                .collect();
+            (index, distinct)
+        })
+        .collect();
+    conditions.sort_by_key(|(_, condition)| std::cmp::Reverse(condition.len()));
+    conditions
+}
+
+fn repeats(plan: &ContingentPlan) -> bool {
+    match plan {
+        ContingentPlan::Repeat => true,
+        ContingentPlan::Step { branches, .. } => branches.iter().any(|b| repeats(&b.plan)),
+        _ => false,
+    }
+}
+
+/// Ticks a behaviour tree against a world state. Sequences and selectors
+/// remember a `Running` child and continue with it on the next tick.
+/// Actions are performed by a callback, which also updates the world state.
+pub struct BtExecutor<'t> {
+    tree: &'t BtNode,
+    /// current child of composites and attempts of retries, by node path
+    memory: HashMap<Vec<usize>, usize>,
+}
+
+impl<'t> BtExecutor<'t> {
+    pub fn new(tree: &'t BtNode) -> Self {
+        BtExecutor {
+            tree,
+            memory: HashMap::new(),
+        }
+    }
+
+    pub fn tick<F>(&mut self, state: &mut WorldState, mut perform: F) -> BtStatus
+    where
+        F: FnMut(&str, &mut WorldState) -> BtStatus,
+    {
+        let tree = self.tree;
+        self.tick_node(tree, &mut vec![], state, &mut perform)
+    }
+
+    /// Ticks until the tree is no longer running, at most `max_ticks` times
+    pub fn run<F>(&mut self, state: &mut WorldState, mut perform: F, max_ticks: usize) -> BtStatus
+    where
+        F: FnMut(&str, &mut WorldState) -> BtStatus,
+    {
+        let mut status = BtStatus::Running;
+        for _ in 0..max_ticks {
+            status = self.tick(state, &mut perform);
+            if status != BtStatus::Running {
+                break;
+            }
+        }
+        status
+    }
+
+    fn tick_node(
+        &mut self,
+        node: &BtNode,
+        path: &mut Vec<usize>,
+        state: &mut WorldState,
+        perform: &mut dyn FnMut(&str, &mut WorldState) -> BtStatus,
+    ) -> BtStatus {
+        match node {
+            BtNode::Succeed => BtStatus::Success,
+            BtNode::Fail => BtStatus::Failure,
+            BtNode::Condition { state: condition } => {
+                if hasmap_contains(state, condition) {
+                    BtStatus::Success
+                } else {
+                    BtStatus::Failure
+                }
+            }
+            BtNode::Action { name } => perform(name, state),
+            BtNode::Sequence { children } => {
+                self.tick_children(children, BtStatus::Success, path, state, perform)
+            }
+            BtNode::Selector { children } => {
+                self.tick_children(children, BtStatus::Failure, path, state, perform)
+            }
+            BtNode::Retry { attempts, child } => loop {
+                path.push(0);
+                let status = self.tick_node(child, path, state, perform);
+                path.pop();
+
+                let failed = self.memory.get(path).copied().unwrap_or(0) + 1;
+                match status {
+                    BtStatus::Failure if failed < *attempts => {
+                        self.memory.insert(path.clone(), failed);
+                    }
+                    BtStatus::Running => return BtStatus::Running,
+                    done => {
+                        self.memory.remove(path);
+                        return done;
+                    }
+                }
+            },
+        }
+    }
+
+    /// Children run while they return `keep_going`, which is also the result
+    /// when all of them did
+    fn tick_children(
+        &mut self,
+        children: &[BtNode],
+        keep_going: BtStatus,
+        path: &mut Vec<usize>,
+        state: &mut WorldState,
+        perform: &mut dyn FnMut(&str, &mut WorldState) -> BtStatus,
+    ) -> BtStatus {
+        let start = self.memory.get(path).copied().unwrap_or(0);
+
+        for (index, child) in children.iter().enumerate().skip(start) {
+            path.push(index);
+            let status = self.tick_node(child, path, state, perform);
+            path.pop();
+
+            if status == BtStatus::Running {
+                self.memory.insert(path.clone(), index);
+                return status;
+            }
+            if status != keep_going {
+                self.memory.remove(path);
+                return status;
+            }
+        }
+
+        self.memory.remove(path);
+        keep_going
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration::load_versioned;
+    use crate::{Finder, ProbabilisticPlanner};
+
+    fn sample_config() -> Result<PlanConfig, String> {
+        let contents = std::fs::read_to_string("./actions.json").map_err(|e| e.to_string())?;
+        load_versioned(&contents).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn runs_exported_plan() -> Result<(), String> {
+        let config = sample_config()?;
+        let plan = Finder::new(&config).plan().ok_or("no plan")?;
+        let tree = BtNode::from_plan(&config, &plan);
+
+        let imported: BtNode = serde_json::from_str(&tree.to_json()).map_err(|e| e.to_string())?;
+        assert_eq!(imported, tree);
+
+        let mut performed = vec![];
+        let mut state = config.worldState.clone();
+        let status = BtExecutor::new(&imported).tick(&mut state, |name, state| {
+            performed.push(name.to_string());
+            config.actions[name].update_with_post_conditions(state);
+            BtStatus::Success
+        });
+
+        assert_eq!(status, BtStatus::Success);
+        assert_eq!(performed, plan.actions);
+        assert_eq!(state.get("enemyAlive"), Some(&false));
+        Ok(())
+    }
+
+    #[test]
+    fn guard_stops_plan_when_action_had_no_effect() -> Result<(), String> {
+        let config = sample_config()?;
+        let plan = Finder::new(&config).plan().ok_or("no plan")?;
+        let tree = BtNode::from_plan(&config, &plan);
+
+        let mut performed = vec![];
+        let mut state = config.worldState.clone();
+        let status = BtExecutor::new(&tree).tick(&mut state, |name, state| {
+            performed.push(name.to_string());
+            // the weapon is never found
+            if name != "search Weapon" {
+                config.actions[name].update_with_post_conditions(state);
+            }
+            BtStatus::Success
+        });
+
+        assert_eq!(status, BtStatus::Failure);
+        assert_eq!(performed.last().map(|a| a.as_str()), Some("search Weapon"));
+        Ok(())
+    }
+
+    #[test]
+    fn resumes_running_action() -> Result<(), String> {
+        let tree: BtNode = serde_json::from_str(
+            r#"{ "type": "sequence", "children": [
+                { "type": "action", "name": "walk" },
+                { "type": "action", "name": "open door" }
+            ] }"#,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let mut calls: Vec<String> = vec![];
+        let mut perform = |name: &str, _: &mut WorldState| {
+            calls.push(name.to_string());
+            if calls.iter().filter(|c| *c == "walk").count() < 3 {
+                BtStatus::Running
+            } else {
+                BtStatus::Success
+            }
+        };
+
+        let mut state = WorldState::new();
+        let mut executor = BtExecutor::new(&tree);
+        assert_eq!(executor.tick(&mut state, &mut perform), BtStatus::Running);
+        assert_eq!(
+            executor.run(&mut state, &mut perform, 10),
+            BtStatus::Success
+        );
+        assert_eq!(calls, vec!["walk", "walk", "walk", "open door"]);
+        Ok(())
+    }
+
+    #[test]
+    fn retries_contingent_plan() -> Result<(), String> {
+        let config: PlanConfig = serde_json::from_str(
+            r#"{
+                "actions": {
+                    "pickup Ammo": {
+                        "cost": 1,
+                        "pre_state": { "hasAmmo": false },
+                        "post_state": { "hasAmmo": true },
+                        "outcomes": [
+                            { "probability": 0.8, "post_state": { "hasAmmo": true } },
+                            { "probability": 0.2, "post_state": {} }
+                        ]
+                    }
+                },
+                "goals": [{ "name": "armed", "state": { "hasAmmo": true } }],
+                "worldState": { "hasAmmo": false }
+            }"#,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let plan = ProbabilisticPlanner::new(&config).plan().ok_or("no plan")?;
+        let tree = BtNode::from_contingent(&config, &plan.plan);
+        assert!(matches!(tree, BtNode::Retry { .. }), "{:?}", tree);
+
+        let mut attempts = 0;
+        let mut state = config.worldState.clone();
+        let status = BtExecutor::new(&tree).tick(&mut state, |_, state| {
+            attempts += 1;
+            // the first two pickups fail
+            if attempts == 3 {
+                state.insert("hasAmmo".to_string(), true);
+            }
+            BtStatus::Success
+        });
+
+        assert_eq!(status, BtStatus::Success);
+        assert_eq!(attempts, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn checks_outcome_which_changes_nothing_last() -> Result<(), String> {
+        let config: PlanConfig = serde_json::from_str(
+            r#"{
+                "actions": {
+                    "pickup Ammo": {
+                        "cost": 1,
+                        "pre_state": { "hasAmmo": false },
+                        "post_state": { "hasAmmo": true },
+                        "outcomes": [
+                            { "probability": 0.2, "post_state": {} },
+                            { "probability": 0.8, "post_state": { "hasAmmo": true } }
+                        ]
+                    }
+                },
+                "goals": [{ "name": "armed", "state": { "hasAmmo": true } }],
+                "worldState": { "hasAmmo": false }
+            }"#,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let plan = ProbabilisticPlanner::new(&config).plan().ok_or("no plan")?;
+        let tree = BtNode::from_contingent(&config, &plan.plan);
+
+        let mut attempts = 0;
+        let mut state = config.worldState.clone();
+        let status = BtExecutor::new(&tree).tick(&mut state, |_, state| {
+            attempts += 1;
+            // the first pickup fails
+            if attempts == 2 {
+                state.insert("hasAmmo".to_string(), true);
+            }
+            BtStatus::Success
+        });
+
+        assert_eq!(status, BtStatus::Success);
+        assert_eq!(attempts, 2);
+        Ok(())
+    }
+}
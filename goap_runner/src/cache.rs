@@ -0,0 +1,287 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+use crate::configs::{GoalMode, PlanConfig};
+use crate::plan::PlanResult;
+use crate::Finder;
+
+type StateKey = Vec<(String, bool)>;
+
+fn sorted_state(state: &HashMap<String, bool>) -> StateKey {
+    let mut pairs: StateKey = state.iter().map(|(k, v)| (k.clone(), *v)).collect();
+    pairs.sort();
+    pairs
+}
+
+impl PlanConfig {
+    /// Fingerprint of the action set, changes whenever an action is added,
+    /// removed or has its cost or conditions edited
+    pub fn actions_version(&self) -> u64 {
+        let mut names: Vec<&String> = self.actions.keys().collect();
+        names.sort();
+
+        let mut hasher = DefaultHasher::new();
+        for name in names {
+            let action = &self.actions[name];
+            name.hash(&mut hasher);
+            action.cost.hash(&mut hasher);
+            sorted_state(&action.pre_state).hash(&mut hasher);
+            sorted_state(&action.post_state).hash(&mut hasher);
+            action.duration.hash(&mut hasher);
+            action.resources.hash(&mut hasher);
+            for outcome in &action.outcomes {
+                outcome.probability.to_bits().hash(&mut hasher);
+                sorted_state(&outcome.post_state).hash(&mut hasher);
+            }
+            let mut costs: Vec<(&String, &usize)> = action.costs.iter().collect();
+            costs.sort();
+            costs.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PlanKey {
+    world_state: StateKey,
+    goals: Vec<(String, StateKey)>,
+    goal_mode: GoalMode,
+    actions_version: u64,
+}
+
+impl PlanKey {
+    fn new(config: &PlanConfig) -> Self {
+        PlanKey {
+            world_state: sorted_state(&config.worldState),
+            goals: config
+                .goals
+                .iter()
+                .map(|g| (g.name.clone(), sorted_state(&g.state)))
+                .collect(),
+            goal_mode: config.goal_mode,
+            actions_version: config.actions_version(),
+        }
+    }
+}
+
+struct CacheEntry {
+    plan: Option<PlanResult>,
+    last_used: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Memoizes `Finder::plan` results, least recently used plans are evicted first.
+/// Keys include `PlanConfig::actions_version` of the config being planned, so
+/// editing actions never returns a stale plan. Fingerprinting rehashes every
+/// action on each call, which is still far cheaper than a plan.
+pub struct PlanCache {
+    capacity: usize,
+    entries: HashMap<PlanKey, CacheEntry>,
+    /// last use tick -> key, oldest first
+    usage: BTreeMap<u64, PlanKey>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl PlanCache {
+    pub fn new(capacity: usize) -> Self {
+        PlanCache {
+            capacity: capacity.max(1),
+            entries: HashMap::with_capacity(capacity),
+            usage: BTreeMap::new(),
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Cached plan for the config world state and goals, planned on a miss
+    pub fn plan(&mut self, config: &PlanConfig) -> Option<PlanResult> {
+        let key = PlanKey::new(config);
+        self.tick += 1;
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.stats.hits += 1;
+            self.usage.remove(&entry.last_used);
+            entry.last_used = self.tick;
+            self.usage.insert(self.tick, key);
+            return entry.plan.clone();
+        }
+
+        self.stats.misses += 1;
+        let plan = Finder::new(config).plan();
+
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.usage.pop_first() {
+                self.entries.remove(&oldest);
+                self.stats.evictions += 1;
+            }
+        }
+
+        self.usage.insert(self.tick, key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                plan: plan.clone(),
+                last_used: self.tick,
+            },
+        );
+
+        plan
+    }
+
+    /// Drops every plan made with the given action set, see `PlanConfig::actions_version`
+    pub fn invalidate_actions(&mut self, actions_version: u64) {
+        let usage = &mut self.usage;
+        self.entries.retain(|key, entry| {
+            let keep = key.actions_version != actions_version;
+            if !keep {
+                usage.remove(&entry.last_used);
+            }
+            keep
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.usage.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration::load_versioned;
+
+    fn sample_config() -> Result<PlanConfig, String> {
+        let contents = std::fs::read_to_string("./actions.json").map_err(|e| e.to_string())?;
+        load_versioned(&contents).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn counts_hits_and_misses() -> Result<(), String> {
+        let mut config = sample_config()?;
+        let mut cache = PlanCache::new(4);
+
+        let planned = cache.plan(&config);
+        let cached = cache.plan(&config);
+        assert_eq!(planned, cached);
+        assert_eq!(planned.map(|p| p.cost), Some(8));
+
+        config.worldState.insert("hasWeapon".to_string(), true);
+        cache.plan(&config);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+        assert_eq!(cache.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn evicts_least_recently_used() -> Result<(), String> {
+        let mut config = sample_config()?;
+        let mut cache = PlanCache::new(2);
+
+        let first = config.worldState.clone();
+        cache.plan(&config);
+        config.worldState.insert("hasWeapon".to_string(), true);
+        let second = config.worldState.clone();
+        cache.plan(&config);
+
+        // touch first, so second is the oldest one
+        config.worldState = first.clone();
+        cache.plan(&config);
+
+        config.worldState.insert("injured".to_string(), true);
+        cache.plan(&config);
+        assert_eq!(cache.stats().evictions, 1);
+
+        config.worldState = first;
+        cache.plan(&config);
+        assert_eq!(cache.stats().hits, 2);
+
+        config.worldState = second;
+        cache.plan(&config);
+        assert_eq!(cache.stats().misses, 4);
+        assert_eq!(cache.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn changed_actions_miss_the_cache() -> Result<(), String> {
+        let mut config = sample_config()?;
+        let mut cache = PlanCache::new(4);
+
+        let old_version = config.actions_version();
+        assert_eq!(cache.plan(&config).map(|p| p.cost), Some(8));
+
+        config
+            .actions
+            .get_mut("Change mood")
+            .ok_or("missing action")?
+            .cost = 1;
+        assert_ne!(config.actions_version(), old_version);
+        assert_eq!(
+            cache.plan(&config).map(|p| p.actions),
+            Some(vec!["Change mood".to_string()])
+        );
+        assert_eq!(cache.stats().misses, 2);
+
+        cache.invalidate_actions(old_version);
+        assert_eq!(cache.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn plans_with_the_actions_of_the_given_config() -> Result<(), String> {
+        let config = sample_config()?;
+        let mut cache = PlanCache::new(4);
+        assert_eq!(cache.plan(&config).map(|p| p.cost), Some(8));
+
+        // same world state and goals, but a different action set
+        let mut edited = sample_config()?;
+        edited
+            .actions
+            .get_mut("Change mood")
+            .ok_or("missing action")?
+            .cost = 1;
+        assert_eq!(
+            cache.plan(&edited).map(|p| p.actions),
+            Some(vec!["Change mood".to_string()])
+        );
+        assert_eq!(cache.plan(&config).map(|p| p.cost), Some(8));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+        Ok(())
+    }
+}
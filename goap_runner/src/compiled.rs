@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use std::thread;
+
+use crate::configs::{GoalMode, PlanConfig, WorldState, MAX_PLAN_COST};
+use crate::plan::{goal_progress, goal_segments, goal_target, start_progress, PlanResult};
+
+/// Facts packed into bits, `known` marks facts which have a value at all
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Bits {
+    known: Vec<u64>,
+    values: Vec<u64>,
+}
+
+impl Bits {
+    fn empty(words: usize) -> Self {
+        Bits {
+            known: vec![0; words],
+            values: vec![0; words],
+        }
+    }
+
+    fn set(&mut self, fact: usize, value: bool) {
+        let (word, bit) = (fact / 64, 1u64 << (fact % 64));
+        self.known[word] |= bit;
+        if value {
+            self.values[word] |= bit;
+        } else {
+            self.values[word] &= !bit;
+        }
+    }
+
+    /// same as `hasmap_contains(self, condition)`
+    fn contains(&self, condition: &Bits) -> bool {
+        (0..self.known.len()).all(|w| {
+            let mask = condition.known[w];
+            self.known[w] & mask == mask && self.values[w] & mask == condition.values[w]
+        })
+    }
+
+    /// same as `Gaction::update_with_post_conditions`
+    fn apply(&mut self, post: &Bits) {
+        for w in 0..self.known.len() {
+            let mask = post.known[w];
+            self.known[w] |= mask;
+            self.values[w] = (self.values[w] & !mask) | post.values[w];
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CompiledAction {
+    name: String,
+    cost: usize,
+    pre_state: Bits,
+    post_state: Bits,
+}
+
+/// Immutable, indexed form of a `PlanConfig` action set and goals.
+/// It is built once and can plan from many world states, also from several threads.
+#[derive(Debug)]
+pub struct CompiledConfig {
+    fact_index: HashMap<String, usize>,
+    words: usize,
+    /// sorted by name, like `Finder` tries them
+    actions: Vec<CompiledAction>,
+    goal_names: Vec<String>,
+    goals: Vec<Bits>,
+    goal_mode: GoalMode,
+}
+
+impl CompiledConfig {
+    pub fn new(config: &PlanConfig) -> Self {
+        let mut fact_index: HashMap<String, usize> = HashMap::new();
+        let mut facts = |state: &HashMap<String, bool>| {
+            let mut keys: Vec<&String> = state.keys().collect();
+            keys.sort();
+            for key in keys {
+                let next = fact_index.len();
+                fact_index.entry(key.clone()).or_insert(next);
+            }
+        };
+
+        let mut names: Vec<&String> = config.actions.keys().collect();
+        names.sort();
+
+        for name in &names {
+            facts(&config.actions[*name].pre_state);
+            facts(&config.actions[*name].post_state);
+        }
+        for goal in &config.goals {
+            facts(&goal.state);
+        }
+
+        let words = fact_index.len().div_ceil(64);
+        let compile = |state: &HashMap<String, bool>| {
+            let mut bits = Bits::empty(words);
+            for (fact, value) in state {
+                if let Some(&index) = fact_index.get(fact) {
+                    bits.set(index, *value);
+                }
+            }
+            bits
+        };
+
+        let actions = names
+            .iter()
+            .map(|name| {
+                let action = &config.actions[*name];
+                CompiledAction {
+                    name: name.to_string(),
+                    cost: action.cost,
+                    pre_state: compile(&action.pre_state),
+                    post_state: compile(&action.post_state),
+                }
+            })
+            .collect();
+
+        CompiledConfig {
+            goal_names: config.goals.iter().map(|g| g.name.clone()).collect(),
+            goals: config.goals.iter().map(|g| compile(&g.state)).collect(),
+            goal_mode: config.goal_mode,
+            actions,
+            fact_index,
+            words,
+        }
+    }
+
+    /// Facts unknown to actions and goals can not change a plan and are dropped
+    fn compile_state(&self, state: &WorldState) -> Bits {
+        let mut bits = Bits::empty(self.words);
+        for (fact, value) in state {
+            if let Some(&index) = self.fact_index.get(fact) {
+                bits.set(index, *value);
+            }
+        }
+        bits
+    }
+
+    /// Cheapest plan from `world_state`, same result as `Finder::plan`
+    pub fn plan(&self, world_state: &WorldState) -> Option<PlanResult> {
+        Search::new(self).plan(world_state)
+    }
+}
+
+struct SearchNode {
+    action: Option<usize>,
+    from_node: Option<usize>,
+    state: Bits,
+    running_cost: usize,
+    goal_progress: usize,
+}
+
+struct SearchFrame {
+    node_index: usize,
+    next: usize,
+    /// actions already on the path to this node, one bit per action
+    used: Vec<u64>,
+}
+
+/// Search buffers which are reused between plans of one thread
+struct Search<'c> {
+    compiled: &'c CompiledConfig,
+    nodes: Vec<SearchNode>,
+    stack: Vec<SearchFrame>,
+}
+
+impl<'c> Search<'c> {
+    fn new(compiled: &'c CompiledConfig) -> Self {
+        Search {
+            compiled,
+            nodes: Vec::with_capacity(300),
+            stack: Vec::with_capacity(compiled.actions.len()),
+        }
+    }
+
+    fn plan(&mut self, world_state: &WorldState) -> Option<PlanResult> {
+        let compiled = self.compiled;
+        let target = goal_target(compiled.goal_mode, compiled.goals.len());
+        let start = compiled.compile_state(world_state);
+
+        self.nodes.clear();
+        self.stack.clear();
+        self.nodes.push(SearchNode {
+            action: None,
+            from_node: None,
+            goal_progress: start_progress(compiled.goal_mode, compiled.goals.len(), |g| {
+                start.contains(&compiled.goals[g])
+            }),
+            state: start,
+            running_cost: 0,
+        });
+
+        if self.nodes[0].goal_progress >= target {
+            return Some(self.plan_result(0));
+        }
+
+        let mut cheapest = MAX_PLAN_COST;
+        let mut leaf: Option<usize> = None;
+        self.stack.push(SearchFrame {
+            node_index: 0,
+            next: 0,
+            used: vec![0; compiled.actions.len().div_ceil(64)],
+        });
+
+        while let Some(frame) = self.stack.last_mut() {
+            let action_index = frame.next;
+            if action_index >= compiled.actions.len() {
+                self.stack.pop();
+                continue;
+            }
+            frame.next += 1;
+
+            let (word, bit) = (action_index / 64, 1u64 << (action_index % 64));
+            if frame.used[word] & bit != 0 {
+                continue;
+            }
+
+            let action = &compiled.actions[action_index];
+            let start_node_index = frame.node_index;
+            let start_node = &self.nodes[start_node_index];
+
+            if !start_node.state.contains(&action.pre_state) {
+                continue;
+            }
+
+            let cost = start_node.running_cost + action.cost;
+            if cost > cheapest {
+                continue;
+            }
+
+            let mut next_state = start_node.state.clone();
+            next_state.apply(&action.post_state);
+            let progress = goal_progress(
+                compiled.goal_mode,
+                compiled.goals.len(),
+                start_node.goal_progress,
+                |g| next_state.contains(&compiled.goals[g]),
+            );
+
+            let mut used = frame.used.clone();
+            used[word] |= bit;
+
+            self.nodes.push(SearchNode {
+                action: Some(action_index),
+                from_node: Some(start_node_index),
+                state: next_state,
+                running_cost: cost,
+                goal_progress: progress,
+            });
+            let next_node_index = self.nodes.len() - 1;
+
+            if progress >= target {
+                if cheapest > cost {
+                    cheapest = cost;
+                    leaf = Some(next_node_index);
+                }
+            } else {
+                self.stack.push(SearchFrame {
+                    node_index: next_node_index,
+                    next: 0,
+                    used,
+                });
+            }
+        }
+
+        leaf.map(|leaf_index| self.plan_result(leaf_index))
+    }
+
+    fn plan_result(&self, leaf_index: usize) -> PlanResult {
+        let mut path: Vec<&SearchNode> = Vec::with_capacity(32);
+        let mut idx_option = Some(leaf_index);
+        while let Some(idx) = idx_option {
+            path.push(&self.nodes[idx]);
+            idx_option = self.nodes[idx].from_node;
+        }
+        path.reverse();
+
+        let compiled = self.compiled;
+        let names: Vec<&str> = compiled.goal_names.iter().map(|n| n.as_str()).collect();
+        let progress: Vec<usize> = path.iter().map(|n| n.goal_progress).collect();
+
+        PlanResult {
+            actions: path
+                .iter()
+                .filter_map(|n| n.action)
+                .map(|a| compiled.actions[a].name.clone())
+                .collect(),
+            cost: self.nodes[leaf_index].running_cost,
+            segments: goal_segments(compiled.goal_mode, &names, &progress, |g, step| {
+                path[step].state.contains(&compiled.goals[g])
+            }),
+        }
+    }
+}
+
+/// Plans for every world state on all available cores, see `plan_batch_with_threads`
+pub fn plan_batch(compiled: &CompiledConfig, states: &[WorldState]) -> Vec<Option<PlanResult>> {
+    let threads = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    plan_batch_with_threads(compiled, states, threads)
+}
+
+/// Plans for every world state, `None` where no plan exists. States are split
+/// into one chunk per thread and each thread reuses its search buffers.
+pub fn plan_batch_with_threads(
+    compiled: &CompiledConfig,
+    states: &[WorldState],
+    threads: usize,
+) -> Vec<Option<PlanResult>> {
+    let mut results: Vec<Option<PlanResult>> = vec![None; states.len()];
+    if states.is_empty() {
+        return results;
+    }
+
+    let threads = threads.clamp(1, states.len());
+    let chunk_size = states.len().div_ceil(threads);
+
+    if threads == 1 {
+        let mut search = Search::new(compiled);
+        for (state, result) in states.iter().zip(results.iter_mut()) {
+            *result = search.plan(state);
+        }
+        return results;
+    }
+
+    thread::scope(|scope| {
+        for (state_chunk, result_chunk) in states
+            .chunks(chunk_size)
+            .zip(results.chunks_mut(chunk_size))
+        {
+            scope.spawn(move || {
+                let mut search = Search::new(compiled);
+                for (state, result) in state_chunk.iter().zip(result_chunk.iter_mut()) {
+                    *result = search.plan(state);
+                }
+            });
+        }
+    });
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration::load_versioned;
+    use crate::Finder;
+
+    fn sample_config(goal_mode: GoalMode) -> Result<PlanConfig, String> {
+        let contents = std::fs::read_to_string("./actions.json").map_err(|e| e.to_string())?;
+        let mut config: PlanConfig = load_versioned(&contents).map_err(|e| e.to_string())?;
+        config.goal_mode = goal_mode;
+        Ok(config)
+    }
+
+    #[test]
+    fn compiled_plan_matches_finder() -> Result<(), String> {
+        for goal_mode in [GoalMode::Any, GoalMode::All, GoalMode::Sequence] {
+            let config = sample_config(goal_mode)?;
+            let compiled = CompiledConfig::new(&config);
+
+            assert_eq!(
+                compiled.plan(&config.worldState),
+                Finder::new(&config).plan()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn plans_batch_of_states() -> Result<(), String> {
+        let config = sample_config(GoalMode::Any)?;
+        let compiled = CompiledConfig::new(&config);
+
+        let mut armed = config.worldState.clone();
+        armed.insert("hasWeapon".to_string(), true);
+        armed.insert("hasAmmo".to_string(), true);
+
+        let mut stuck = config.worldState.clone();
+        stuck.insert("readyForPeace".to_string(), false);
+        stuck.insert("injured".to_string(), true);
+        stuck.insert("hasBomb".to_string(), true);
+
+        let states: Vec<WorldState> = (0..30)
+            .map(|i| match i % 3 {
+                0 => config.worldState.clone(),
+                1 => armed.clone(),
+                _ => stuck.clone(),
+            })
+            .collect();
+
+        let sequential = plan_batch_with_threads(&compiled, &states, 1);
+        let parallel = plan_batch_with_threads(&compiled, &states, 4);
+        assert_eq!(sequential, parallel);
+        assert_eq!(plan_batch(&compiled, &states), parallel);
+
+        let costs: Vec<Option<usize>> = parallel[..3]
+            .iter()
+            .map(|r| r.as_ref().map(|p| p.cost))
+            .collect();
+        assert_eq!(costs, vec![Some(8), Some(4), None]);
+        Ok(())
+    }
+}
@@ -1,6 +1,14 @@
 use std::collections::HashMap;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::htn::CompoundTask;
+use crate::migration::CURRENT_VERSION;
+
+/// upper bound for a plan's total cost, more expensive branches are never explored
+pub const MAX_PLAN_COST: usize = 10000;
+
+pub type WorldState = HashMap<String, bool>;
 
 pub fn hasmap_contains(
     prev_state: &HashMap<String, bool>,
@@ -25,13 +33,52 @@ pub struct Gnode {
     pub from_node: Option<usize>,
     pub state: HashMap<String, bool>,
     pub running_cost: usize,
+    /// how far along `PlanConfig.goals` this node is, see `PlanConfig::goal_progress`
+    pub goal_progress: usize,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Gaction {
     pub cost: usize,
     pub pre_state: HashMap<String, bool>,
     pub post_state: HashMap<String, bool>,
+    /// time units the action takes, used by `TemporalPlanner`
+    #[serde(default = "default_duration")]
+    pub duration: usize,
+    /// exclusive resources held while the action runs, e.g. "hands"
+    #[serde(default)]
+    pub resources: Vec<String>,
+    /// possible results with their probability, used by `ProbabilisticPlanner`.
+    /// Other planners assume `post_state` always happens.
+    #[serde(default)]
+    pub outcomes: Vec<Outcome>,
+    /// extra named costs such as "time", "risk" or "noise", ranked with `Finder::plan_ranked`
+    #[serde(default)]
+    pub costs: HashMap<String, usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Outcome {
+    pub probability: f64,
+    pub post_state: HashMap<String, bool>,
+}
+
+fn default_duration() -> usize {
+    1
+}
+
+impl Default for Gaction {
+    fn default() -> Self {
+        Gaction {
+            cost: 0,
+            pre_state: HashMap::new(),
+            post_state: HashMap::new(),
+            duration: default_duration(),
+            resources: vec![],
+            outcomes: vec![],
+            costs: HashMap::new(),
+        }
+    }
 }
 
 impl Gaction {
@@ -44,17 +91,68 @@ impl Gaction {
             prev_state.insert(state_key, value);
         }
     }
+
+    /// `outcomes`, or `post_state` as the only certain outcome when none are declared
+    pub fn possible_outcomes(&self) -> Vec<(f64, &HashMap<String, bool>)> {
+        if self.outcomes.is_empty() {
+            vec![(1.0, &self.post_state)]
+        } else {
+            self.outcomes
+                .iter()
+                .map(|o| (o.probability, &o.post_state))
+                .collect()
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Goal {
     pub name: String,
     pub state: HashMap<String, bool>,
 }
 
-#[derive(Debug, Deserialize)]
+/// How `PlanConfig.goals` combine into the condition a plan has to reach
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalMode {
+    /// any single goal is enough
+    #[default]
+    Any,
+    /// all goals have to hold at the same time at the end of the plan
+    All,
+    /// goals are reached one after another, in the listed order
+    Sequence,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct PlanConfig {
+    /// format version, see `migrate`. Load files with `load_versioned` so
+    /// older versions are upgraded first.
+    #[serde(default = "current_version")]
+    pub version: u64,
     pub actions: HashMap<String, Gaction>,
     pub goals: Vec<Goal>,
     pub worldState: HashMap<String, bool>,
+    #[serde(default)]
+    pub goal_mode: GoalMode,
+    /// compound tasks for `HtnPlanner`, built from `actions` and goals
+    #[serde(default)]
+    pub tasks: HashMap<String, CompoundTask>,
+}
+
+fn current_version() -> u64 {
+    CURRENT_VERSION
+}
+
+impl Default for PlanConfig {
+    fn default() -> Self {
+        PlanConfig {
+            version: CURRENT_VERSION,
+            actions: HashMap::new(),
+            goals: vec![],
+            worldState: HashMap::new(),
+            goal_mode: GoalMode::default(),
+            tasks: HashMap::new(),
+        }
+    }
 }
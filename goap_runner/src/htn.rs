@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::configs::{hasmap_contains, Goal, GoalMode, PlanConfig, WorldState};
+use crate::Finder;
+
+/// decomposition deeper than this is treated as a task recursing into itself
+pub const MAX_HTN_DEPTH: usize = 64;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Subtask {
+    /// primitive `Gaction` from `PlanConfig.actions`
+    Action(String),
+    /// another compound task from `PlanConfig.tasks`
+    Task(String),
+    /// state to reach, planned with `Finder` over `PlanConfig.actions`
+    Goal(HashMap<String, bool>),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Method {
+    pub name: String,
+    #[serde(default)]
+    pub pre_state: HashMap<String, bool>,
+    pub subtasks: Vec<Subtask>,
+}
+
+/// Task with methods tried in order, the first one which fully decomposes,
+/// along with the subtasks after the task, is used
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CompoundTask {
+    pub methods: Vec<Method>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DecompositionNode {
+    Task {
+        name: String,
+        method: String,
+        children: Vec<DecompositionNode>,
+    },
+    Action {
+        name: String,
+    },
+    Goal {
+        state: HashMap<String, bool>,
+        actions: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HtnPlan {
+    /// primitive actions in execution order
+    pub actions: Vec<String>,
+    pub cost: usize,
+    pub tree: DecompositionNode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HtnError {
+    UnknownTask(String),
+    UnknownAction(String),
+    DepthExceeded(String),
+    NoDecomposition(String),
+}
+
+impl fmt::Display for HtnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HtnError::UnknownTask(name) => write!(f, "unknown task \"{}\"", name),
+            HtnError::UnknownAction(name) => write!(f, "unknown action \"{}\"", name),
+            HtnError::DepthExceeded(name) => write!(
+                f,
+                "task \"{}\" is nested deeper than {}, is it recursive?",
+                name, MAX_HTN_DEPTH
+            ),
+            HtnError::NoDecomposition(name) => {
+                write!(f, "no method of task \"{}\" applies", name)
+            }
+        }
+    }
+}
+
+/// Subtasks of a method still to decompose, with the nodes of those done
+#[derive(Clone)]
+struct Level<'t> {
+    /// task and method name, `None` for the root task itself
+    task: Option<(&'t str, &'t str)>,
+    rest: &'t [Subtask],
+    children: Vec<DecompositionNode>,
+}
+
+/// Decomposition so far, the last level is the innermost method
+#[derive(Clone)]
+struct Partial<'t> {
+    state: WorldState,
+    actions: Vec<String>,
+    cost: usize,
+    levels: Vec<Level<'t>>,
+}
+
+/// Total order HTN planner. Compound tasks are decomposed depth first,
+/// GOAP goal subtasks are planned from the state reached at that point.
+/// When a subtask fails the next applicable method of the innermost task
+/// is tried, then those of the tasks around it.
+pub struct HtnPlanner<'a> {
+    config: &'a PlanConfig,
+}
+
+impl<'a> HtnPlanner<'a> {
+    pub fn new(config: &'a PlanConfig) -> Self {
+        HtnPlanner { config }
+    }
+
+    /// Decomposes `root_task` starting from the config world state
+    pub fn plan(&self, root_task: &str) -> Result<HtnPlan, HtnError> {
+        let root = [Subtask::Task(root_task.to_string())];
+        let start = Partial {
+            state: self.config.worldState.clone(),
+            actions: vec![],
+            cost: 0,
+            levels: vec![Level {
+                task: None,
+                rest: &root,
+                children: vec![],
+            }],
+        };
+        // goal subtasks are planned with these actions, cloned once per plan
+        let mut goal_config = PlanConfig {
+            actions: self.config.actions.clone(),
+            // a single goal which already holds needs no actions
+            goal_mode: GoalMode::All,
+            ..Default::default()
+        };
+
+        match self.decompose(start, &mut goal_config)? {
+            Some(mut done) => Ok(HtnPlan {
+                actions: done.actions,
+                cost: done.cost,
+                tree: done.levels.remove(0).children.remove(0),
+            }),
+            None => Err(HtnError::NoDecomposition(root_task.to_string())),
+        }
+    }
+
+    /// Decomposes the remaining subtasks of every level, `None` when no
+    /// choice of methods gets through all of them
+    fn decompose<'t>(
+        &'t self,
+        mut partial: Partial<'t>,
+        goal_config: &mut PlanConfig,
+    ) -> Result<Option<Partial<'t>>, HtnError> {
+        loop {
+            let depth = partial.levels.len() - 1;
+            let level = &mut partial.levels[depth];
+            let (subtask, rest) = match level.rest.split_first() {
+                Some(next) => next,
+                None if depth == 0 => return Ok(Some(partial)),
+                None => {
+                    let level = partial.levels.remove(depth);
+                    if let Some((name, method)) = level.task {
+                        partial.levels[depth - 1]
+                            .children
+                            .push(DecompositionNode::Task {
+                                name: name.to_string(),
+                                method: method.to_string(),
+                                children: level.children,
+                            });
+                    }
+                    continue;
+                }
+            };
+            level.rest = rest;
+
+            match subtask {
+                Subtask::Action(name) => {
+                    let action = self
+                        .config
+                        .actions
+                        .get(name)
+                        .ok_or_else(|| HtnError::UnknownAction(name.clone()))?;
+
+                    if !action.are_preconditions_met(&partial.state) {
+                        return Ok(None);
+                    }
+
+                    action.update_with_post_conditions(&mut partial.state);
+                    partial.actions.push(name.clone());
+                    partial.cost += action.cost;
+                    partial.levels[depth]
+                        .children
+                        .push(DecompositionNode::Action { name: name.clone() });
+                }
+                Subtask::Task(name) => return self.decompose_task(name, partial, goal_config),
+                Subtask::Goal(goal_state) => {
+                    goal_config.goals = vec![Goal {
+                        name: "subtask".to_string(),
+                        state: goal_state.clone(),
+                    }];
+                    goal_config.worldState = partial.state.clone();
+
+                    let plan = match Finder::new(goal_config).plan() {
+                        Some(plan) => plan,
+                        None => return Ok(None),
+                    };
+
+                    for name in &plan.actions {
+                        self.config.actions[name].update_with_post_conditions(&mut partial.state);
+                    }
+                    partial.actions.extend(plan.actions.iter().cloned());
+                    partial.cost += plan.cost;
+                    partial.levels[depth]
+                        .children
+                        .push(DecompositionNode::Goal {
+                            state: goal_state.clone(),
+                            actions: plan.actions,
+                        });
+                }
+            }
+        }
+    }
+
+    /// Tries the applicable methods of task `name` in order, each followed by
+    /// the subtasks left after it
+    fn decompose_task<'t>(
+        &'t self,
+        name: &'t str,
+        partial: Partial<'t>,
+        goal_config: &mut PlanConfig,
+    ) -> Result<Option<Partial<'t>>, HtnError> {
+        if partial.levels.len() > MAX_HTN_DEPTH + 1 {
+            return Err(HtnError::DepthExceeded(name.to_string()));
+        }
+
+        let task = self
+            .config
+            .tasks
+            .get(name)
+            .ok_or_else(|| HtnError::UnknownTask(name.to_string()))?;
+
+        for method in &task.methods {
+            if !hasmap_contains(&partial.state, &method.pre_state) {
+                continue;
+            }
+
+            let mut branch = partial.clone();
+            branch.levels.push(Level {
+                task: Some((name, &method.name)),
+                rest: &method.subtasks,
+                children: Vec::with_capacity(method.subtasks.len()),
+            });
+            if let Some(done) = self.decompose(branch, goal_config)? {
+                return Ok(Some(done));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration::load_versioned;
+
+    fn sample_config() -> Result<PlanConfig, String> {
+        let contents = std::fs::read_to_string("./actions.json").map_err(|e| e.to_string())?;
+        let mut config: PlanConfig = load_versioned(&contents).map_err(|e| e.to_string())?;
+
+        config.tasks = serde_json::from_str(
+            r#"{
+                "Win the day": {
+                    "methods": [
+                        {
+                            "name": "peace first",
+                            "pre_state": { "injured": true },
+                            "subtasks": [{ "action": "Change mood" }]
+                        },
+                        {
+                            "name": "fight then rest",
+                            "subtasks": [
+                                { "task": "Arm up" },
+                                { "goal": { "enemyAlive": false } },
+                                { "action": "Change mood" }
+                            ]
+                        }
+                    ]
+                },
+                "Arm up": {
+                    "methods": [
+                        {
+                            "name": "already armed",
+                            "pre_state": { "hasWeapon": true },
+                            "subtasks": []
+                        },
+                        {
+                            "name": "find one",
+                            "subtasks": [{ "action": "search Weapon" }, { "action": "pickupWeapon" }]
+                        }
+                    ]
+                },
+                "Loop": {
+                    "methods": [{ "name": "again", "subtasks": [{ "task": "Loop" }] }]
+                }
+            }"#,
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(config)
+    }
+
+    #[test]
+    fn decomposes_tasks_and_goals() -> Result<(), String> {
+        let config = sample_config()?;
+        let plan = HtnPlanner::new(&config)
+            .plan("Win the day")
+            .map_err(|e| e.to_string())?;
+
+        assert_eq!(plan.actions[..2], ["search Weapon", "pickupWeapon"]);
+        assert_eq!(plan.actions.last().map(|a| a.as_str()), Some("Change mood"));
+        assert_eq!(plan.cost, 18);
+
+        match &plan.tree {
+            DecompositionNode::Task {
+                method, children, ..
+            } => {
+                assert_eq!(method, "fight then rest");
+                assert_eq!(children.len(), 3);
+                match &children[1] {
+                    DecompositionNode::Goal { actions, .. } => assert_eq!(actions.len(), 5),
+                    other => return Err(format!("expected goal node, got {:?}", other)),
+                }
+            }
+            other => return Err(format!("expected task node, got {:?}", other)),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn picks_first_applicable_method() -> Result<(), String> {
+        let mut config = sample_config()?;
+        config.worldState.insert("injured".to_string(), true);
+
+        let plan = HtnPlanner::new(&config)
+            .plan("Win the day")
+            .map_err(|e| e.to_string())?;
+        assert_eq!(plan.actions, vec!["Change mood".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn backtracks_into_methods_of_nested_tasks() -> Result<(), String> {
+        let config: PlanConfig = serde_json::from_str(
+            r#"{
+                "actions": {
+                    "kick door": { "cost": 1, "pre_state": {}, "post_state": { "doorOpen": true, "noisy": true } },
+                    "grab key": { "cost": 1, "pre_state": {}, "post_state": { "hasKey": true } },
+                    "unlock door": { "cost": 1, "pre_state": { "hasKey": true }, "post_state": { "doorOpen": true } },
+                    "sneak in": { "cost": 1, "pre_state": { "doorOpen": true, "noisy": false }, "post_state": { "inside": true } }
+                },
+                "goals": [],
+                "worldState": { "doorOpen": false, "noisy": false, "hasKey": false, "inside": false },
+                "tasks": {
+                    "Enter": { "methods": [
+                        { "name": "quietly", "subtasks": [{ "task": "Open door" }, { "action": "sneak in" }] }
+                    ] },
+                    "Open door": { "methods": [
+                        { "name": "kick", "subtasks": [{ "action": "kick door" }] },
+                        { "name": "with key", "subtasks": [{ "goal": { "hasKey": true } }, { "action": "unlock door" }] }
+                    ] }
+                }
+            }"#,
+        )
+        .map_err(|e| e.to_string())?;
+
+        // kicking opens the door, but then sneaking in fails
+        let plan = HtnPlanner::new(&config)
+            .plan("Enter")
+            .map_err(|e| e.to_string())?;
+        assert_eq!(plan.actions, vec!["grab key", "unlock door", "sneak in"]);
+        assert_eq!(plan.cost, 3);
+
+        match &plan.tree {
+            DecompositionNode::Task { children, .. } => match &children[0] {
+                DecompositionNode::Task { method, .. } => assert_eq!(method, "with key"),
+                other => return Err(format!("expected task node, got {:?}", other)),
+            },
+            other => return Err(format!("expected task node, got {:?}", other)),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reports_broken_task_config() -> Result<(), String> {
+        let mut config = sample_config()?;
+
+        assert_eq!(
+            HtnPlanner::new(&config).plan("Missing").err(),
+            Some(HtnError::UnknownTask("Missing".to_string()))
+        );
+        assert_eq!(
+            HtnPlanner::new(&config).plan("Loop").err(),
+            Some(HtnError::DepthExceeded("Loop".to_string()))
+        );
+
+        config.worldState.insert("readyForPeace".to_string(), false);
+        assert_eq!(
+            HtnPlanner::new(&config).plan("Win the day").err(),
+            Some(HtnError::NoDecomposition("Win the day".to_string()))
+        );
+        Ok(())
+    }
+}
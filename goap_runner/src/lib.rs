@@ -1,115 +1,406 @@
+mod behaviour_tree;
+mod cache;
+mod compiled;
 mod configs;
-use configs::hasmap_contains;
+mod htn;
+mod library;
+mod migration;
+mod multi_agent;
+mod objectives;
+mod plan;
+mod probabilistic;
+mod repair;
+mod scenario;
+mod temporal;
+mod validation;
 use std::collections::HashMap;
 
-pub use configs::{Gaction, Gnode, Goal, PlanConfig};
+pub use behaviour_tree::{BtExecutor, BtNode, BtStatus, MAX_BT_RETRIES};
+pub use cache::{CacheStats, PlanCache};
+pub use compiled::{plan_batch, plan_batch_with_threads, CompiledConfig};
+pub use configs::{Gaction, Gnode, Goal, GoalMode, Outcome, PlanConfig, WorldState, MAX_PLAN_COST};
+pub use htn::{
+    CompoundTask, DecompositionNode, HtnError, HtnPlan, HtnPlanner, Method, Subtask, MAX_HTN_DEPTH,
+};
+pub use library::{load_config, DerivedAction, LibraryError, LibraryFile};
+pub use migration::{
+    detect_version, load_value, load_versioned, migrate, migrate_file, migrate_to, MigrationError,
+    CURRENT_VERSION,
+};
+pub use multi_agent::{
+    AgentConfig, AgentPlan, JointPlan, MultiAgentConfig, MultiAgentError, MultiAgentPlanner,
+    MAX_AGENT_ORDERS,
+};
+pub use objectives::{ObjectivePlan, Ranking, BASE_OBJECTIVE};
+pub use plan::{GoalSegment, PlanResult};
+pub use probabilistic::{
+    ContingentPlan, OutcomeBranch, ProbabilisticPlan, ProbabilisticPlanner, DEAD_END_COST,
+    MAX_POLICY_STATES,
+};
+pub use repair::{repair_plan, RepairKind, RepairedPlan, MAX_REPAIR_ACTIONS};
+pub use scenario::{LogEntry, Scenario, ScenarioAgent, ScenarioEvent, ScenarioLog, Simulator};
+pub use temporal::{
+    Schedule, ScheduledAction, TemporalPlanner, Timeline, TimelineEntry, TimelineTrack,
+};
+pub use validation::{
+    action_path, ConfigIssue, IssueKind, ListedAction, Severity, ValidationReport,
+};
 
 pub struct Finder<'a> {
     config: &'a PlanConfig,
+    /// action names, sorted so equally cheap plans are found in a stable order
+    action_names: Vec<&'a String>,
     nodes: Vec<Gnode>,
     cheapest: usize,
+    /// progress made towards the goals before the world state, see `from_progress`
+    start_progress: Option<usize>,
 }
 
 impl<'a> Finder<'a> {
     pub fn new(config: &'a PlanConfig) -> Self {
+        let mut action_names: Vec<&'a String> = config.actions.keys().collect();
+        action_names.sort();
+
         Finder {
             config,
+            action_names,
             nodes: Vec::with_capacity(300),
-            cheapest: 10000,
+            cheapest: MAX_PLAN_COST,
+            start_progress: None,
+        }
+    }
+
+    /// Plans on from `progress` already made towards the goals, so `Sequence`
+    /// goals reached earlier are not needed again
+    pub fn from_progress(mut self, progress: usize) -> Self {
+        self.start_progress = Some(progress);
+        self
+    }
+
+    fn start_progress(&self) -> usize {
+        match self.start_progress {
+            Some(progress) => self.config.goal_progress(&self.config.worldState, progress),
+            None => self.config.start_progress(),
         }
     }
     pub fn execute(&mut self) -> Vec<String> {
+        match self.plan() {
+            Some(result) => result.actions,
+            None => vec![],
+        }
+    }
+
+    /// Cheapest plan reaching the config goals, according to its `goal_mode`
+    pub fn plan(&mut self) -> Option<PlanResult> {
+        self.plans().last()
+    }
+
+    /// Restarts the search and yields a plan every time one cheaper than the
+    /// previous is found. The last plan yielded is the cheapest one.
+    pub fn plans(&mut self) -> Plans<'_, 'a> {
+        self.nodes.clear();
+        self.cheapest = MAX_PLAN_COST;
+
         let g = Gnode {
             id: String::from("start"),
             from_node: None,
             state: self.config.worldState.clone(),
             running_cost: 0,
+            goal_progress: self.start_progress(),
         };
 
         self.nodes.push(g);
 
-        let leaf = self.build_graph(0, &self.config.actions);
+        let mut stack: Vec<Frame<'a>> = vec![];
+        let mut done_at_start = false;
+
+        if self.nodes[0].goal_progress >= self.config.goal_target() {
+            done_at_start = true;
+        } else {
+            stack.push(Frame {
+                node_index: 0,
+                available: self.action_names.clone(),
+                next: 0,
+            });
+        }
+
+        Plans {
+            finder: self,
+            stack,
+            done_at_start,
+        }
+    }
 
-        let mut idx_option = leaf;
-        let mut leaves: Vec<String> = Vec::with_capacity(100);
+    fn plan_result(&self, leaf_index: usize) -> PlanResult {
+        let mut path: Vec<&Gnode> = Vec::with_capacity(100);
+        let mut idx_option = Some(leaf_index);
         while let Some(idx) = idx_option {
-            leaves.push(self.nodes[idx].id.clone());
+            path.push(&self.nodes[idx]);
             idx_option = self.nodes[idx].from_node;
         }
+        path.reverse();
 
-        leaves.pop(); // remove start
-        leaves.reverse();
+        let states: Vec<&HashMap<String, bool>> = path.iter().map(|n| &n.state).collect();
+        let progress: Vec<usize> = path.iter().map(|n| n.goal_progress).collect();
 
-        return leaves;
+        PlanResult {
+            // first node is start
+            actions: path[1..].iter().map(|n| n.id.clone()).collect(),
+            cost: self.nodes[leaf_index].running_cost,
+            segments: self.config.goal_segments(&states, &progress),
+        }
     }
+}
 
-    fn build_graph(
-        &mut self,
-        start_node_index: usize,
-        available_actions: &HashMap<String, Gaction>,
-    ) -> Option<usize> {
-        let start_node_option = self.nodes.get(start_node_index);
-        if start_node_option.is_none() {
-            return None;
-        }
+/// Node of the depth first search which still has actions left to try
+struct Frame<'a> {
+    node_index: usize,
+    available: Vec<&'a String>,
+    next: usize,
+}
 
-        let mut leaf: Option<usize> = None;
-        let start_node = start_node_option.unwrap();
+/// Anytime search over a `Finder`, see `Finder::plans`
+pub struct Plans<'f, 'a> {
+    finder: &'f mut Finder<'a>,
+    stack: Vec<Frame<'a>>,
+    done_at_start: bool,
+}
 
-        let next_state_base = start_node.state.clone();
-        let running_cost = start_node.running_cost;
+impl<'f, 'a> Iterator for Plans<'f, 'a> {
+    type Item = PlanResult;
 
-        for (key, action) in available_actions.into_iter() {
-            let has_preconditions = action.are_preconditions_met(&next_state_base);
+    fn next(&mut self) -> Option<PlanResult> {
+        if self.done_at_start {
+            // world state already is the goal, nothing can be cheaper than doing nothing
+            self.done_at_start = false;
+            self.finder.cheapest = 0;
+            return Some(self.finder.plan_result(0));
+        }
 
-            if has_preconditions {
-                let cost: usize = running_cost + action.cost;
+        let finder = &mut *self.finder;
+        let config = finder.config;
+        let target = config.goal_target();
 
-                if cost > self.cheapest {
-                    continue;
-                }
+        while let Some(frame) = self.stack.last_mut() {
+            if frame.next >= frame.available.len() {
+                self.stack.pop();
+                continue;
+            }
 
-                let mut next_state = next_state_base.clone();
-                action.update_with_post_conditions(&mut next_state);
+            let key = frame.available[frame.next];
+            frame.next += 1;
+            let start_node_index = frame.node_index;
 
-                let all_goals = &self.config.goals;
+            let action = &config.actions[key];
+            let start_node = &finder.nodes[start_node_index];
 
-                let matching_goal = all_goals
-                    .into_iter()
-                    .find(|&g| hasmap_contains(&next_state, &g.state));
+            if !action.are_preconditions_met(&start_node.state) {
+                continue;
+            }
 
-                let next_node: Gnode = Gnode {
-                    id: key.to_string(),
-                    from_node: Some(start_node_index),
-                    state: next_state,
-                    running_cost: cost,
-                };
+            let cost: usize = start_node.running_cost + action.cost;
 
-                self.nodes.push(next_node);
+            if cost > finder.cheapest {
+                continue;
+            }
 
-                if let Some(_g) = matching_goal {
-                    if self.cheapest > cost {
-                        let existing_size = self.nodes.len() - 1;
-                        leaf = Some(existing_size);
+            let mut next_state = start_node.state.clone();
+            action.update_with_post_conditions(&mut next_state);
 
-                        self.cheapest = cost;
-                    }
-                } else {
-                    let mut next_available_actions: HashMap<String, Gaction> =
-                        available_actions.clone();
+            let goal_progress = config.goal_progress(&next_state, start_node.goal_progress);
 
-                    next_available_actions.remove(key);
+            let next_available: Option<Vec<&'a String>> = if goal_progress >= target {
+                None
+            } else {
+                Some(
+                    frame
+                        .available
+                        .iter()
+                        .filter(|&&k| k != key)
+                        .copied()
+                        .collect(),
+                )
+            };
 
-                    let leaf_internal =
-                        self.build_graph(self.nodes.len() - 1, &next_available_actions);
+            finder.nodes.push(Gnode {
+                id: key.to_string(),
+                from_node: Some(start_node_index),
+                state: next_state,
+                running_cost: cost,
+                goal_progress,
+            });
+            let next_node_index = finder.nodes.len() - 1;
 
-                    if let Some(leaf_index) = leaf_internal {
-                        leaf = Some(leaf_index);
+            match next_available {
+                None => {
+                    if finder.cheapest > cost {
+                        finder.cheapest = cost;
+                        return Some(finder.plan_result(next_node_index));
                     }
                 }
+                Some(available) => self.stack.push(Frame {
+                    node_index: next_node_index,
+                    available,
+                    next: 0,
+                }),
             }
         }
 
-        return leaf;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_config(goal_mode: GoalMode) -> Result<PlanConfig, String> {
+        let contents = std::fs::read_to_string("./actions.json").map_err(|e| e.to_string())?;
+        let mut config: PlanConfig = load_versioned(&contents).map_err(|e| e.to_string())?;
+        config.goal_mode = goal_mode;
+        Ok(config)
+    }
+
+    fn segment(goal: &str, start: usize, end: usize) -> GoalSegment {
+        GoalSegment {
+            goal: goal.to_string(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn finds_cheapest_single_goal() -> Result<(), String> {
+        let config = sample_config(GoalMode::Any)?;
+        let result = Finder::new(&config).plan().ok_or("no plan")?;
+
+        assert_eq!(result.cost, 8);
+        assert_eq!(result.actions.len(), 7);
+        assert_eq!(result.segments, vec![segment("Kill the enemy", 0, 7)]);
+        Ok(())
+    }
+
+    #[test]
+    fn finds_plan_for_all_goals() -> Result<(), String> {
+        let config = sample_config(GoalMode::All)?;
+        let result = Finder::new(&config).plan().ok_or("no plan")?;
+
+        assert_eq!(result.cost, 18);
+        assert_eq!(result.actions.len(), 8);
+
+        let mood_step = result
+            .actions
+            .iter()
+            .position(|a| a == "Change mood")
+            .ok_or("peace is missing")?;
+        let attack_step = result
+            .actions
+            .iter()
+            .position(|a| a.starts_with("Attack"))
+            .ok_or("attack is missing")?;
+        assert_eq!(
+            result.segments,
+            vec![
+                segment("Kill the enemy", 0, attack_step + 1),
+                segment("Be peaceful", 0, mood_step + 1),
+            ]
+        );
+        Ok(())
+    }
+
+    fn state_after(config: &PlanConfig, actions: &[String]) -> HashMap<String, bool> {
+        let mut state = config.worldState.clone();
+        for name in actions {
+            config.actions[name].update_with_post_conditions(&mut state);
+        }
+        state
+    }
+
+    #[test]
+    fn finds_plan_for_goal_sequence() -> Result<(), String> {
+        let mut config = sample_config(GoalMode::Sequence)?;
+        let mut weapon_goal_state = HashMap::new();
+        weapon_goal_state.insert("hasWeapon".to_string(), true);
+        config.goals.insert(
+            0,
+            Goal {
+                name: "Get weapon".to_string(),
+                state: weapon_goal_state,
+            },
+        );
+        let result = Finder::new(&config).plan().ok_or("no plan")?;
+
+        assert_eq!(result.cost, 18);
+
+        let names: Vec<&str> = result.segments.iter().map(|s| s.goal.as_str()).collect();
+        assert_eq!(names, vec!["Get weapon", "Kill the enemy", "Be peaceful"]);
+
+        let mut start = 0;
+        for (segment, goal) in result.segments.iter().zip(config.goals.iter()) {
+            assert_eq!(segment.start, start);
+            let state = state_after(&config, &result.actions[..segment.end]);
+            assert!(configs::hasmap_contains(&state, &goal.state));
+            start = segment.end;
+        }
+        assert_eq!(start, result.actions.len());
+        Ok(())
+    }
+
+    #[test]
+    fn sequence_skips_goals_met_by_world_state() -> Result<(), String> {
+        let mut config = sample_config(GoalMode::Sequence)?;
+        config.worldState.insert("foundPeace".to_string(), true);
+        config.goals.reverse();
+        let result = Finder::new(&config).plan().ok_or("no plan")?;
+
+        assert_eq!(result.cost, 8);
+        assert_eq!(
+            result.segments,
+            vec![
+                segment("Be peaceful", 0, 0),
+                segment("Kill the enemy", 0, 7)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn execute_returns_action_names() -> Result<(), String> {
+        let config = sample_config(GoalMode::Any)?;
+        let actions = Finder::new(&config).execute();
+
+        assert_eq!(actions.len(), 7);
+        assert_eq!(actions[6], "Attack with Weapon");
+        Ok(())
+    }
+
+    #[test]
+    fn plans_get_cheaper_until_optimal() -> Result<(), String> {
+        let config = sample_config(GoalMode::Any)?;
+        let mut finder = Finder::new(&config);
+        let costs: Vec<usize> = finder.plans().map(|p| p.cost).collect();
+
+        assert!(costs.len() > 1);
+        assert!(costs.windows(2).all(|w| w[1] < w[0]));
+        assert_eq!(costs.last(), Some(&8));
+
+        // finder restarts the search on every call
+        let first = finder.plans().next().ok_or("no plan")?;
+        assert_eq!(first.cost, costs[0]);
+        assert_eq!(finder.plan().map(|p| p.cost), Some(8));
+        Ok(())
+    }
+
+    #[test]
+    fn plans_yield_empty_plan_for_reached_goals() -> Result<(), String> {
+        let mut config = sample_config(GoalMode::All)?;
+        config.worldState.insert("enemyAlive".to_string(), false);
+        config.worldState.insert("foundPeace".to_string(), true);
+
+        let plans: Vec<PlanResult> = Finder::new(&config).plans().collect();
+        assert_eq!(plans.len(), 1);
+        assert!(plans[0].actions.is_empty());
+        assert_eq!(plans[0].cost, 0);
+        Ok(())
     }
 }
@@ -0,0 +1,522 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::configs::{Gaction, Goal, GoalMode, PlanConfig, WorldState};
+use crate::htn::CompoundTask;
+use crate::migration::{migrate, CURRENT_VERSION};
+
+/// fields of a derived action which extend the base instead of replacing it
+const MERGED_FIELDS: [&str; 3] = ["pre_state", "post_state", "costs"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibraryError {
+    Io {
+        path: String,
+        message: String,
+    },
+    Parse {
+        path: String,
+        message: String,
+    },
+    /// files from the including one back to itself
+    CircularInclude {
+        chain: Vec<String>,
+    },
+    /// two different definitions of one action name
+    DuplicateAction {
+        name: String,
+        first: String,
+        second: String,
+    },
+    UnknownOverride {
+        name: String,
+        path: String,
+    },
+    UnknownBase {
+        name: String,
+        base: String,
+        path: String,
+    },
+    CircularDerive {
+        chain: Vec<String>,
+        path: String,
+    },
+    /// override or derived action setting a field `Gaction` does not have
+    UnknownField {
+        name: String,
+        field: String,
+        path: String,
+    },
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::Io { path, message } => write!(f, "can not read {}: {}", path, message),
+            LibraryError::Parse { path, message } => write!(f, "invalid {}: {}", path, message),
+            LibraryError::CircularInclude { chain } => {
+                write!(f, "circular include: {}", chain.join(" -> "))
+            }
+            LibraryError::DuplicateAction {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "action \"{}\" is defined in {} and {}, use \"overrides\" to change it",
+                name, first, second
+            ),
+            LibraryError::UnknownOverride { name, path } => {
+                write!(f, "{} overrides unknown action \"{}\"", path, name)
+            }
+            LibraryError::UnknownBase { name, base, path } => write!(
+                f,
+                "{} derives \"{}\" from unknown action \"{}\"",
+                path, name, base
+            ),
+            LibraryError::CircularDerive { chain, path } => {
+                write!(f, "{} derives in a circle: {}", path, chain.join(" -> "))
+            }
+            LibraryError::UnknownField { name, field, path } => write!(
+                f,
+                "{} sets unknown field \"{}\" of action \"{}\"",
+                path, field, name
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DerivedAction {
+    pub base: String,
+    /// `pre_state`, `post_state` and `costs` are added to the base ones,
+    /// other `Gaction` fields replace them
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+/// Config file which may pull actions from other files
+#[derive(Debug, Deserialize, Default)]
+pub struct LibraryFile {
+    /// paths relative to this file
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub actions: HashMap<String, Gaction>,
+    /// replaces the given fields of included actions
+    #[serde(default)]
+    pub overrides: HashMap<String, Map<String, Value>>,
+    #[serde(default)]
+    pub derived: HashMap<String, DerivedAction>,
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+    #[serde(default, rename = "worldState")]
+    pub world_state: WorldState,
+    #[serde(default)]
+    pub goal_mode: GoalMode,
+    #[serde(default)]
+    pub tasks: HashMap<String, CompoundTask>,
+}
+
+/// Action with the file which defined it last
+type Library = HashMap<String, (Gaction, String)>;
+
+/// Resolves includes, overrides and derived actions of `path` into one
+/// `PlanConfig`. Goals, world state and tasks come from `path` itself,
+/// included files only contribute actions. A file included on several
+/// paths is loaded and merged once, where one path overrides its actions
+/// the changed ones replace the untouched copies of the other paths.
+pub fn load_config(path: impl AsRef<Path>) -> Result<PlanConfig, LibraryError> {
+    let mut loader = Loader {
+        loaded: HashMap::new(),
+        reached: HashMap::new(),
+        stack: vec![],
+    };
+    let path = canonical(path.as_ref())?;
+    let (library, file) = loader.load(&path)?;
+
+    Ok(PlanConfig {
+        version: CURRENT_VERSION,
+        actions: library
+            .into_iter()
+            .map(|(name, (action, _))| (name, action))
+            .collect(),
+        goals: file.goals,
+        worldState: file.world_state,
+        goal_mode: file.goal_mode,
+        tasks: file.tasks,
+    })
+}
+
+fn display(path: &Path) -> String {
+    path.display().to_string()
+}
+
+fn canonical(path: &Path) -> Result<PathBuf, LibraryError> {
+    fs::canonicalize(path).map_err(|e| LibraryError::Io {
+        path: display(path),
+        message: e.to_string(),
+    })
+}
+
+struct Loader {
+    loaded: HashMap<PathBuf, Library>,
+    /// every file a loaded file includes, directly or through other files
+    reached: HashMap<String, HashSet<String>>,
+    /// files being loaded, to find circular includes
+    stack: Vec<PathBuf>,
+}
+
+impl Loader {
+    fn load(&mut self, path: &Path) -> Result<(Library, LibraryFile), LibraryError> {
+        let name = display(path);
+        let contents = fs::read_to_string(path).map_err(|e| LibraryError::Io {
+            path: name.clone(),
+            message: e.to_string(),
+        })?;
+        let parse_error = |message: String| LibraryError::Parse {
+            path: name.clone(),
+            message,
+        };
+        // files of older versions are upgraded before they are read
+        let document: Value =
+            serde_json::from_str(&contents).map_err(|e| parse_error(e.to_string()))?;
+        let document = migrate(document).map_err(|e| parse_error(e.to_string()))?;
+        let file: LibraryFile =
+            serde_json::from_value(document).map_err(|e| parse_error(e.to_string()))?;
+
+        self.stack.push(path.to_path_buf());
+        let mut library = Library::new();
+        let mut reached = HashSet::new();
+        for include in &file.include {
+            let included = canonical(&path.parent().unwrap_or(Path::new(".")).join(include))?;
+            let actions = self.include(&included)?;
+            let included = display(&included);
+
+            for (action_name, (action, origin)) in actions {
+                match library.get(&action_name) {
+                    // the same file reached on two include paths, keep the
+                    // copy which a file on the way changed
+                    Some((_, first)) if *first == origin || self.reaches(first, &origin) => {}
+                    Some((_, first)) if !self.reaches(&origin, first) => {
+                        return Err(LibraryError::DuplicateAction {
+                            name: action_name,
+                            first: first.clone(),
+                            second: origin,
+                        })
+                    }
+                    _ => {
+                        library.insert(action_name, (action, origin));
+                    }
+                }
+            }
+            reached.extend(self.reached.get(&included).cloned().unwrap_or_default());
+            reached.insert(included);
+        }
+        self.stack.pop();
+        self.reached.insert(name.clone(), reached);
+
+        let mut names: Vec<&String> = file.actions.keys().collect();
+        names.sort();
+        for action_name in names {
+            if let Some((_, first)) = library.get(action_name) {
+                return Err(LibraryError::DuplicateAction {
+                    name: action_name.clone(),
+                    first: first.clone(),
+                    second: name,
+                });
+            }
+            library.insert(
+                action_name.clone(),
+                (file.actions[action_name].clone(), name.clone()),
+            );
+        }
+
+        let mut names: Vec<&String> = file.overrides.keys().collect();
+        names.sort();
+        for action_name in names {
+            let (action, origin) =
+                library
+                    .get_mut(action_name)
+                    .ok_or_else(|| LibraryError::UnknownOverride {
+                        name: action_name.clone(),
+                        path: name.clone(),
+                    })?;
+            *action = patch(
+                action,
+                action_name,
+                &file.overrides[action_name],
+                &[],
+                &name,
+            )?;
+            *origin = name.clone();
+        }
+
+        let mut names: Vec<&String> = file.derived.keys().collect();
+        names.sort();
+        let mut done = HashSet::new();
+        for action_name in names {
+            derive(
+                &mut library,
+                &file,
+                action_name,
+                &mut vec![],
+                &mut done,
+                &name,
+            )?;
+        }
+
+        Ok((library, file))
+    }
+
+    /// Whether `file` includes `other`, directly or through other files
+    fn reaches(&self, file: &str, other: &str) -> bool {
+        self.reached
+            .get(file)
+            .is_some_and(|reached| reached.contains(other))
+    }
+
+    fn include(&mut self, path: &Path) -> Result<Library, LibraryError> {
+        if let Some(start) = self.stack.iter().position(|p| p == path) {
+            let mut chain: Vec<String> = self.stack[start..].iter().map(|p| display(p)).collect();
+            chain.push(display(path));
+            return Err(LibraryError::CircularInclude { chain });
+        }
+
+        if let Some(library) = self.loaded.get(path) {
+            return Ok(library.clone());
+        }
+
+        let (library, _) = self.load(path)?;
+        self.loaded.insert(path.to_path_buf(), library.clone());
+        Ok(library)
+    }
+}
+
+/// Adds derived action `name` of `file`, after the derived actions it is based on
+fn derive(
+    library: &mut Library,
+    file: &LibraryFile,
+    name: &str,
+    chain: &mut Vec<String>,
+    done: &mut HashSet<String>,
+    path: &str,
+) -> Result<(), LibraryError> {
+    if done.contains(name) {
+        return Ok(());
+    }
+    if chain.iter().any(|n| n == name) {
+        chain.push(name.to_string());
+        return Err(LibraryError::CircularDerive {
+            chain: chain.clone(),
+            path: path.to_string(),
+        });
+    }
+    if let Some((_, first)) = library.get(name) {
+        return Err(LibraryError::DuplicateAction {
+            name: name.to_string(),
+            first: first.clone(),
+            second: path.to_string(),
+        });
+    }
+
+    let derived = &file.derived[name];
+    if file.derived.contains_key(&derived.base) {
+        chain.push(name.to_string());
+        derive(library, file, &derived.base, chain, done, path)?;
+        chain.pop();
+    }
+
+    let base = match library.get(&derived.base) {
+        Some((base, _)) => base,
+        None => {
+            return Err(LibraryError::UnknownBase {
+                name: name.to_string(),
+                base: derived.base.clone(),
+                path: path.to_string(),
+            })
+        }
+    };
+
+    let action = patch(base, name, &derived.fields, &MERGED_FIELDS, path)?;
+    library.insert(name.to_string(), (action, path.to_string()));
+    done.insert(name.to_string());
+    Ok(())
+}
+
+/// Copy of `action` with `fields` replaced, or added to for `merged` ones
+fn patch(
+    action: &Gaction,
+    name: &str,
+    fields: &Map<String, Value>,
+    merged: &[&str],
+    path: &str,
+) -> Result<Gaction, LibraryError> {
+    let invalid = |e: serde_json::Error| LibraryError::Parse {
+        path: path.to_string(),
+        message: e.to_string(),
+    };
+
+    let mut value = serde_json::to_value(action).map_err(invalid)?;
+    if let Value::Object(object) = &mut value {
+        for (key, field) in fields {
+            match (object.get_mut(key), field) {
+                (Some(Value::Object(existing)), Value::Object(extra))
+                    if merged.contains(&key.as_str()) =>
+                {
+                    existing.extend(extra.clone());
+                }
+                // every field is serialized, so a missing one is misspelled
+                (None, _) => {
+                    return Err(LibraryError::UnknownField {
+                        name: name.to_string(),
+                        field: key.clone(),
+                        path: path.to_string(),
+                    })
+                }
+                (Some(existing), _) => *existing = field.clone(),
+            }
+        }
+    }
+
+    serde_json::from_value(value).map_err(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Finder;
+
+    const FIXTURES: &str = "./fixtures/library";
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(FIXTURES).join(name)
+    }
+
+    #[test]
+    fn resolves_includes_overrides_and_derived_actions() -> Result<(), String> {
+        let config = load_config(fixture("squad.json")).map_err(|e| e.to_string())?;
+
+        let mut names: Vec<&String> = config.actions.keys().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "Aiming",
+                "Attack with Weapon",
+                "pickup Ammo",
+                "pickupWeapon",
+                "scout with Weapon",
+                "search Ammo",
+                "search Weapon",
+                "search Weapon quietly"
+            ]
+        );
+
+        assert_eq!(config.actions["search Weapon"].cost, 2);
+
+        let quietly = &config.actions["search Weapon quietly"];
+        assert_eq!(quietly.cost, 3);
+        assert_eq!(quietly.pre_state.get("sneaking"), Some(&true));
+        assert_eq!(quietly.pre_state.get("hasWeapon"), Some(&false));
+        assert_eq!(quietly.post_state.get("seeWeapon"), Some(&true));
+
+        // the override makes the plan one more expensive than the sample one
+        let plan = Finder::new(&config).plan().ok_or("no plan")?;
+        assert_eq!(plan.cost, 9);
+        Ok(())
+    }
+
+    #[test]
+    fn upgrades_older_versions() -> Result<(), String> {
+        let config = load_config("./fixtures/migrations/v1.json").map_err(|e| e.to_string())?;
+        assert_eq!(config.version, CURRENT_VERSION);
+        assert_eq!(config.actions["pickupWeapon"].cost, 1);
+        assert_eq!(config.worldState.get("hasWeapon"), Some(&false));
+        Ok(())
+    }
+
+    #[test]
+    fn merges_a_file_included_on_several_paths_once() -> Result<(), String> {
+        // both sides include soldier_basics.json, the right one overrides an action of it
+        let config = load_config(fixture("diamond.json")).map_err(|e| e.to_string())?;
+
+        let mut names: Vec<&String> = config.actions.keys().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "Aiming",
+                "Attack with Weapon",
+                "pickup Ammo",
+                "pickupWeapon",
+                "scout with Weapon",
+                "search Ammo",
+                "search Weapon"
+            ]
+        );
+        assert_eq!(config.actions["search Weapon"].cost, 4);
+        Ok(())
+    }
+
+    #[test]
+    fn prevents_circular_includes() {
+        match load_config(fixture("cycle_a.json")) {
+            Err(LibraryError::CircularInclude { chain }) => {
+                assert_eq!(chain.len(), 3);
+                assert!(chain[0].ends_with("cycle_a.json"));
+                assert!(chain[1].ends_with("cycle_b.json"));
+                assert!(chain[2].ends_with("cycle_a.json"));
+            }
+            other => panic!("expected circular include, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_conflicts() {
+        match load_config(fixture("conflict.json")) {
+            Err(LibraryError::DuplicateAction {
+                name,
+                first,
+                second,
+            }) => {
+                assert_eq!(name, "search Weapon");
+                assert!(first.ends_with("soldier_basics.json"));
+                assert!(second.ends_with("conflict.json"));
+            }
+            other => panic!("expected duplicate action, got {:?}", other),
+        }
+
+        match load_config(fixture("unknown_base.json")) {
+            Err(LibraryError::UnknownBase { name, base, .. }) => {
+                assert_eq!(name, "fly quietly");
+                assert_eq!(base, "fly");
+            }
+            other => panic!("expected unknown base, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_misspelled_fields() {
+        match load_config(fixture("misspelled_override.json")) {
+            Err(LibraryError::UnknownField { name, field, .. }) => {
+                assert_eq!(name, "search Weapon");
+                assert_eq!(field, "post_stat");
+            }
+            other => panic!("expected unknown field, got {:?}", other),
+        }
+
+        match load_config(fixture("misspelled_derived.json")) {
+            Err(LibraryError::UnknownField { name, field, .. }) => {
+                assert_eq!(name, "search Weapon twice");
+                assert_eq!(field, "cots");
+            }
+            other => panic!("expected unknown field, got {:?}", other),
+        }
+    }
+}
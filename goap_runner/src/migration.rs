@@ -0,0 +1,388 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+use crate::configs::PlanConfig;
+
+/// version written by `migrate`
+pub const CURRENT_VERSION: u64 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    Io {
+        path: String,
+        message: String,
+    },
+    Parse {
+        message: String,
+    },
+    UnsupportedVersion {
+        found: u64,
+        latest: u64,
+    },
+    /// `version` is not a positive integer
+    InvalidVersion {
+        found: String,
+    },
+    /// the document does not match the format of `version`
+    Invalid {
+        version: u64,
+        path: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Io { path, message } => {
+                write!(f, "can not access {}: {}", path, message)
+            }
+            MigrationError::Parse { message } => write!(f, "invalid json: {}", message),
+            MigrationError::UnsupportedVersion { found, latest } => write!(
+                f,
+                "unsupported version {}, known versions are 1 to {}",
+                found, latest
+            ),
+            MigrationError::InvalidVersion { found } => {
+                write!(f, "$.version: expected a positive integer, found {}", found)
+            }
+            MigrationError::Invalid {
+                version,
+                path,
+                message,
+            } => write!(f, "{} (version {}): {}", path, version, message),
+        }
+    }
+}
+
+/// Upgrades a document from the version it is in to the next one
+type Migration = fn(Value) -> Result<Value, MigrationError>;
+
+/// `MIGRATIONS[i]` upgrades version `i + 1` to `i + 2`
+const MIGRATIONS: [Migration; 2] = [parameter_lists_to_maps, add_version];
+
+/// Format version of a config document:
+/// 1. lists of `{ "parameter", "value" }` and a list of named actions, as the playground sends them
+/// 2. maps of facts and actions by name, without a `version` field
+/// 3. as 2, with the `version` field of `PlanConfig`
+pub fn detect_version(document: &Value) -> Result<u64, MigrationError> {
+    if let Some(version) = document.get("version") {
+        return version
+            .as_u64()
+            .ok_or_else(|| MigrationError::InvalidVersion {
+                found: version.to_string(),
+            });
+    }
+
+    if document.get("actions").is_some_and(Value::is_array) {
+        Ok(1)
+    } else {
+        Ok(2)
+    }
+}
+
+/// Upgrades `document` step by step to `CURRENT_VERSION`
+pub fn migrate(document: Value) -> Result<Value, MigrationError> {
+    migrate_to(document, CURRENT_VERSION)
+}
+
+/// Upgrades `document` step by step to `version`, documents already at or
+/// past it are returned unchanged
+pub fn migrate_to(mut document: Value, version: u64) -> Result<Value, MigrationError> {
+    let mut current = detect_version(&document)?;
+    if current > CURRENT_VERSION || current == 0 {
+        return Err(MigrationError::UnsupportedVersion {
+            found: current,
+            latest: CURRENT_VERSION,
+        });
+    }
+
+    while current < version.min(CURRENT_VERSION) {
+        document = MIGRATIONS[current as usize - 1](document)?;
+        current += 1;
+    }
+    Ok(document)
+}
+
+/// Parses a config of any known version
+pub fn load_versioned(json: &str) -> Result<PlanConfig, MigrationError> {
+    let document: Value = serde_json::from_str(json).map_err(|e| MigrationError::Parse {
+        message: e.to_string(),
+    })?;
+    load_value(document)
+}
+
+/// Config of a parsed document of any known version
+pub fn load_value(document: Value) -> Result<PlanConfig, MigrationError> {
+    serde_json::from_value(migrate(document)?).map_err(|e| MigrationError::Invalid {
+        version: CURRENT_VERSION,
+        path: "$".to_string(),
+        message: e.to_string(),
+    })
+}
+
+/// Rewrites the config file at `path` in the latest format.
+/// Returns the version the file was in, files already up to date are not written.
+pub fn migrate_file(path: impl AsRef<Path>) -> Result<u64, MigrationError> {
+    let path = path.as_ref();
+    let io_error = |e: std::io::Error| MigrationError::Io {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    };
+
+    let contents = fs::read_to_string(path).map_err(io_error)?;
+    let document: Value = serde_json::from_str(&contents).map_err(|e| MigrationError::Parse {
+        message: e.to_string(),
+    })?;
+
+    let version = detect_version(&document)?;
+    if version != CURRENT_VERSION {
+        let migrated = migrate(document)?;
+        let mut output =
+            serde_json::to_string_pretty(&migrated).map_err(|e| MigrationError::Parse {
+                message: e.to_string(),
+            })?;
+        output.push('\n');
+        fs::write(path, output).map_err(io_error)?;
+    }
+    Ok(version)
+}
+
+fn invalid_v1(path: String, message: &str) -> MigrationError {
+    MigrationError::Invalid {
+        version: 1,
+        path,
+        message: message.to_string(),
+    }
+}
+
+/// `[{ "parameter": "a", "value": true }]` to `{ "a": true }`
+fn parameter_list_to_map(list: &Value, path: &str) -> Result<Value, MigrationError> {
+    let items = list
+        .as_array()
+        .ok_or_else(|| invalid_v1(path.to_string(), "expected a list of parameters"))?;
+
+    let mut map = Map::new();
+    for (index, item) in items.iter().enumerate() {
+        match (
+            item.get("parameter").and_then(Value::as_str),
+            item.get("value"),
+        ) {
+            (Some(parameter), Some(value)) => {
+                map.insert(parameter.to_string(), value.clone());
+            }
+            _ => {
+                return Err(invalid_v1(
+                    format!("{}[{}]", path, index),
+                    "expected \"parameter\" and \"value\"",
+                ))
+            }
+        }
+    }
+    Ok(Value::Object(map))
+}
+
+fn parameter_lists_to_maps(mut document: Value) -> Result<Value, MigrationError> {
+    let object = document
+        .as_object_mut()
+        .ok_or_else(|| invalid_v1("$".to_string(), "expected an object"))?;
+
+    let list = match object.get("actions") {
+        None => vec![],
+        Some(Value::Array(list)) => list.clone(),
+        Some(_) => {
+            return Err(invalid_v1(
+                "$.actions".to_string(),
+                "expected a list of actions",
+            ))
+        }
+    };
+
+    let mut actions = Map::new();
+    for (index, action) in list.iter().enumerate() {
+        let path = format!("$.actions[{}]", index);
+        let mut action = action
+            .as_object()
+            .cloned()
+            .ok_or_else(|| invalid_v1(path.clone(), "expected an object"))?;
+
+        let name = match action.remove("name") {
+            Some(Value::String(name)) => name,
+            _ => return Err(invalid_v1(format!("{}.name", path), "expected a name")),
+        };
+        for field in ["pre_state", "post_state"] {
+            if let Some(list) = action.get(field) {
+                let map = parameter_list_to_map(list, &format!("{}.{}", path, field))?;
+                action.insert(field.to_string(), map);
+            }
+        }
+
+        if actions.contains_key(&name) {
+            return Err(invalid_v1(
+                format!("{}.name", path),
+                &format!("duplicate action \"{}\"", name),
+            ));
+        }
+        actions.insert(name, Value::Object(action));
+    }
+    object.insert("actions".to_string(), Value::Object(actions));
+
+    if let Some(Value::Array(goals)) = object.get_mut("goals") {
+        for (index, goal) in goals.iter_mut().enumerate() {
+            if let Some(state) = goal.get("state") {
+                let map = parameter_list_to_map(state, &format!("$.goals[{}].state", index))?;
+                goal["state"] = map;
+            }
+        }
+    }
+
+    if let Some(state) = object.get("worldState") {
+        let map = parameter_list_to_map(state, "$.worldState")?;
+        object.insert("worldState".to_string(), map);
+    }
+
+    Ok(document)
+}
+
+/// `PlanConfig.version`, which version 2 files do not have
+fn add_version(mut document: Value) -> Result<Value, MigrationError> {
+    if let Some(object) = document.as_object_mut() {
+        object.insert("version".to_string(), Value::from(3));
+    }
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn golden(version: u64) -> Result<Value, String> {
+        let path = format!("./fixtures/migrations/v{}.json", version);
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn migrates_every_version_to_the_latest() -> Result<(), String> {
+        let latest = golden(CURRENT_VERSION)?;
+
+        for version in 1..=CURRENT_VERSION {
+            let document = golden(version)?;
+            assert_eq!(detect_version(&document), Ok(version));
+            assert_eq!(migrate(document), Ok(latest.clone()), "from v{}", version);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn migrates_one_step_at_a_time() -> Result<(), String> {
+        for version in 1..CURRENT_VERSION {
+            assert_eq!(
+                migrate_to(golden(version)?, version + 1),
+                Ok(golden(version + 1)?),
+                "from v{}",
+                version
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn loads_all_versions_into_same_config() -> Result<(), String> {
+        for version in 1..=CURRENT_VERSION {
+            let contents = fs::read_to_string(format!("./fixtures/migrations/v{}.json", version))
+                .map_err(|e| e.to_string())?;
+            let config = load_versioned(&contents).map_err(|e| e.to_string())?;
+
+            assert_eq!(config.actions["pickupWeapon"].cost, 1);
+            assert_eq!(config.goals[0].state.get("enemyAlive"), Some(&false));
+            assert_eq!(config.worldState.get("hasWeapon"), Some(&false));
+            assert_eq!(config.version, CURRENT_VERSION);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_version_when_saved_and_loaded() -> Result<(), String> {
+        let contents =
+            fs::read_to_string("./fixtures/migrations/v1.json").map_err(|e| e.to_string())?;
+        let config = load_versioned(&contents).map_err(|e| e.to_string())?;
+
+        let saved = serde_json::to_value(&config).map_err(|e| e.to_string())?;
+        assert_eq!(saved["version"], CURRENT_VERSION);
+        assert_eq!(detect_version(&saved), Ok(CURRENT_VERSION));
+        let reloaded = load_value(saved).map_err(|e| e.to_string())?;
+        assert_eq!(reloaded.version, CURRENT_VERSION);
+        assert_eq!(reloaded.actions.len(), config.actions.len());
+        Ok(())
+    }
+
+    #[test]
+    fn rewrites_file_in_latest_format() -> Result<(), String> {
+        let path = std::env::temp_dir().join(format!("goap_migrate_{}.json", std::process::id()));
+        fs::copy("./fixtures/migrations/v1.json", &path).map_err(|e| e.to_string())?;
+
+        let first = migrate_file(&path);
+        let second = migrate_file(&path);
+        let contents = fs::read_to_string(&path).map_err(|e| e.to_string());
+        fs::remove_file(&path).map_err(|e| e.to_string())?;
+
+        assert_eq!(first, Ok(1));
+        assert_eq!(second, Ok(CURRENT_VERSION));
+        let rewritten: Value = serde_json::from_str(&contents?).map_err(|e| e.to_string())?;
+        assert_eq!(rewritten, golden(CURRENT_VERSION)?);
+        Ok(())
+    }
+
+    #[test]
+    fn reports_unsupported_and_broken_documents() {
+        assert_eq!(
+            migrate(serde_json::json!({ "version": 99 })),
+            Err(MigrationError::UnsupportedVersion {
+                found: 99,
+                latest: CURRENT_VERSION
+            })
+        );
+
+        let duplicate = serde_json::json!({
+            "actions": [
+                { "name": "a", "cost": 1, "pre_state": [], "post_state": [] },
+                { "name": "a", "cost": 2, "pre_state": [], "post_state": [] }
+            ],
+            "goals": [],
+            "worldState": []
+        });
+        match migrate(duplicate) {
+            Err(MigrationError::Invalid { version, path, .. }) => {
+                assert_eq!(version, 1);
+                assert_eq!(path, "$.actions[1].name");
+            }
+            other => panic!("expected invalid document, got {:?}", other),
+        }
+
+        assert_eq!(
+            detect_version(&serde_json::json!({ "version": "two" })),
+            Err(MigrationError::InvalidVersion {
+                found: "\"two\"".to_string()
+            })
+        );
+
+        // labeled version 1 while its actions are already a map
+        let mislabeled = serde_json::json!({
+            "version": 1,
+            "actions": { "a": { "cost": 1, "pre_state": {}, "post_state": {} } },
+            "goals": [],
+            "worldState": []
+        });
+        match migrate(mislabeled) {
+            Err(MigrationError::Invalid { version, path, .. }) => {
+                assert_eq!(version, 1);
+                assert_eq!(path, "$.actions");
+            }
+            other => panic!("expected invalid document, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,822 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::configs::{
+    hasmap_contains, Gaction, Goal, GoalMode, PlanConfig, WorldState, MAX_PLAN_COST,
+};
+use crate::Finder;
+
+/// agent priority orders tried before searching the joint state space
+pub const MAX_AGENT_ORDERS: usize = 720;
+
+/// joint states expanded by the joint search before giving up
+pub const MAX_JOINT_STATES: usize = 100_000;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AgentConfig {
+    pub name: String,
+    pub actions: HashMap<String, Gaction>,
+    pub goal: HashMap<String, bool>,
+}
+
+/// Agents acting on one shared world state. Every `Gaction.resources` entry
+/// listed in `exclusive_resources` is reserved by one agent from the first to
+/// the last step in which it uses the resource.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct MultiAgentConfig {
+    pub agents: Vec<AgentConfig>,
+    #[serde(rename = "worldState")]
+    pub world_state: WorldState,
+    #[serde(default)]
+    pub exclusive_resources: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AgentPlan {
+    pub agent: String,
+    /// one entry per step, `None` while the agent waits
+    pub steps: Vec<Option<String>>,
+    pub cost: usize,
+}
+
+impl AgentPlan {
+    pub fn actions(&self) -> Vec<&str> {
+        self.steps.iter().flatten().map(|a| a.as_str()).collect()
+    }
+}
+
+/// Per agent plans in config order. All agents perform their n-th step
+/// together, within a step they act in `priority` order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JointPlan {
+    pub agents: Vec<AgentPlan>,
+    pub priority: Vec<String>,
+    pub makespan: usize,
+    pub cost: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultiAgentError {
+    DuplicateAgent(String),
+    /// the agent can not reach its goal even when acting alone
+    Unreachable(String),
+    /// every agent has a plan, but they can not be combined
+    NoJointPlan,
+    /// neither prioritized planning nor `MAX_JOINT_STATES` joint states gave a plan
+    SearchLimit,
+}
+
+impl fmt::Display for MultiAgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MultiAgentError::DuplicateAgent(name) => {
+                write!(f, "agent \"{}\" is declared twice", name)
+            }
+            MultiAgentError::Unreachable(name) => {
+                write!(f, "agent \"{}\" can not reach its goal", name)
+            }
+            MultiAgentError::NoJointPlan => {
+                write!(f, "no joint plan without conflicts exists")
+            }
+            MultiAgentError::SearchLimit => write!(
+                f,
+                "no joint plan found within {} joint states",
+                MAX_JOINT_STATES
+            ),
+        }
+    }
+}
+
+/// Step of one agent, index into its sorted action names or `None` to wait
+type Step = Option<usize>;
+
+/// (cost, makespan, priority order, plans in that order)
+type JointCandidate = (usize, usize, Vec<usize>, Vec<Vec<Step>>);
+
+/// Exclusive resource during the joint search: the agent holding it and the
+/// agents whose reservation already ended
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct Reservation {
+    holder: Option<usize>,
+    released: Vec<usize>,
+}
+
+/// State of the joint search after some joint steps
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct JointState {
+    /// world state sorted by fact
+    facts: Vec<(String, bool)>,
+    /// actions each agent has taken, by index into its sorted action names
+    used: Vec<Vec<bool>>,
+    /// by index into `exclusive_resources`
+    reservations: Vec<Reservation>,
+}
+
+impl JointState {
+    fn world(&self) -> WorldState {
+        self.facts.iter().cloned().collect()
+    }
+}
+
+/// Prioritized planner: agents are planned one after another around the
+/// plans and reservations of the agents before them. Different priority
+/// orders are tried and the cheapest joint plan wins. When no order gives a
+/// plan, the joint state space of all agents is searched, so
+/// `MultiAgentError::NoJointPlan` means there is none.
+pub struct MultiAgentPlanner<'a> {
+    config: &'a MultiAgentConfig,
+    action_names: Vec<Vec<&'a String>>,
+}
+
+impl<'a> MultiAgentPlanner<'a> {
+    pub fn new(config: &'a MultiAgentConfig) -> Self {
+        let action_names = config
+            .agents
+            .iter()
+            .map(|agent| {
+                let mut names: Vec<&'a String> = agent.actions.keys().collect();
+                names.sort();
+                names
+            })
+            .collect();
+
+        MultiAgentPlanner {
+            config,
+            action_names,
+        }
+    }
+
+    pub fn plan(&self) -> Result<JointPlan, MultiAgentError> {
+        let agents = &self.config.agents;
+        for (index, agent) in agents.iter().enumerate() {
+            if agents[..index].iter().any(|a| a.name == agent.name) {
+                return Err(MultiAgentError::DuplicateAgent(agent.name.clone()));
+            }
+        }
+
+        for agent in agents {
+            let alone = PlanConfig {
+                actions: agent.actions.clone(),
+                goals: vec![Goal {
+                    name: agent.name.clone(),
+                    state: agent.goal.clone(),
+                }],
+                worldState: self.config.world_state.clone(),
+                goal_mode: GoalMode::All,
+                ..Default::default()
+            };
+            if Finder::new(&alone).plan().is_none() {
+                return Err(MultiAgentError::Unreachable(agent.name.clone()));
+            }
+        }
+
+        let mut best: Option<JointCandidate> = None;
+        for order in priority_orders(agents.len()) {
+            if let Some(plans) = self.plan_in_order(&order) {
+                let cost: usize = order
+                    .iter()
+                    .zip(plans.iter())
+                    .map(|(agent, plan)| self.plan_cost(*agent, plan))
+                    .sum();
+                let makespan = plans.iter().map(|p| p.len()).max().unwrap_or(0);
+
+                if best.as_ref().is_none_or(|b| (cost, makespan) < (b.0, b.1)) {
+                    best = Some((cost, makespan, order, plans));
+                }
+            }
+        }
+
+        let (cost, makespan, order, plans) = match best {
+            Some(best) => best,
+            None => {
+                let plans = self.joint_search()?;
+                let cost = plans
+                    .iter()
+                    .enumerate()
+                    .map(|(agent, plan)| self.plan_cost(agent, plan))
+                    .sum();
+                let makespan = plans.iter().map(|p| p.len()).max().unwrap_or(0);
+                (cost, makespan, (0..agents.len()).collect(), plans)
+            }
+        };
+
+        let mut agent_plans: Vec<AgentPlan> = order
+            .iter()
+            .zip(plans.iter())
+            .map(|(&agent, plan)| AgentPlan {
+                agent: agents[agent].name.clone(),
+                steps: plan
+                    .iter()
+                    .map(|step| step.map(|a| self.action_names[agent][a].clone()))
+                    .collect(),
+                cost: self.plan_cost(agent, plan),
+            })
+            .collect();
+        agent_plans.sort_by_key(|p| agents.iter().position(|a| a.name == p.agent));
+
+        Ok(JointPlan {
+            agents: agent_plans,
+            priority: order.iter().map(|a| agents[*a].name.clone()).collect(),
+            makespan,
+            cost,
+        })
+    }
+
+    fn action(&self, agent: usize, action_index: usize) -> &'a Gaction {
+        &self.config.agents[agent].actions[self.action_names[agent][action_index]]
+    }
+
+    fn plan_cost(&self, agent: usize, plan: &[Step]) -> usize {
+        plan.iter()
+            .flatten()
+            .map(|a| self.action(agent, *a).cost)
+            .sum()
+    }
+
+    /// Plans of the agents in `order`, each one the cheapest around the ones before it
+    fn plan_in_order(&self, order: &[usize]) -> Option<Vec<Vec<Step>>> {
+        let mut plans: Vec<Vec<Step>> = Vec::with_capacity(order.len());
+
+        for &agent in order {
+            let makespan = plans.iter().map(|p| p.len()).max().unwrap_or(0);
+            plans.push(vec![]);
+
+            let mut best: Option<(usize, Vec<Step>)> = None;
+            if self.simulate(order, &plans, None, makespan) == Some(true) {
+                best = Some((0, vec![]));
+            } else {
+                let mut used = vec![false; self.action_names[agent].len()];
+                self.search(order, &mut plans, &mut used, 0, makespan, &mut best);
+            }
+
+            let (_, plan) = best?;
+            *plans.last_mut()? = plan;
+        }
+
+        Some(plans)
+    }
+
+    /// Depth first search over the steps of the last agent in `plans`.
+    /// It may wait while agents before it still act, `makespan` is their last step.
+    fn search(
+        &self,
+        order: &[usize],
+        plans: &mut Vec<Vec<Step>>,
+        used: &mut Vec<bool>,
+        cost: usize,
+        makespan: usize,
+        best: &mut Option<(usize, Vec<Step>)>,
+    ) {
+        let position = plans.len() - 1;
+        let agent = order[position];
+        let length = plans[position].len() + 1;
+
+        let waits = if length <= makespan {
+            vec![None]
+        } else {
+            vec![]
+        };
+        let choices: Vec<Step> = (0..self.action_names[agent].len())
+            .filter(|a| !used[*a])
+            .map(Some)
+            .chain(waits)
+            .collect();
+
+        for step in choices {
+            let step_cost = step.map_or(0, |a| self.action(agent, a).cost);
+            let next_cost = cost + step_cost;
+            if next_cost > MAX_PLAN_COST {
+                continue;
+            }
+            if let Some((best_cost, best_plan)) = best {
+                if (next_cost, length) >= (*best_cost, best_plan.len()) {
+                    continue;
+                }
+            }
+
+            plans[position].push(step);
+            let reached = self.simulate(order, plans, Some(position), length);
+
+            if let Some(reached) = reached {
+                let complete = reached
+                    && step.is_some()
+                    && self.simulate(order, plans, None, makespan.max(length)) == Some(true);
+
+                if complete {
+                    *best = Some((next_cost, plans[position].clone()));
+                } else {
+                    if let Some(a) = step {
+                        used[a] = true;
+                    }
+                    self.search(order, plans, used, next_cost, makespan, best);
+                    if let Some(a) = step {
+                        used[a] = false;
+                    }
+                }
+            }
+
+            plans[position].pop();
+        }
+    }
+
+    /// Runs `steps` joint steps. `None` when a precondition fails or a
+    /// reservation conflicts. Otherwise whether the goal of the `open` agent,
+    /// whose plan may still grow, holds afterwards, or without an `open`
+    /// agent whether the goals of all agents hold. Goals only count in the
+    /// final state, a later step may undo a goal reached before.
+    fn simulate(
+        &self,
+        order: &[usize],
+        plans: &[Vec<Step>],
+        open: Option<usize>,
+        steps: usize,
+    ) -> Option<bool> {
+        if self.has_reservation_conflict(order, plans, steps) {
+            return None;
+        }
+
+        let mut state = self.config.world_state.clone();
+        for step in 0..steps {
+            for (position, plan) in plans.iter().enumerate() {
+                if let Some(Some(action_index)) = plan.get(step) {
+                    let action = self.action(order[position], *action_index);
+                    if !action.are_preconditions_met(&state) {
+                        return None;
+                    }
+                    action.update_with_post_conditions(&mut state);
+                }
+            }
+        }
+
+        let goal_holds =
+            |position: usize| hasmap_contains(&state, &self.config.agents[order[position]].goal);
+        Some(match open {
+            Some(position) => goal_holds(position),
+            None => (0..plans.len()).all(goal_holds),
+        })
+    }
+
+    /// Cheapest joint steps of all agents, found by searching their joint
+    /// state space. Within a step agents act in config order. Plans are in
+    /// config order as well.
+    fn joint_search(&self) -> Result<Vec<Vec<Step>>, MultiAgentError> {
+        let agents = &self.config.agents;
+        let mut facts: Vec<(String, bool)> = self.config.world_state.clone().into_iter().collect();
+        facts.sort();
+        let start = JointState {
+            facts,
+            used: self
+                .action_names
+                .iter()
+                .map(|n| vec![false; n.len()])
+                .collect(),
+            reservations: vec![Reservation::default(); self.config.exclusive_resources.len()],
+        };
+
+        // (state, node it was reached from, the joint step leading to it)
+        let mut nodes: Vec<(JointState, Option<usize>, Vec<Step>)> = vec![(start, None, vec![])];
+        let mut queue = BinaryHeap::from([Reverse((0, 0, 0))]);
+        let mut expanded: HashSet<JointState> = HashSet::new();
+
+        while let Some(Reverse((cost, length, index))) = queue.pop() {
+            let state = &nodes[index].0;
+            let world = state.world();
+            if agents.iter().all(|a| hasmap_contains(&world, &a.goal)) {
+                return Ok(self.joint_plans(&nodes, index));
+            }
+            if !expanded.insert(state.clone()) {
+                continue;
+            }
+            if expanded.len() > MAX_JOINT_STATES {
+                return Err(MultiAgentError::SearchLimit);
+            }
+
+            let mut successors = vec![];
+            self.joint_steps(state.clone(), world, &mut vec![], &mut successors);
+            for (next, steps) in successors {
+                let next_cost = cost + self.step_cost(&steps);
+                if next_cost > MAX_PLAN_COST || expanded.contains(&next) {
+                    continue;
+                }
+                nodes.push((next, Some(index), steps));
+                queue.push(Reverse((next_cost, length + 1, nodes.len() - 1)));
+            }
+        }
+
+        Err(MultiAgentError::NoJointPlan)
+    }
+
+    fn step_cost(&self, steps: &[Step]) -> usize {
+        steps
+            .iter()
+            .enumerate()
+            .filter_map(|(agent, step)| step.map(|a| self.action(agent, a).cost))
+            .sum()
+    }
+
+    /// Every joint step from `state`, the agents after the ones in `steps`
+    /// waiting or taking an action they have not taken yet. Steps where all
+    /// agents wait are left out.
+    fn joint_steps(
+        &self,
+        state: JointState,
+        world: WorldState,
+        steps: &mut Vec<Step>,
+        successors: &mut Vec<(JointState, Vec<Step>)>,
+    ) {
+        let agent = steps.len();
+        if agent == self.config.agents.len() {
+            if steps.iter().any(Option::is_some) {
+                let mut next = state;
+                next.facts = world.into_iter().collect();
+                next.facts.sort();
+                successors.push((next, steps.clone()));
+            }
+            return;
+        }
+
+        steps.push(None);
+        self.joint_steps(state.clone(), world.clone(), steps, successors);
+        steps.pop();
+
+        for action_index in 0..self.action_names[agent].len() {
+            let action = self.action(agent, action_index);
+            if state.used[agent][action_index] || !action.are_preconditions_met(&world) {
+                continue;
+            }
+
+            let mut next = state.clone();
+            if !self.reserve(&mut next, agent, action, steps) {
+                continue;
+            }
+            next.used[agent][action_index] = true;
+            let mut next_world = world.clone();
+            action.update_with_post_conditions(&mut next_world);
+
+            steps.push(Some(action_index));
+            self.joint_steps(next, next_world, steps, successors);
+            steps.pop();
+        }
+    }
+
+    /// Reserves the exclusive resources of `action` for `agent`, false when
+    /// another agent uses one in this joint step or `agent` released it before
+    fn reserve(
+        &self,
+        state: &mut JointState,
+        agent: usize,
+        action: &Gaction,
+        steps: &[Step],
+    ) -> bool {
+        for resource in &action.resources {
+            let index = match self
+                .config
+                .exclusive_resources
+                .iter()
+                .position(|r| r == resource)
+            {
+                Some(index) => index,
+                None => continue,
+            };
+            let reservation = &mut state.reservations[index];
+            if reservation.holder == Some(agent) {
+                continue;
+            }
+            if reservation.released.contains(&agent) {
+                return false;
+            }
+
+            if let Some(holder) = reservation.holder {
+                // agents later in the order have not picked their step yet
+                let used_now = steps
+                    .get(holder)
+                    .copied()
+                    .flatten()
+                    .is_some_and(|a| self.action(holder, a).resources.contains(resource));
+                if used_now {
+                    return false;
+                }
+                reservation.released.push(holder);
+                reservation.released.sort();
+            }
+            reservation.holder = Some(agent);
+        }
+        true
+    }
+
+    /// Per agent steps leading to node `index`, without trailing waits
+    fn joint_plans(
+        &self,
+        nodes: &[(JointState, Option<usize>, Vec<Step>)],
+        mut index: usize,
+    ) -> Vec<Vec<Step>> {
+        let mut joint_steps = vec![];
+        while let (_, Some(parent), steps) = &nodes[index] {
+            joint_steps.push(steps);
+            index = *parent;
+        }
+        joint_steps.reverse();
+
+        (0..self.config.agents.len())
+            .map(|agent| {
+                let mut plan: Vec<Step> = joint_steps.iter().map(|steps| steps[agent]).collect();
+                while plan.last() == Some(&None) {
+                    plan.pop();
+                }
+                plan
+            })
+            .collect()
+    }
+
+    /// Two agents hold the same exclusive resource in overlapping step ranges
+    fn has_reservation_conflict(&self, order: &[usize], plans: &[Vec<Step>], steps: usize) -> bool {
+        let mut reserved: HashMap<&str, Vec<(usize, usize, usize)>> = HashMap::new();
+
+        for (position, plan) in plans.iter().enumerate() {
+            let mut ranges: HashMap<&str, (usize, usize)> = HashMap::new();
+            for (step, action_index) in plan.iter().enumerate().take(steps) {
+                let action = match action_index {
+                    Some(a) => self.action(order[position], *a),
+                    None => continue,
+                };
+                for resource in &action.resources {
+                    if self.config.exclusive_resources.contains(resource) {
+                        let range = ranges.entry(resource.as_str()).or_insert((step, step));
+                        range.1 = step;
+                    }
+                }
+            }
+
+            for (resource, (first, last)) in ranges {
+                let holders = reserved.entry(resource).or_default();
+                if holders
+                    .iter()
+                    .any(|(_, start, end)| first <= *end && *start <= last)
+                {
+                    return true;
+                }
+                holders.push((position, first, last));
+            }
+        }
+
+        false
+    }
+}
+
+/// Permutations of `0..count` in lexicographic order, at most `MAX_AGENT_ORDERS`
+fn priority_orders(count: usize) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..count).collect();
+    let mut orders = vec![order.clone()];
+
+    while orders.len() < MAX_AGENT_ORDERS {
+        let pivot = match (1..order.len()).rev().find(|&i| order[i - 1] < order[i]) {
+            Some(i) => i - 1,
+            None => break,
+        };
+        let swap = (pivot + 1..order.len())
+            .rev()
+            .find(|&i| order[i] > order[pivot])
+            .unwrap_or(pivot);
+        order.swap(pivot, swap);
+        order[pivot + 1..].reverse();
+        orders.push(order.clone());
+    }
+
+    orders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn squad_config(json: &str) -> MultiAgentConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn plan_of<'p>(plan: &'p JointPlan, agent: &str) -> &'p AgentPlan {
+        plan.agents.iter().find(|p| p.agent == agent).unwrap()
+    }
+
+    #[test]
+    fn waits_for_reserved_resource() -> Result<(), String> {
+        let config = squad_config(
+            r#"{
+                "agents": [
+                    { "name": "alpha", "goal": { "alphaOutside": true }, "actions": {
+                        "go through door": { "cost": 1, "resources": ["door"], "pre_state": {}, "post_state": { "alphaOutside": true } }
+                    } },
+                    { "name": "bravo", "goal": { "bravoOutside": true }, "actions": {
+                        "go through door": { "cost": 1, "resources": ["door"], "pre_state": {}, "post_state": { "bravoOutside": true } }
+                    } }
+                ],
+                "worldState": { "alphaOutside": false, "bravoOutside": false },
+                "exclusive_resources": ["door"]
+            }"#,
+        );
+
+        let plan = MultiAgentPlanner::new(&config)
+            .plan()
+            .map_err(|e| e.to_string())?;
+
+        assert_eq!(plan.makespan, 2);
+        assert_eq!(plan.cost, 2);
+        assert_eq!(plan.priority, vec!["alpha", "bravo"]);
+        assert_eq!(
+            plan_of(&plan, "alpha").steps,
+            vec![Some("go through door".to_string())]
+        );
+        assert_eq!(
+            plan_of(&plan, "bravo").steps,
+            vec![None, Some("go through door".to_string())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn shares_weapons_between_soldiers() -> Result<(), String> {
+        let config = squad_config(
+            r#"{
+                "agents": [
+                    { "name": "alpha", "goal": { "alphaArmed": true }, "actions": {
+                        "take rifle": { "cost": 1, "resources": ["rifle"], "pre_state": { "rifleOnRack": true }, "post_state": { "rifleOnRack": false, "alphaArmed": true } },
+                        "take pistol": { "cost": 2, "resources": ["pistol"], "pre_state": { "pistolOnRack": true }, "post_state": { "pistolOnRack": false, "alphaArmed": true } }
+                    } },
+                    { "name": "bravo", "goal": { "bravoArmed": true }, "actions": {
+                        "take rifle": { "cost": 1, "resources": ["rifle"], "pre_state": { "rifleOnRack": true }, "post_state": { "rifleOnRack": false, "bravoArmed": true } }
+                    } }
+                ],
+                "worldState": { "rifleOnRack": true, "pistolOnRack": true },
+                "exclusive_resources": ["rifle", "pistol"]
+            }"#,
+        );
+
+        let plan = MultiAgentPlanner::new(&config)
+            .plan()
+            .map_err(|e| e.to_string())?;
+
+        // alpha picking first would leave bravo without a weapon
+        assert_eq!(plan.priority, vec!["bravo", "alpha"]);
+        assert_eq!(plan_of(&plan, "alpha").actions(), vec!["take pistol"]);
+        assert_eq!(plan_of(&plan, "bravo").actions(), vec!["take rifle"]);
+        assert_eq!(plan.cost, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn detects_missing_joint_plan() {
+        let mut config = squad_config(
+            r#"{
+                "agents": [
+                    { "name": "alpha", "goal": { "alphaArmed": true }, "actions": {
+                        "take rifle": { "cost": 1, "resources": ["rifle"], "pre_state": { "rifleOnRack": true }, "post_state": { "rifleOnRack": false, "alphaArmed": true } }
+                    } },
+                    { "name": "bravo", "goal": { "bravoArmed": true }, "actions": {
+                        "take rifle": { "cost": 1, "resources": ["rifle"], "pre_state": { "rifleOnRack": true }, "post_state": { "rifleOnRack": false, "bravoArmed": true } }
+                    } }
+                ],
+                "worldState": { "rifleOnRack": true },
+                "exclusive_resources": ["rifle"]
+            }"#,
+        );
+
+        // each soldier could arm itself alone, but there is only one rifle
+        assert_eq!(
+            MultiAgentPlanner::new(&config).plan().err(),
+            Some(MultiAgentError::NoJointPlan)
+        );
+
+        config.agents[0]
+            .goal
+            .insert("alphaFlying".to_string(), true);
+        assert_eq!(
+            MultiAgentPlanner::new(&config).plan().err(),
+            Some(MultiAgentError::Unreachable("alpha".to_string()))
+        );
+
+        config.agents[1].name = "alpha".to_string();
+        assert_eq!(
+            MultiAgentPlanner::new(&config).plan().err(),
+            Some(MultiAgentError::DuplicateAgent("alpha".to_string()))
+        );
+    }
+
+    #[test]
+    fn searches_joint_states_when_priorities_fail() -> Result<(), String> {
+        // whoever plans first takes its cheap action, which leaves the other one stuck
+        let config = squad_config(
+            r#"{
+                "agents": [
+                    { "name": "alpha", "goal": { "alphaOver": true }, "actions": {
+                        "cross and burn bridge": { "cost": 1, "pre_state": { "ferryFree": true }, "post_state": { "alphaOver": true, "bridgeUp": false } },
+                        "cross": { "cost": 5, "pre_state": { "ferryFree": true }, "post_state": { "alphaOver": true } }
+                    } },
+                    { "name": "bravo", "goal": { "bravoOver": true }, "actions": {
+                        "cross and sink ferry": { "cost": 1, "pre_state": { "bridgeUp": true }, "post_state": { "bravoOver": true, "ferryFree": false } },
+                        "cross": { "cost": 5, "pre_state": { "bridgeUp": true }, "post_state": { "bravoOver": true } }
+                    } }
+                ],
+                "worldState": { "bridgeUp": true, "ferryFree": true, "alphaOver": false, "bravoOver": false }
+            }"#,
+        );
+
+        let plan = MultiAgentPlanner::new(&config)
+            .plan()
+            .map_err(|e| e.to_string())?;
+
+        assert_eq!(plan.cost, 6);
+        assert_eq!(plan.priority, vec!["alpha", "bravo"]);
+        assert_eq!(
+            plan_of(&plan, "alpha").actions(),
+            vec!["cross"],
+            "{:?}",
+            plan
+        );
+        assert_eq!(
+            plan_of(&plan, "bravo").actions(),
+            vec!["cross and sink ferry"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn searches_joint_states_with_resources_held_by_later_agents() -> Result<(), String> {
+        let config = squad_config(
+            r#"{
+                "agents": [
+                    { "name": "alpha", "goal": { "alphaOver": true }, "actions": {
+                        "cross and burn bridge": { "cost": 1, "pre_state": { "ferryFree": true }, "post_state": { "alphaOver": true, "bridgeUp": false } },
+                        "cross": { "cost": 5, "pre_state": { "ferryFree": true }, "post_state": { "alphaOver": true } },
+                        "knock": { "cost": 1, "pre_state": {}, "post_state": { "knocked": true }, "resources": ["door"] }
+                    } },
+                    { "name": "bravo", "goal": { "bravoOver": true }, "actions": {
+                        "cross and sink ferry": { "cost": 1, "pre_state": { "bridgeUp": true }, "post_state": { "bravoOver": true, "ferryFree": false } },
+                        "cross": { "cost": 5, "pre_state": { "bridgeUp": true }, "post_state": { "bravoOver": true } },
+                        "knock": { "cost": 1, "pre_state": {}, "post_state": { "knocked": true }, "resources": ["door"] }
+                    } }
+                ],
+                "worldState": { "bridgeUp": true, "ferryFree": true, "alphaOver": false, "bravoOver": false, "knocked": false },
+                "exclusive_resources": ["door"]
+            }"#,
+        );
+
+        let plan = MultiAgentPlanner::new(&config)
+            .plan()
+            .map_err(|e| e.to_string())?;
+
+        // knocking helps nobody, the plan is the one without the door
+        assert_eq!(plan.cost, 6);
+        assert_eq!(plan_of(&plan, "alpha").actions(), vec!["cross"]);
+        assert_eq!(
+            plan_of(&plan, "bravo").actions(),
+            vec!["cross and sink ferry"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn checks_goals_in_the_final_state() -> Result<(), String> {
+        let config = squad_config(
+            r#"{
+                "agents": [
+                    { "name": "alpha", "goal": { "lightOn": true }, "actions": {
+                        "switch light on": { "cost": 1, "pre_state": {}, "post_state": { "lightOn": true } }
+                    } },
+                    { "name": "bravo", "goal": { "bravoAsleep": true }, "actions": {
+                        "sleep": { "cost": 1, "pre_state": {}, "post_state": { "bravoAsleep": true, "lightOn": false } },
+                        "sleep with light on": { "cost": 3, "pre_state": {}, "post_state": { "bravoAsleep": true } }
+                    } }
+                ],
+                "worldState": { "lightOn": false, "bravoAsleep": false }
+            }"#,
+        );
+
+        let plan = MultiAgentPlanner::new(&config)
+            .plan()
+            .map_err(|e| e.to_string())?;
+
+        // alpha acting first would have its light switched off again by bravo
+        assert_eq!(plan.priority, vec!["bravo", "alpha"]);
+        assert_eq!(plan_of(&plan, "bravo").actions(), vec!["sleep"]);
+        assert_eq!(plan.cost, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn lists_priority_orders() {
+        assert_eq!(
+            priority_orders(3),
+            vec![
+                vec![0, 1, 2],
+                vec![0, 2, 1],
+                vec![1, 0, 2],
+                vec![1, 2, 0],
+                vec![2, 0, 1],
+                vec![2, 1, 0]
+            ]
+        );
+        assert_eq!(priority_orders(0), vec![Vec::<usize>::new()]);
+    }
+}
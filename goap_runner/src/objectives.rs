@@ -0,0 +1,403 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::configs::{Gnode, MAX_PLAN_COST};
+use crate::plan::PlanResult;
+use crate::Finder;
+
+/// name of the objective backed by `Gaction.cost`
+pub const BASE_OBJECTIVE: &str = "cost";
+
+/// How plans with several named costs are compared. Objectives which are not
+/// mentioned are ignored, `"cost"` refers to the plain action cost.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ranking {
+    Weighted(HashMap<String, f64>),
+    /// first objective decides, later ones only break ties
+    Lexicographic(Vec<String>),
+}
+
+impl Ranking {
+    /// weights a plan can be ranked with, NaN or infinite ones compare
+    /// nothing
+    fn is_valid(&self) -> bool {
+        match self {
+            Ranking::Weighted(weights) => weights.values().all(|w| w.is_finite()),
+            Ranking::Lexicographic(_) => true,
+        }
+    }
+
+    /// a partial plan never ranks better than it did before the next
+    /// action, false once a negative weight rewards some objective
+    fn only_gets_worse(&self) -> bool {
+        match self {
+            Ranking::Weighted(weights) => weights.values().all(|w| *w >= 0.0),
+            Ranking::Lexicographic(_) => true,
+        }
+    }
+
+    fn compare(&self, names: &[String], a: &[usize], b: &[usize]) -> Ordering {
+        let value = |v: &[usize], name: &str| {
+            names
+                .iter()
+                .position(|n| n == name)
+                .map(|i| v[i])
+                .unwrap_or(0)
+        };
+
+        match self {
+            Ranking::Weighted(weights) => {
+                let score = |v: &[usize]| -> f64 {
+                    weights
+                        .iter()
+                        .map(|(name, weight)| weight * value(v, name) as f64)
+                        .sum()
+                };
+                score(a).partial_cmp(&score(b)).unwrap_or(Ordering::Equal)
+            }
+            Ranking::Lexicographic(order) => order
+                .iter()
+                .map(|name| value(a, name).cmp(&value(b, name)))
+                .find(|o| *o != Ordering::Equal)
+                .unwrap_or(Ordering::Equal),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ObjectivePlan {
+    pub plan: PlanResult,
+    pub objectives: BTreeMap<String, usize>,
+}
+
+/// `a` is at least as good as `b` in every objective
+fn weakly_dominates(a: &[usize], b: &[usize]) -> bool {
+    a.iter().zip(b.iter()).all(|(x, y)| x <= y)
+}
+
+trait Collector {
+    /// partial plans only get more expensive, so they can be dropped early
+    fn prunes(&self, objectives: &[usize]) -> bool;
+    fn found(&mut self, node_index: usize, objectives: Vec<usize>);
+}
+
+struct Best<'r> {
+    ranking: &'r Ranking,
+    names: &'r [String],
+    best: Option<(usize, Vec<usize>)>,
+}
+
+impl<'r> Collector for Best<'r> {
+    fn prunes(&self, objectives: &[usize]) -> bool {
+        match &self.best {
+            Some((_, best)) if self.ranking.only_gets_worse() => {
+                self.ranking.compare(self.names, objectives, best) == Ordering::Greater
+            }
+            _ => false,
+        }
+    }
+
+    fn found(&mut self, node_index: usize, objectives: Vec<usize>) {
+        let better = match &self.best {
+            Some((_, best)) => {
+                self.ranking.compare(self.names, &objectives, best) == Ordering::Less
+            }
+            None => true,
+        };
+        if better {
+            self.best = Some((node_index, objectives));
+        }
+    }
+}
+
+struct Front {
+    plans: Vec<(usize, Vec<usize>)>,
+}
+
+impl Collector for Front {
+    fn prunes(&self, objectives: &[usize]) -> bool {
+        self.plans
+            .iter()
+            .any(|(_, p)| weakly_dominates(p, objectives))
+    }
+
+    fn found(&mut self, node_index: usize, objectives: Vec<usize>) {
+        if self.prunes(&objectives) {
+            return;
+        }
+        self.plans
+            .retain(|(_, p)| !weakly_dominates(&objectives, p));
+        self.plans.push((node_index, objectives));
+    }
+}
+
+impl<'a> Finder<'a> {
+    /// `"cost"` followed by every named cost used by an action, sorted
+    pub fn objective_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .config
+            .actions
+            .values()
+            .flat_map(|a| a.costs.keys().cloned())
+            .filter(|n| n != BASE_OBJECTIVE)
+            .collect();
+        names.sort();
+        names.dedup();
+        names.insert(0, BASE_OBJECTIVE.to_string());
+        names
+    }
+
+    /// Best plan according to `ranking` instead of the plain cost, `None` when
+    /// a weight is NaN or infinite
+    pub fn plan_ranked(&mut self, ranking: &Ranking) -> Option<ObjectivePlan> {
+        if !ranking.is_valid() {
+            return None;
+        }
+        let names = self.objective_names();
+        let mut best = Best {
+            ranking,
+            names: &names,
+            best: None,
+        };
+        self.search_objectives(&names, &mut best);
+
+        best.best
+            .map(|(node_index, objectives)| self.objective_plan(&names, node_index, objectives))
+    }
+
+    /// All plans which no other plan beats in every objective at once
+    pub fn pareto_front(&mut self) -> Vec<ObjectivePlan> {
+        let names = self.objective_names();
+        let mut front = Front { plans: vec![] };
+        self.search_objectives(&names, &mut front);
+
+        front
+            .plans
+            .into_iter()
+            .map(|(node_index, objectives)| self.objective_plan(&names, node_index, objectives))
+            .collect()
+    }
+
+    fn objective_plan(
+        &self,
+        names: &[String],
+        node_index: usize,
+        objectives: Vec<usize>,
+    ) -> ObjectivePlan {
+        ObjectivePlan {
+            plan: self.plan_result(node_index),
+            objectives: names.iter().cloned().zip(objectives).collect(),
+        }
+    }
+
+    fn search_objectives(&mut self, names: &[String], collector: &mut dyn Collector) {
+        self.nodes.clear();
+        self.nodes.push(Gnode {
+            id: String::from("start"),
+            from_node: None,
+            state: self.config.worldState.clone(),
+            running_cost: 0,
+            goal_progress: self.start_progress(),
+        });
+
+        let start = vec![0; names.len()];
+        if self.nodes[0].goal_progress >= self.config.goal_target() {
+            collector.found(0, start);
+            return;
+        }
+
+        let mut used = vec![false; self.action_names.len()];
+        self.expand_objectives(names, 0, &start, &mut used, collector);
+    }
+
+    fn expand_objectives(
+        &mut self,
+        names: &[String],
+        node_index: usize,
+        objectives: &[usize],
+        used: &mut Vec<bool>,
+        collector: &mut dyn Collector,
+    ) {
+        let config = self.config;
+        let target = config.goal_target();
+
+        for action_index in 0..self.action_names.len() {
+            if used[action_index] {
+                continue;
+            }
+
+            let name = self.action_names[action_index];
+            let action = &config.actions[name];
+            let node = &self.nodes[node_index];
+            if !action.are_preconditions_met(&node.state) {
+                continue;
+            }
+
+            let cost = node.running_cost + action.cost;
+            if cost > MAX_PLAN_COST {
+                continue;
+            }
+
+            let next_objectives: Vec<usize> = names
+                .iter()
+                .zip(objectives.iter())
+                .map(|(objective, total)| {
+                    let step = if objective == BASE_OBJECTIVE {
+                        action.cost
+                    } else {
+                        action.costs.get(objective).copied().unwrap_or(0)
+                    };
+                    total + step
+                })
+                .collect();
+
+            if collector.prunes(&next_objectives) {
+                continue;
+            }
+
+            let mut next_state = node.state.clone();
+            action.update_with_post_conditions(&mut next_state);
+            let goal_progress = config.goal_progress(&next_state, node.goal_progress);
+
+            self.nodes.push(Gnode {
+                id: name.to_string(),
+                from_node: Some(node_index),
+                state: next_state,
+                running_cost: cost,
+                goal_progress,
+            });
+            let next_index = self.nodes.len() - 1;
+
+            if goal_progress >= target {
+                collector.found(next_index, next_objectives);
+            } else {
+                used[action_index] = true;
+                self.expand_objectives(names, next_index, &next_objectives, used, collector);
+                used[action_index] = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::PlanConfig;
+
+    fn approach_config() -> PlanConfig {
+        serde_json::from_str(
+            r#"{
+                "actions": {
+                    "sneak": { "cost": 1, "costs": { "time": 5, "noise": 0 }, "pre_state": {}, "post_state": { "atTarget": true } },
+                    "walk": { "cost": 1, "costs": { "time": 3, "noise": 2 }, "pre_state": {}, "post_state": { "atTarget": true } },
+                    "sprint": { "cost": 1, "costs": { "time": 1, "noise": 5 }, "pre_state": {}, "post_state": { "atTarget": true } },
+                    "crawl": { "cost": 1, "costs": { "time": 6, "noise": 1 }, "pre_state": {}, "post_state": { "atTarget": true } }
+                },
+                "goals": [{ "name": "arrive", "state": { "atTarget": true } }],
+                "worldState": { "atTarget": false }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn first_action(plan: Option<ObjectivePlan>) -> Option<String> {
+        plan.and_then(|p| p.plan.actions.first().cloned())
+    }
+
+    #[test]
+    fn ranks_by_weighted_sum() {
+        let config = approach_config();
+        let mut finder = Finder::new(&config);
+
+        let fast = Ranking::Weighted([("time".to_string(), 1.0)].into_iter().collect());
+        assert_eq!(
+            first_action(finder.plan_ranked(&fast)),
+            Some("sprint".to_string())
+        );
+
+        let quiet_then_fast = Ranking::Weighted(
+            [("noise".to_string(), 1.0), ("time".to_string(), 0.1)]
+                .into_iter()
+                .collect(),
+        );
+        let plan = finder.plan_ranked(&quiet_then_fast);
+        assert_eq!(
+            plan.as_ref().map(|p| p.objectives["time"]),
+            Some(5),
+            "{:?}",
+            plan
+        );
+        assert_eq!(first_action(plan), Some("sneak".to_string()));
+    }
+
+    #[test]
+    fn searches_every_plan_with_negative_weights() {
+        let config: PlanConfig = serde_json::from_str(
+            r#"{
+                "actions": {
+                    "go": { "cost": 1, "pre_state": {}, "post_state": { "atTarget": true } },
+                    "open chest": { "cost": 2, "pre_state": {}, "post_state": { "chestOpen": true } },
+                    "take loot": { "cost": 1, "costs": { "loot": 5 }, "pre_state": { "chestOpen": true }, "post_state": { "looted": true } }
+                },
+                "goals": [{ "name": "arrive", "state": { "atTarget": true } }],
+                "worldState": { "atTarget": false, "chestOpen": false, "looted": false }
+            }"#,
+        )
+        .unwrap();
+        let mut finder = Finder::new(&config);
+
+        // opening the chest looks worse than going straight until the loot is taken
+        let greedy = Ranking::Weighted(
+            [("cost".to_string(), 1.0), ("loot".to_string(), -1.0)]
+                .into_iter()
+                .collect(),
+        );
+        let plan = finder.plan_ranked(&greedy);
+        assert_eq!(
+            plan.map(|p| p.plan.actions),
+            Some(vec![
+                "open chest".to_string(),
+                "take loot".to_string(),
+                "go".to_string()
+            ])
+        );
+
+        let broken = Ranking::Weighted([("loot".to_string(), f64::NAN)].into_iter().collect());
+        assert_eq!(finder.plan_ranked(&broken), None);
+    }
+
+    #[test]
+    fn ranks_lexicographically() {
+        let config = approach_config();
+        let mut finder = Finder::new(&config);
+
+        let ranking = Ranking::Lexicographic(vec!["noise".to_string(), "time".to_string()]);
+        assert_eq!(
+            first_action(finder.plan_ranked(&ranking)),
+            Some("sneak".to_string())
+        );
+
+        let ranking: Ranking = serde_json::from_str(r#"{ "lexicographic": ["time"] }"#).unwrap();
+        assert_eq!(
+            first_action(finder.plan_ranked(&ranking)),
+            Some("sprint".to_string())
+        );
+    }
+
+    #[test]
+    fn returns_pareto_front() {
+        let config = approach_config();
+        let mut front: Vec<String> = Finder::new(&config)
+            .pareto_front()
+            .into_iter()
+            .map(|p| p.plan.actions.join(","))
+            .collect();
+        front.sort();
+
+        // crawl is both slower and louder than sneak
+        assert_eq!(front, vec!["sneak", "sprint", "walk"]);
+    }
+}
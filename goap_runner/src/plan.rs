@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::configs::{hasmap_contains, GoalMode, PlanConfig};
+
+/// Part of a plan, `actions[start..end]`, which ends with `goal` being reached
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GoalSegment {
+    pub goal: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlanResult {
+    pub actions: Vec<String>,
+    pub cost: usize,
+    pub segments: Vec<GoalSegment>,
+}
+
+pub(crate) fn goal_target(mode: GoalMode, goal_count: usize) -> usize {
+    match mode {
+        GoalMode::Any | GoalMode::All => 1,
+        GoalMode::Sequence => goal_count,
+    }
+}
+
+/// `holds(goal_index)` tells if a goal is met by the state being checked
+pub(crate) fn goal_progress(
+    mode: GoalMode,
+    goal_count: usize,
+    previous: usize,
+    holds: impl Fn(usize) -> bool,
+) -> usize {
+    match mode {
+        GoalMode::Any => (0..goal_count).any(holds) as usize,
+        GoalMode::All => (0..goal_count).all(holds) as usize,
+        GoalMode::Sequence => {
+            let mut progress = previous;
+            while progress < goal_count && holds(progress) {
+                progress += 1;
+            }
+            progress
+        }
+    }
+}
+
+/// `Any` starts at 0 so that a plan always has at least one action,
+/// which is how the planner always behaved
+pub(crate) fn start_progress(
+    mode: GoalMode,
+    goal_count: usize,
+    holds: impl Fn(usize) -> bool,
+) -> usize {
+    match mode {
+        GoalMode::Any => 0,
+        _ => goal_progress(mode, goal_count, 0, holds),
+    }
+}
+
+/// Splits a found plan into goal segments. `progress[k]` is the progress after
+/// `k` actions and `holds(goal_index, k)` tells if a goal is met after `k` actions.
+pub(crate) fn goal_segments(
+    mode: GoalMode,
+    goal_names: &[&str],
+    progress: &[usize],
+    holds: impl Fn(usize, usize) -> bool,
+) -> Vec<GoalSegment> {
+    let last = progress.len() - 1;
+
+    match mode {
+        GoalMode::Any => (0..goal_names.len())
+            .find(|&g| holds(g, last))
+            .map(|g| GoalSegment {
+                goal: goal_names[g].to_string(),
+                start: 0,
+                end: last,
+            })
+            .into_iter()
+            .collect(),
+        GoalMode::All => (0..goal_names.len())
+            .map(|g| {
+                // first step after which the goal keeps holding until the end
+                let mut end = last;
+                while end > 0 && holds(g, end - 1) {
+                    end -= 1;
+                }
+                GoalSegment {
+                    goal: goal_names[g].to_string(),
+                    start: 0,
+                    end,
+                }
+            })
+            .collect(),
+        GoalMode::Sequence => {
+            let mut segments: Vec<GoalSegment> = Vec::with_capacity(goal_names.len());
+            let mut start = 0;
+            let mut reached = 0;
+
+            for (step, &step_progress) in progress.iter().enumerate() {
+                while reached < step_progress {
+                    segments.push(GoalSegment {
+                        goal: goal_names[reached].to_string(),
+                        start,
+                        end: step,
+                    });
+                    start = step;
+                    reached += 1;
+                }
+            }
+
+            segments
+        }
+    }
+}
+
+impl PlanConfig {
+    /// Progress value a node needs for the plan to be complete
+    pub fn goal_target(&self) -> usize {
+        goal_target(self.goal_mode, self.goals.len())
+    }
+
+    /// Progress towards the goals after reaching `state`, given the progress of the node before.
+    /// For `Any` and `All` it is 0 or 1, for `Sequence` it counts goals reached in order.
+    pub fn goal_progress(&self, state: &HashMap<String, bool>, previous: usize) -> usize {
+        goal_progress(self.goal_mode, self.goals.len(), previous, |g| {
+            hasmap_contains(state, &self.goals[g].state)
+        })
+    }
+
+    /// Progress of the world state itself
+    pub fn start_progress(&self) -> usize {
+        start_progress(self.goal_mode, self.goals.len(), |g| {
+            hasmap_contains(&self.worldState, &self.goals[g].state)
+        })
+    }
+
+    /// Splits a found plan into goal segments.
+    /// `states[k]` and `progress[k]` describe the world after `k` actions, `states[0]` is the start.
+    pub fn goal_segments(
+        &self,
+        states: &[&HashMap<String, bool>],
+        progress: &[usize],
+    ) -> Vec<GoalSegment> {
+        let names: Vec<&str> = self.goals.iter().map(|g| g.name.as_str()).collect();
+
+        goal_segments(self.goal_mode, &names, progress, |g, step| {
+            hasmap_contains(states[step], &self.goals[g].state)
+        })
+    }
+}
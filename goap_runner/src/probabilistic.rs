@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::configs::{PlanConfig, WorldState, MAX_PLAN_COST};
+
+/// expected cost of a state from which the goal can not be reached
+pub const DEAD_END_COST: f64 = MAX_PLAN_COST as f64;
+/// states explored before the rest is treated as dead ends
+pub const MAX_POLICY_STATES: usize = 10000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutcomeBranch {
+    pub probability: f64,
+    pub plan: ContingentPlan,
+}
+
+/// Policy unrolled from the start state, with one branch per action outcome
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContingentPlan {
+    Goal,
+    DeadEnd,
+    /// outcome leads back to a state earlier on this branch, continue from there
+    Repeat,
+    Step {
+        action: String,
+        expected_cost: f64,
+        branches: Vec<OutcomeBranch>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProbabilisticPlan {
+    pub expected_cost: f64,
+    /// chance of reaching the goal when following the plan
+    pub success_probability: f64,
+    pub plan: ContingentPlan,
+}
+
+type StateKey = (Vec<(String, bool)>, usize);
+
+struct PolicyState {
+    state: WorldState,
+    goal_progress: usize,
+    /// (action index, [(probability, next state index)])
+    transitions: Vec<(usize, Vec<(f64, usize)>)>,
+}
+
+/// Plans with uncertain action outcomes by minimizing expected cost.
+/// Reachable states are enumerated from the world state and solved with
+/// value iteration, failed outcomes may be retried.
+pub struct ProbabilisticPlanner<'a> {
+    config: &'a PlanConfig,
+    action_names: Vec<&'a String>,
+    states: Vec<PolicyState>,
+    index: HashMap<StateKey, usize>,
+}
+
+impl<'a> ProbabilisticPlanner<'a> {
+    pub fn new(config: &'a PlanConfig) -> Self {
+        let mut action_names: Vec<&'a String> = config.actions.keys().collect();
+        action_names.sort();
+
+        ProbabilisticPlanner {
+            config,
+            action_names,
+            states: vec![],
+            index: HashMap::new(),
+        }
+    }
+
+    fn intern(&mut self, state: WorldState, goal_progress: usize) -> usize {
+        let mut pairs: Vec<(String, bool)> = state.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        pairs.sort();
+
+        let key = (pairs, goal_progress);
+        if let Some(&existing) = self.index.get(&key) {
+            return existing;
+        }
+
+        self.states.push(PolicyState {
+            state,
+            goal_progress,
+            transitions: vec![],
+        });
+        self.index.insert(key, self.states.len() - 1);
+        self.states.len() - 1
+    }
+
+    fn explore(&mut self) {
+        self.states.clear();
+        self.index.clear();
+
+        let config = self.config;
+        let target = config.goal_target();
+        self.intern(config.worldState.clone(), config.start_progress());
+
+        let mut next_to_expand = 0;
+        while next_to_expand < self.states.len() && self.states.len() < MAX_POLICY_STATES {
+            let current = next_to_expand;
+            next_to_expand += 1;
+
+            if self.states[current].goal_progress >= target {
+                continue;
+            }
+
+            let mut transitions = vec![];
+            for action_index in 0..self.action_names.len() {
+                let action = &config.actions[self.action_names[action_index]];
+                if !action.are_preconditions_met(&self.states[current].state) {
+                    continue;
+                }
+
+                let mut branches = vec![];
+                for (probability, post_state) in action.possible_outcomes() {
+                    let mut next = self.states[current].state.clone();
+                    for (fact, value) in post_state {
+                        next.insert(fact.clone(), *value);
+                    }
+                    let progress = config.goal_progress(&next, self.states[current].goal_progress);
+                    branches.push((probability, self.intern(next, progress)));
+                }
+                transitions.push((action_index, branches));
+            }
+            self.states[current].transitions = transitions;
+        }
+    }
+
+    /// Expected cost of every explored state, and the best action index of each.
+    /// Values start at `DEAD_END_COST` and only go down, so a cycle of actions
+    /// costing nothing never looks better than reaching the goal.
+    fn solve(&self) -> (Vec<f64>, Vec<Option<usize>>) {
+        let target = self.config.goal_target();
+        let mut values: Vec<f64> = self
+            .states
+            .iter()
+            .map(|s| {
+                if s.goal_progress >= target {
+                    0.0
+                } else {
+                    DEAD_END_COST
+                }
+            })
+            .collect();
+        let mut policy: Vec<Option<usize>> = vec![None; self.states.len()];
+
+        for _ in 0..100000 {
+            let mut delta: f64 = 0.0;
+
+            for (state_index, state) in self.states.iter().enumerate() {
+                if state.transitions.is_empty() {
+                    continue;
+                }
+
+                let expected = |(action_index, branches): &(usize, Vec<(f64, usize)>)| {
+                    let cost = self.config.actions[self.action_names[*action_index]].cost as f64;
+                    cost + branches
+                        .iter()
+                        .map(|(p, next)| p * values[*next])
+                        .sum::<f64>()
+                };
+
+                // the current action stays unless another one is better, an action
+                // leading back to this state at no cost always ties with it
+                let mut best_action = policy[state_index];
+                let mut best = best_action.map_or(DEAD_END_COST, |t| {
+                    expected(&state.transitions[t]).min(DEAD_END_COST)
+                });
+                for (transition_index, transition) in state.transitions.iter().enumerate() {
+                    let expected = expected(transition);
+                    if expected < best - 1e-9 {
+                        best = expected;
+                        best_action = Some(transition_index);
+                    }
+                }
+
+                delta = delta.max((values[state_index] - best).abs());
+                values[state_index] = best;
+                policy[state_index] = best_action;
+            }
+
+            if delta < 1e-9 {
+                break;
+            }
+        }
+
+        (values, policy)
+    }
+
+    fn success_probability(&self, policy: &[Option<usize>]) -> Vec<f64> {
+        let target = self.config.goal_target();
+        let mut success: Vec<f64> = self
+            .states
+            .iter()
+            .map(|s| if s.goal_progress >= target { 1.0 } else { 0.0 })
+            .collect();
+
+        for _ in 0..100000 {
+            let mut delta: f64 = 0.0;
+            for (state_index, state) in self.states.iter().enumerate() {
+                if let Some(transition) = policy[state_index] {
+                    let chance: f64 = state.transitions[transition]
+                        .1
+                        .iter()
+                        .map(|(p, next)| p * success[*next])
+                        .sum();
+                    delta = delta.max((success[state_index] - chance).abs());
+                    success[state_index] = chance;
+                }
+            }
+            if delta < 1e-9 {
+                break;
+            }
+        }
+
+        success
+    }
+
+    fn unroll(
+        &self,
+        state_index: usize,
+        values: &[f64],
+        policy: &[Option<usize>],
+        path: &mut Vec<usize>,
+    ) -> ContingentPlan {
+        if self.states[state_index].goal_progress >= self.config.goal_target() {
+            return ContingentPlan::Goal;
+        }
+        if path.contains(&state_index) {
+            return ContingentPlan::Repeat;
+        }
+        let transition = match policy[state_index] {
+            Some(transition) => transition,
+            None => return ContingentPlan::DeadEnd,
+        };
+
+        let (action_index, branches) = &self.states[state_index].transitions[transition];
+        path.push(state_index);
+        let branches = branches
+            .iter()
+            .map(|(probability, next)| OutcomeBranch {
+                probability: *probability,
+                plan: self.unroll(*next, values, policy, path),
+            })
+            .collect();
+        path.pop();
+
+        ContingentPlan::Step {
+            action: self.action_names[*action_index].to_string(),
+            expected_cost: values[state_index],
+            branches,
+        }
+    }
+
+    /// Policy with the lowest expected cost, `None` when the goal is never reachable
+    pub fn plan(&mut self) -> Option<ProbabilisticPlan> {
+        self.explore();
+        let (values, policy) = self.solve();
+
+        if values[0] >= DEAD_END_COST {
+            return None;
+        }
+
+        let success = self.success_probability(&policy);
+        // a policy which never reaches the goal is no plan, whatever it costs
+        if success[0] < 1e-9 {
+            return None;
+        }
+        Some(ProbabilisticPlan {
+            expected_cost: values[0],
+            success_probability: success[0],
+            plan: self.unroll(0, &values, &policy, &mut vec![]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configs::Gaction;
+
+    fn ammo_config(pickup_chance: f64) -> PlanConfig {
+        let mut config: PlanConfig = serde_json::from_str(
+            r#"{
+                "actions": {
+                    "pickup Ammo": {
+                        "cost": 1,
+                        "pre_state": { "hasAmmo": false },
+                        "post_state": { "hasAmmo": true },
+                        "outcomes": [
+                            { "probability": 0.8, "post_state": { "hasAmmo": true } },
+                            { "probability": 0.2, "post_state": {} }
+                        ]
+                    },
+                    "buy Ammo": { "cost": 3, "pre_state": { "hasAmmo": false }, "post_state": { "hasAmmo": true } }
+                },
+                "goals": [{ "name": "armed", "state": { "hasAmmo": true } }],
+                "worldState": { "hasAmmo": false }
+            }"#,
+        )
+        .unwrap();
+
+        let pickup = config.actions.get_mut("pickup Ammo").unwrap();
+        pickup.outcomes[0].probability = pickup_chance;
+        pickup.outcomes[1].probability = 1.0 - pickup_chance;
+        config
+    }
+
+    fn first_action(plan: &ContingentPlan) -> Option<&str> {
+        match plan {
+            ContingentPlan::Step { action, .. } => Some(action),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn retries_likely_action() -> Result<(), String> {
+        let config = ammo_config(0.8);
+        assert!(!config.validate().has_errors());
+
+        let result = ProbabilisticPlanner::new(&config).plan().ok_or("no plan")?;
+
+        assert!((result.expected_cost - 1.25).abs() < 1e-6);
+        assert!((result.success_probability - 1.0).abs() < 1e-6);
+        match &result.plan {
+            ContingentPlan::Step {
+                action, branches, ..
+            } => {
+                assert_eq!(action, "pickup Ammo");
+                assert_eq!(branches[0].plan, ContingentPlan::Goal);
+                assert_eq!(branches[1].plan, ContingentPlan::Repeat);
+            }
+            other => return Err(format!("expected a step, got {:?}", other)),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn prefers_certain_action_over_unlikely_one() -> Result<(), String> {
+        let config = ammo_config(0.2);
+        let result = ProbabilisticPlanner::new(&config).plan().ok_or("no plan")?;
+
+        assert_eq!(first_action(&result.plan), Some("buy Ammo"));
+        assert!((result.expected_cost - 3.0).abs() < 1e-6);
+        Ok(())
+    }
+
+    #[test]
+    fn avoids_risk_of_dead_end() -> Result<(), String> {
+        let config: PlanConfig = serde_json::from_str(
+            r#"{
+                "actions": {
+                    "charge": {
+                        "cost": 1,
+                        "pre_state": { "alive": true, "enemyAlive": true },
+                        "post_state": { "enemyAlive": false },
+                        "outcomes": [
+                            { "probability": 0.9, "post_state": { "enemyAlive": false } },
+                            { "probability": 0.1, "post_state": { "alive": false } }
+                        ]
+                    },
+                    "snipe": { "cost": 20, "pre_state": { "alive": true, "enemyAlive": true }, "post_state": { "enemyAlive": false } }
+                },
+                "goals": [{ "name": "win", "state": { "enemyAlive": false, "alive": true } }],
+                "worldState": { "alive": true, "enemyAlive": true }
+            }"#,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let result = ProbabilisticPlanner::new(&config).plan().ok_or("no plan")?;
+        assert_eq!(first_action(&result.plan), Some("snipe"));
+        assert!((result.success_probability - 1.0).abs() < 1e-6);
+        Ok(())
+    }
+
+    #[test]
+    fn ignores_actions_which_change_nothing() -> Result<(), String> {
+        let mut config = ammo_config(0.8);
+        config.actions.insert(
+            "idle".to_string(),
+            Gaction {
+                cost: 0,
+                ..Default::default()
+            },
+        );
+
+        let result = ProbabilisticPlanner::new(&config).plan().ok_or("no plan")?;
+        assert_eq!(first_action(&result.plan), Some("pickup Ammo"));
+        assert!((result.expected_cost - 1.25).abs() < 1e-6);
+        assert!((result.success_probability - 1.0).abs() < 1e-6);
+
+        // idling is all there is to do
+        config.actions.retain(|name, _| name == "idle");
+        assert_eq!(ProbabilisticPlanner::new(&config).plan(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn reports_invalid_probabilities() {
+        let mut config = ammo_config(0.8);
+        config.actions.get_mut("pickup Ammo").unwrap().outcomes[1].probability = 0.5;
+
+        let issues = config.validate().issues;
+        assert!(issues
+            .iter()
+            .any(|i| i.path == "$.actions[\"pickup Ammo\"].outcomes"));
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::configs::{Goal, GoalMode, PlanConfig, WorldState};
+use crate::plan::PlanResult;
+use crate::Finder;
+
+/// longest sub-plan inserted before falling back to a full search
+pub const MAX_REPAIR_ACTIONS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RepairKind {
+    /// the rest of the old plan still works from the observed state
+    Resumed,
+    /// `inserted` sub-plans re-establish preconditions of old steps,
+    /// `skipped` old steps could not be applied any more
+    Patched {
+        inserted: Vec<String>,
+        skipped: Vec<String>,
+    },
+    /// the old steps do not reach the goals any more, planned again with `Finder`
+    Replanned,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RepairedPlan {
+    /// plan from the observed state on
+    pub plan: PlanResult,
+    pub kind: RepairKind,
+    /// actions taken over from the old plan
+    pub kept: usize,
+}
+
+/// Repairs `old_plan` after its step `k` failed and the world turned out to be
+/// `observed_state`. The old steps from `k` on are replayed in order, a step
+/// whose preconditions no longer hold gets a short sub-plan inserted before
+/// it, or is skipped when none exists. Only when the result does not reach
+/// the goals a new plan is searched. Goals count from the observed state,
+/// progress of `Sequence` goals before step `k` is not remembered.
+pub fn repair_plan(
+    config: &PlanConfig,
+    old_plan: &PlanResult,
+    k: usize,
+    observed_state: &WorldState,
+) -> Option<RepairedPlan> {
+    let observed = PlanConfig {
+        actions: config.actions.clone(),
+        goals: config.goals.clone(),
+        worldState: observed_state.clone(),
+        goal_mode: config.goal_mode,
+        ..Default::default()
+    };
+    let target = observed.goal_target();
+
+    let mut state = observed_state.clone();
+    let mut progress = observed.start_progress();
+    // (action, taken from the old plan)
+    let mut steps: Vec<(String, bool)> = vec![];
+    let mut skipped: Vec<String> = vec![];
+
+    for name in old_plan.actions.get(k..).unwrap_or_default() {
+        if progress >= target {
+            break;
+        }
+
+        let action = match config.actions.get(name) {
+            Some(action) => action,
+            None => {
+                skipped.push(name.clone());
+                continue;
+            }
+        };
+
+        if !action.are_preconditions_met(&state) {
+            match sub_plan(&observed, &state, &action.pre_state) {
+                Some(inserted) if inserted.len() <= MAX_REPAIR_ACTIONS => {
+                    for inserted_name in inserted {
+                        config.actions[&inserted_name].update_with_post_conditions(&mut state);
+                        progress = observed.goal_progress(&state, progress);
+                        steps.push((inserted_name, false));
+                    }
+                }
+                _ => {
+                    skipped.push(name.clone());
+                    continue;
+                }
+            }
+        }
+
+        action.update_with_post_conditions(&mut state);
+        progress = observed.goal_progress(&state, progress);
+        steps.push((name.clone(), true));
+    }
+
+    let actions = steps.iter().map(|(name, _)| name.clone()).collect();
+    if let Some(plan) = replay(&observed, actions) {
+        let steps = &steps[..plan.actions.len()];
+        let inserted: Vec<String> = steps
+            .iter()
+            .filter(|(_, old)| !old)
+            .map(|(name, _)| name.clone())
+            .collect();
+        let kind = if inserted.is_empty() && skipped.is_empty() {
+            RepairKind::Resumed
+        } else {
+            RepairKind::Patched { inserted, skipped }
+        };
+
+        return Some(RepairedPlan {
+            kept: steps.iter().filter(|(_, old)| *old).count(),
+            plan,
+            kind,
+        });
+    }
+
+    Finder::new(&observed).plan().map(|plan| RepairedPlan {
+        plan,
+        kind: RepairKind::Replanned,
+        kept: 0,
+    })
+}
+
+/// Cheapest actions which make `goal` hold, starting from `state`
+fn sub_plan(
+    config: &PlanConfig,
+    state: &WorldState,
+    goal: &HashMap<String, bool>,
+) -> Option<Vec<String>> {
+    let precondition = PlanConfig {
+        actions: config.actions.clone(),
+        goals: vec![Goal {
+            name: "repair".to_string(),
+            state: goal.clone(),
+        }],
+        worldState: state.clone(),
+        goal_mode: GoalMode::All,
+        ..Default::default()
+    };
+
+    Finder::new(&precondition).plan().map(|plan| plan.actions)
+}
+
+/// Executes `actions` from the config world state, `None` when one of them is
+/// not applicable or the goals are not reached. Actions after the goals are
+/// reached are dropped.
+fn replay(config: &PlanConfig, mut actions: Vec<String>) -> Option<PlanResult> {
+    let target = config.goal_target();
+    let mut states: Vec<WorldState> = vec![config.worldState.clone()];
+    let mut progress: Vec<usize> = vec![config.start_progress()];
+    let mut cost = 0;
+
+    for (step, name) in actions.iter().enumerate() {
+        if progress[step] >= target {
+            break;
+        }
+
+        let action = config.actions.get(name)?;
+        if !action.are_preconditions_met(&states[step]) {
+            return None;
+        }
+
+        let mut next = states[step].clone();
+        action.update_with_post_conditions(&mut next);
+        progress.push(config.goal_progress(&next, progress[step]));
+        states.push(next);
+        cost += action.cost;
+    }
+
+    if *progress.last()? < target {
+        return None;
+    }
+
+    actions.truncate(states.len() - 1);
+    let state_refs: Vec<&WorldState> = states.iter().collect();
+    Some(PlanResult {
+        segments: config.goal_segments(&state_refs, &progress),
+        actions,
+        cost,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration::load_versioned;
+
+    fn sample_config() -> Result<PlanConfig, String> {
+        let contents = std::fs::read_to_string("./actions.json").map_err(|e| e.to_string())?;
+        load_versioned(&contents).map_err(|e| e.to_string())
+    }
+
+    /// World state expected before step `k` of `plan`
+    fn state_before(config: &PlanConfig, plan: &PlanResult, k: usize) -> WorldState {
+        let mut state = config.worldState.clone();
+        for name in &plan.actions[..k] {
+            config.actions[name].update_with_post_conditions(&mut state);
+        }
+        state
+    }
+
+    #[test]
+    fn resumes_when_nothing_changed() -> Result<(), String> {
+        let config = sample_config()?;
+        let plan = Finder::new(&config).plan().ok_or("no plan")?;
+
+        let observed = state_before(&config, &plan, 2);
+        let repaired = repair_plan(&config, &plan, 2, &observed).ok_or("no repair")?;
+
+        assert_eq!(repaired.kind, RepairKind::Resumed);
+        assert_eq!(repaired.plan.actions, plan.actions[2..]);
+        assert_eq!(repaired.kept, plan.actions.len() - 2);
+        Ok(())
+    }
+
+    #[test]
+    fn inserts_sub_plan_for_failed_step() -> Result<(), String> {
+        let config = sample_config()?;
+        let plan = Finder::new(&config).plan().ok_or("no plan")?;
+        let k = plan
+            .actions
+            .iter()
+            .position(|a| a == "pickup Ammo")
+            .ok_or("no ammo pickup")?;
+
+        // the ammo which was seen is gone
+        let mut observed = state_before(&config, &plan, k);
+        observed.insert("seeAmmo".to_string(), false);
+
+        let repaired = repair_plan(&config, &plan, k, &observed).ok_or("no repair")?;
+        assert_eq!(
+            repaired.kind,
+            RepairKind::Patched {
+                inserted: vec!["search Ammo".to_string()],
+                skipped: vec![]
+            }
+        );
+        assert_eq!(repaired.plan.actions[1..], plan.actions[k..]);
+        assert_eq!(repaired.kept, plan.actions.len() - k);
+        assert_eq!(repaired.plan.cost, 1 + plan.cost - k);
+        Ok(())
+    }
+
+    #[test]
+    fn skips_steps_which_are_no_longer_needed() -> Result<(), String> {
+        let config = sample_config()?;
+        let plan = Finder::new(&config).plan().ok_or("no plan")?;
+
+        let k = plan
+            .actions
+            .iter()
+            .position(|a| a == "search Weapon")
+            .ok_or("no weapon search")?;
+
+        // searching the weapon failed, but the enemy dropped one right here
+        let mut observed = state_before(&config, &plan, k);
+        observed.insert("hasWeapon".to_string(), true);
+
+        let repaired = repair_plan(&config, &plan, k, &observed).ok_or("no repair")?;
+        let skipped = vec!["search Weapon".to_string(), "pickupWeapon".to_string()];
+        assert_eq!(
+            repaired.kind,
+            RepairKind::Patched {
+                inserted: vec![],
+                skipped: skipped.clone()
+            }
+        );
+
+        let rest: Vec<String> = plan.actions[k..]
+            .iter()
+            .filter(|a| !skipped.contains(a))
+            .cloned()
+            .collect();
+        assert_eq!(repaired.plan.actions, rest);
+        assert_eq!(repaired.kept, rest.len());
+        Ok(())
+    }
+
+    #[test]
+    fn replans_when_old_steps_are_unusable() -> Result<(), String> {
+        let config = sample_config()?;
+        let plan = Finder::new(&config).plan().ok_or("no plan")?;
+
+        // a bomb blocks getting any weapon, every remaining step needs one
+        let mut observed = config.worldState.clone();
+        observed.insert("hasBomb".to_string(), true);
+
+        let repaired = repair_plan(&config, &plan, 0, &observed).ok_or("no repair")?;
+        assert_eq!(repaired.kind, RepairKind::Replanned);
+        assert_eq!(repaired.plan.actions, vec!["Change mood".to_string()]);
+        assert_eq!(repaired.kept, 0);
+
+        observed.insert("readyForPeace".to_string(), false);
+        observed.insert("injured".to_string(), true);
+        assert_eq!(repair_plan(&config, &plan, 0, &observed), None);
+        Ok(())
+    }
+}
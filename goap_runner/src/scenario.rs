@@ -0,0 +1,422 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::configs::{hasmap_contains, Gaction, Goal, GoalMode, PlanConfig, WorldState};
+use crate::Finder;
+
+/// External change of the world at the start of `tick`
+#[derive(Debug, Deserialize, Clone)]
+pub struct ScenarioEvent {
+    pub tick: usize,
+    pub state: HashMap<String, bool>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ScenarioAgent {
+    pub name: String,
+    pub actions: HashMap<String, Gaction>,
+    pub goals: Vec<Goal>,
+    #[serde(default)]
+    pub goal_mode: GoalMode,
+}
+
+/// Scripted world in which agents plan with `Finder` and act one action per tick
+#[derive(Debug, Deserialize, Clone)]
+pub struct Scenario {
+    #[serde(rename = "worldState")]
+    pub world_state: WorldState,
+    pub agents: Vec<ScenarioAgent>,
+    #[serde(default)]
+    pub events: Vec<ScenarioEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LogEntry {
+    Event {
+        tick: usize,
+        changes: BTreeMap<String, bool>,
+    },
+    Planned {
+        tick: usize,
+        agent: String,
+        actions: Vec<String>,
+        cost: usize,
+    },
+    NoPlan {
+        tick: usize,
+        agent: String,
+    },
+    Action {
+        tick: usize,
+        agent: String,
+        action: String,
+        changes: BTreeMap<String, bool>,
+    },
+    GoalReached {
+        tick: usize,
+        agent: String,
+        /// goals holding at that moment
+        goals: Vec<String>,
+    },
+}
+
+impl LogEntry {
+    pub fn tick(&self) -> usize {
+        match self {
+            LogEntry::Event { tick, .. }
+            | LogEntry::Planned { tick, .. }
+            | LogEntry::NoPlan { tick, .. }
+            | LogEntry::Action { tick, .. }
+            | LogEntry::GoalReached { tick, .. } => *tick,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ScenarioLog {
+    pub entries: Vec<LogEntry>,
+}
+
+impl ScenarioLog {
+    /// World state after all changes logged before `tick`
+    pub fn state_at(&self, initial: &WorldState, tick: usize) -> WorldState {
+        let mut state = initial.clone();
+        for entry in self.entries.iter().take_while(|e| e.tick() < tick) {
+            if let LogEntry::Event { changes, .. } | LogEntry::Action { changes, .. } = entry {
+                state.extend(changes.iter().map(|(k, v)| (k.clone(), *v)));
+            }
+        }
+        state
+    }
+
+    /// World state after replaying every logged change
+    pub fn replay(&self, initial: &WorldState) -> WorldState {
+        self.state_at(initial, usize::MAX)
+    }
+
+    /// First tick at which `agent` reached its goals while `goal` held
+    pub fn goal_reached_at(&self, agent: &str, goal: &str) -> Option<usize> {
+        self.entries.iter().find_map(|e| match e {
+            LogEntry::GoalReached {
+                tick,
+                agent: a,
+                goals,
+            } if a == agent && goals.iter().any(|g| g == goal) => Some(*tick),
+            _ => None,
+        })
+    }
+
+    pub fn actions_of(&self, agent: &str) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter_map(|e| match e {
+                LogEntry::Action {
+                    agent: a, action, ..
+                } if a == agent => Some(action.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+struct AgentRun {
+    name: String,
+    config: PlanConfig,
+    plan: VecDeque<String>,
+    /// progress towards the goals so far, `Sequence` goals stay reached
+    progress: usize,
+    reached: bool,
+    /// last planning failed, `NoPlan` is logged once until a plan is found
+    stuck: bool,
+}
+
+impl AgentRun {
+    /// Whether the goals hold in `state`, logs when they are newly reached
+    fn check_goals(&mut self, state: &WorldState, tick: usize, log: &mut ScenarioLog) -> bool {
+        self.config.worldState = state.clone();
+        // unlike planning, a goal which already holds counts in every mode
+        self.progress = self.config.goal_progress(state, self.progress);
+        if self.progress < self.config.goal_target() {
+            self.reached = false;
+            return false;
+        }
+
+        if !self.reached {
+            self.reached = true;
+            self.plan.clear();
+            log.entries.push(LogEntry::GoalReached {
+                tick,
+                agent: self.name.clone(),
+                goals: holding_goals(&self.config),
+            });
+        }
+        true
+    }
+}
+
+/// Deterministic tick based simulation of a `Scenario`. In every tick the
+/// events of that tick are applied first, then each agent in turn either
+/// rests at its goals or performs the next action of its plan. Agents plan
+/// again when their plan ran out or its next action is no longer applicable.
+pub struct Simulator<'s> {
+    scenario: &'s Scenario,
+    state: WorldState,
+    tick: usize,
+    agents: Vec<AgentRun>,
+    log: ScenarioLog,
+}
+
+impl<'s> Simulator<'s> {
+    pub fn new(scenario: &'s Scenario) -> Self {
+        let agents = scenario
+            .agents
+            .iter()
+            .map(|agent| AgentRun {
+                name: agent.name.clone(),
+                config: PlanConfig {
+                    actions: agent.actions.clone(),
+                    goals: agent.goals.clone(),
+                    worldState: scenario.world_state.clone(),
+                    goal_mode: agent.goal_mode,
+                    ..Default::default()
+                },
+                plan: VecDeque::new(),
+                progress: 0,
+                reached: false,
+                stuck: false,
+            })
+            .collect();
+
+        Simulator {
+            scenario,
+            state: scenario.world_state.clone(),
+            tick: 0,
+            agents,
+            log: ScenarioLog::default(),
+        }
+    }
+
+    pub fn state(&self) -> &WorldState {
+        &self.state
+    }
+
+    pub fn log(&self) -> &ScenarioLog {
+        &self.log
+    }
+
+    /// Runs `ticks` more ticks and returns the whole log
+    pub fn run(&mut self, ticks: usize) -> &ScenarioLog {
+        for _ in 0..ticks {
+            self.step();
+        }
+        &self.log
+    }
+
+    pub fn step(&mut self) {
+        let tick = self.tick;
+
+        for event in self.scenario.events.iter().filter(|e| e.tick == tick) {
+            let changes = apply(&mut self.state, &event.state);
+            if !changes.is_empty() {
+                self.log.entries.push(LogEntry::Event { tick, changes });
+            }
+        }
+
+        for agent in self.agents.iter_mut() {
+            if agent.check_goals(&self.state, tick, &mut self.log) {
+                continue;
+            }
+
+            let applicable = agent
+                .plan
+                .front()
+                .and_then(|name| agent.config.actions.get(name))
+                .is_some_and(|action| action.are_preconditions_met(&self.state));
+
+            if !applicable {
+                match Finder::new(&agent.config)
+                    .from_progress(agent.progress)
+                    .plan()
+                {
+                    Some(plan) => {
+                        agent.stuck = false;
+                        agent.plan = plan.actions.iter().cloned().collect();
+                        self.log.entries.push(LogEntry::Planned {
+                            tick,
+                            agent: agent.name.clone(),
+                            actions: plan.actions,
+                            cost: plan.cost,
+                        });
+                    }
+                    None => {
+                        if !agent.stuck {
+                            agent.stuck = true;
+                            self.log.entries.push(LogEntry::NoPlan {
+                                tick,
+                                agent: agent.name.clone(),
+                            });
+                        }
+                        agent.plan.clear();
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(name) = agent.plan.pop_front() {
+                let changes = apply(&mut self.state, &agent.config.actions[&name].post_state);
+                self.log.entries.push(LogEntry::Action {
+                    tick,
+                    agent: agent.name.clone(),
+                    action: name,
+                    changes,
+                });
+                agent.check_goals(&self.state, tick, &mut self.log);
+            }
+        }
+
+        self.tick += 1;
+    }
+}
+
+/// Applies `post_state`, returning only the facts which changed
+fn apply(state: &mut WorldState, post_state: &HashMap<String, bool>) -> BTreeMap<String, bool> {
+    let mut changes = BTreeMap::new();
+    for (fact, value) in post_state {
+        if state.insert(fact.clone(), *value) != Some(*value) {
+            changes.insert(fact.clone(), *value);
+        }
+    }
+    changes
+}
+
+fn holding_goals(config: &PlanConfig) -> Vec<String> {
+    config
+        .goals
+        .iter()
+        .filter(|g| hasmap_contains(&config.worldState, &g.state))
+        .map(|g| g.name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_scenario(events: &str) -> Result<Scenario, String> {
+        let contents = std::fs::read_to_string("./actions.json").map_err(|e| e.to_string())?;
+        let config: serde_json::Value =
+            serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+
+        let scenario = serde_json::json!({
+            "worldState": config["worldState"],
+            "agents": [
+                { "name": "soldier", "actions": config["actions"], "goals": config["goals"] }
+            ],
+            "events": serde_json::from_str::<serde_json::Value>(events).map_err(|e| e.to_string())?
+        });
+        serde_json::from_value(scenario).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn agent_finds_peace_after_event() -> Result<(), String> {
+        let mut scenario =
+            sample_scenario(r#"[{ "tick": 5, "state": { "readyForPeace": true } }]"#)?;
+        scenario
+            .world_state
+            .insert("readyForPeace".to_string(), false);
+        // nothing to fight
+        scenario.agents[0].goals.retain(|g| g.name == "Be peaceful");
+
+        let mut simulator = Simulator::new(&scenario);
+        let log = simulator.run(20);
+
+        let found_peace = log.goal_reached_at("soldier", "Be peaceful");
+        assert!(found_peace.is_some_and(|tick| tick <= 20), "{:?}", log);
+        assert_eq!(
+            log.entries[0],
+            LogEntry::NoPlan {
+                tick: 0,
+                agent: "soldier".to_string()
+            }
+        );
+        assert_eq!(log.actions_of("soldier"), vec!["Change mood"]);
+        assert_eq!(found_peace, Some(5));
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_sequence_goals_reached_before_replanning() -> Result<(), String> {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+                "worldState": { "fed": false, "rested": false, "bedFree": true },
+                "agents": [
+                    { "name": "cat", "goal_mode": "sequence", "actions": {
+                        "eat": { "cost": 1, "pre_state": {}, "post_state": { "fed": true } },
+                        "sleep": { "cost": 1, "pre_state": { "bedFree": true }, "post_state": { "rested": true } },
+                        "push dog off bed": { "cost": 1, "pre_state": {}, "post_state": { "bedFree": true } }
+                    }, "goals": [
+                        { "name": "eaten", "state": { "fed": true } },
+                        { "name": "slept", "state": { "rested": true } }
+                    ] }
+                ],
+                "events": [{ "tick": 1, "state": { "fed": false, "bedFree": false } }]
+            }"#,
+        )
+        .map_err(|e| e.to_string())?;
+
+        let mut simulator = Simulator::new(&scenario);
+        let log = simulator.run(5);
+
+        // getting hungry again does not undo having eaten first
+        assert_eq!(
+            log.actions_of("cat"),
+            vec!["eat", "push dog off bed", "sleep"],
+            "{}",
+            log.to_json()
+        );
+        assert_eq!(log.goal_reached_at("cat", "slept"), Some(2));
+        Ok(())
+    }
+
+    #[test]
+    fn replans_after_event_breaks_plan() -> Result<(), String> {
+        let scenario = sample_scenario(
+            r#"[{ "tick": 3, "state": { "hasWeapon": false, "hasAmmo": false } }]"#,
+        )?;
+
+        let mut simulator = Simulator::new(&scenario);
+        let log = simulator.run(20).clone();
+
+        let plans = log
+            .entries
+            .iter()
+            .filter(|e| matches!(e, LogEntry::Planned { .. }))
+            .count();
+        assert_eq!(plans, 2, "{}", log.to_json());
+        assert!(log.goal_reached_at("soldier", "Kill the enemy").is_some());
+        assert_eq!(
+            log.actions_of("soldier").last(),
+            Some(&"Attack with Weapon")
+        );
+
+        // replaying the logged changes ends in the simulated world
+        assert_eq!(&log.replay(&scenario.world_state), simulator.state());
+        assert_eq!(
+            log.state_at(&scenario.world_state, 3).get("hasWeapon"),
+            Some(&true)
+        );
+
+        // same scenario, same log
+        assert_eq!(Simulator::new(&scenario).run(20), &log);
+        let imported: ScenarioLog =
+            serde_json::from_str(&log.to_json()).map_err(|e| e.to_string())?;
+        assert_eq!(imported, log);
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::configs::{PlanConfig, WorldState, MAX_PLAN_COST};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScheduledAction {
+    pub action: String,
+    pub start: usize,
+    pub end: usize,
+    pub resources: Vec<String>,
+}
+
+/// Actions with start and end times, ordered by the logical plan order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Schedule {
+    pub steps: Vec<ScheduledAction>,
+    pub makespan: usize,
+    pub cost: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimelineEntry {
+    pub action: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TimelineTrack {
+    pub resource: String,
+    pub entries: Vec<TimelineEntry>,
+}
+
+/// Schedule grouped into one track per resource, actions without resources go to "free"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Timeline {
+    pub makespan: usize,
+    pub cost: usize,
+    pub tracks: Vec<TimelineTrack>,
+}
+
+impl Schedule {
+    pub fn timeline(&self) -> Timeline {
+        let mut tracks: Vec<TimelineTrack> = vec![];
+
+        for step in &self.steps {
+            let entry = TimelineEntry {
+                action: step.action.clone(),
+                start: step.start,
+                end: step.end,
+            };
+            let free = ["free".to_string()];
+            let names = if step.resources.is_empty() {
+                &free[..]
+            } else {
+                &step.resources[..]
+            };
+
+            for name in names {
+                match tracks.iter_mut().find(|t| &t.resource == name) {
+                    Some(track) => track.entries.push(entry.clone()),
+                    None => tracks.push(TimelineTrack {
+                        resource: name.clone(),
+                        entries: vec![entry.clone()],
+                    }),
+                }
+            }
+        }
+
+        for track in tracks.iter_mut() {
+            track.entries.sort_by_key(|e| e.start);
+        }
+
+        Timeline {
+            makespan: self.makespan,
+            cost: self.cost,
+            tracks,
+        }
+    }
+
+    pub fn to_timeline_json(&self) -> String {
+        serde_json::to_string_pretty(&self.timeline()).unwrap_or_default()
+    }
+}
+
+/// Partial schedule of a plan prefix
+#[derive(Clone)]
+struct TemporalNode {
+    state: WorldState,
+    goal_progress: usize,
+    used: Vec<bool>,
+    /// when the last action writing a fact ends
+    written_at: HashMap<String, usize>,
+    /// when the last action reading a fact ends
+    read_until: HashMap<String, usize>,
+    resource_free_at: HashMap<String, usize>,
+    steps: Vec<ScheduledAction>,
+    makespan: usize,
+    cost: usize,
+}
+
+/// Plans with durative actions. Every action starts as soon as the facts it
+/// reads or writes are settled and its resources are free, so independent
+/// actions run in parallel. Among all plans the one with the smallest makespan
+/// wins, cost breaks ties.
+pub struct TemporalPlanner<'a> {
+    config: &'a PlanConfig,
+    action_names: Vec<&'a String>,
+}
+
+impl<'a> TemporalPlanner<'a> {
+    pub fn new(config: &'a PlanConfig) -> Self {
+        let mut action_names: Vec<&'a String> = config.actions.keys().collect();
+        action_names.sort();
+
+        TemporalPlanner {
+            config,
+            action_names,
+        }
+    }
+
+    pub fn schedule(&self) -> Option<Schedule> {
+        let start = TemporalNode {
+            state: self.config.worldState.clone(),
+            goal_progress: self.config.start_progress(),
+            used: vec![false; self.action_names.len()],
+            written_at: HashMap::new(),
+            read_until: HashMap::new(),
+            resource_free_at: HashMap::new(),
+            steps: vec![],
+            makespan: 0,
+            cost: 0,
+        };
+
+        if start.goal_progress >= self.config.goal_target() {
+            return Some(Schedule {
+                steps: vec![],
+                makespan: 0,
+                cost: 0,
+            });
+        }
+
+        let mut best: Option<Schedule> = None;
+        self.search(&start, &mut best);
+        best
+    }
+
+    fn search(&self, node: &TemporalNode, best: &mut Option<Schedule>) {
+        let target = self.config.goal_target();
+
+        for (action_index, name) in self.action_names.iter().enumerate() {
+            if node.used[action_index] {
+                continue;
+            }
+
+            let action = &self.config.actions[*name];
+            if !action.are_preconditions_met(&node.state) {
+                continue;
+            }
+
+            let cost = node.cost + action.cost;
+            if cost > MAX_PLAN_COST {
+                continue;
+            }
+
+            let mut start = 0;
+            for fact in action.pre_state.keys() {
+                start = start.max(node.written_at.get(fact).copied().unwrap_or(0));
+            }
+            for fact in action.post_state.keys() {
+                start = start.max(node.written_at.get(fact).copied().unwrap_or(0));
+                start = start.max(node.read_until.get(fact).copied().unwrap_or(0));
+            }
+            for resource in &action.resources {
+                start = start.max(node.resource_free_at.get(resource).copied().unwrap_or(0));
+            }
+            let end = start + action.duration;
+            let makespan = node.makespan.max(end);
+
+            if let Some(b) = best {
+                if (makespan, cost) >= (b.makespan, b.cost) {
+                    continue;
+                }
+            }
+
+            let mut next = node.clone();
+            next.used[action_index] = true;
+            action.update_with_post_conditions(&mut next.state);
+            next.goal_progress = self.config.goal_progress(&next.state, node.goal_progress);
+            for fact in action.pre_state.keys() {
+                let read = next.read_until.entry(fact.clone()).or_insert(0);
+                *read = (*read).max(end);
+            }
+            for fact in action.post_state.keys() {
+                next.written_at.insert(fact.clone(), end);
+            }
+            for resource in &action.resources {
+                next.resource_free_at.insert(resource.clone(), end);
+            }
+            next.steps.push(ScheduledAction {
+                action: name.to_string(),
+                start,
+                end,
+                resources: action.resources.clone(),
+            });
+            next.makespan = makespan;
+            next.cost = cost;
+
+            if next.goal_progress >= target {
+                *best = Some(Schedule {
+                    steps: next.steps,
+                    makespan,
+                    cost,
+                });
+            } else {
+                self.search(&next, best);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Finder;
+
+    fn door_config() -> PlanConfig {
+        serde_json::from_str(
+            r#"{
+                "actions": {
+                    "walk": { "cost": 1, "duration": 3, "resources": ["legs"], "pre_state": {}, "post_state": { "atDoor": true } },
+                    "run": { "cost": 5, "duration": 1, "resources": ["legs"], "pre_state": {}, "post_state": { "atDoor": true } },
+                    "reload": { "cost": 1, "duration": 2, "resources": ["hands"], "pre_state": { "loaded": false }, "post_state": { "loaded": true } },
+                    "open door": { "cost": 1, "duration": 1, "resources": ["hands"], "pre_state": { "atDoor": true }, "post_state": { "doorOpen": true } }
+                },
+                "goals": [{ "name": "ready", "state": { "doorOpen": true, "loaded": true } }],
+                "worldState": { "loaded": false, "atDoor": false, "doorOpen": false }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn step<'s>(schedule: &'s Schedule, action: &str) -> Option<&'s ScheduledAction> {
+        schedule.steps.iter().find(|s| s.action == action)
+    }
+
+    #[test]
+    fn runs_independent_actions_in_parallel() -> Result<(), String> {
+        let config = door_config();
+        let schedule = TemporalPlanner::new(&config)
+            .schedule()
+            .ok_or("no schedule")?;
+
+        // running is more expensive than walking but finishes sooner
+        assert_eq!(
+            Finder::new(&config).plan().map(|p| p.cost),
+            Some(3),
+            "cheapest plan walks"
+        );
+        assert_eq!(schedule.makespan, 3);
+        assert_eq!(schedule.cost, 7);
+
+        let run = step(&schedule, "run").ok_or("no run")?;
+        let reload = step(&schedule, "reload").ok_or("no reload")?;
+        let open = step(&schedule, "open door").ok_or("no open door")?;
+        assert_eq!((run.start, run.end), (0, 1));
+        assert_eq!((reload.start, reload.end), (0, 2));
+        // waits for the hands to be free after reloading
+        assert_eq!((open.start, open.end), (2, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn exports_timeline_per_resource() -> Result<(), String> {
+        let config = door_config();
+        let schedule = TemporalPlanner::new(&config)
+            .schedule()
+            .ok_or("no schedule")?;
+
+        let timeline: serde_json::Value =
+            serde_json::from_str(&schedule.to_timeline_json()).map_err(|e| e.to_string())?;
+        assert_eq!(timeline["makespan"], 3);
+
+        let tracks = timeline["tracks"].as_array().ok_or("no tracks")?;
+        let hands = tracks
+            .iter()
+            .find(|t| t["resource"] == "hands")
+            .ok_or("no hands track")?;
+        let hand_actions: Vec<&str> = hands["entries"]
+            .as_array()
+            .ok_or("no hand entries")?
+            .iter()
+            .filter_map(|e| e["action"].as_str())
+            .collect();
+        assert_eq!(hand_actions, vec!["reload", "open door"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,565 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::configs::{Gaction, Goal, GoalMode, PlanConfig, MAX_PLAN_COST};
+use crate::htn::Subtask;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind")]
+pub enum IssueKind {
+    EmptyActions,
+    EmptyGoals,
+    EmptyWorldState,
+    EmptyGoalState,
+    DuplicateAction {
+        name: String,
+    },
+    NegativeCost {
+        cost: i64,
+    },
+    CostTooHigh {
+        cost: usize,
+        max: usize,
+    },
+    ZeroCost,
+    /// fact is read by an action but nothing ever sets it
+    UnknownFact {
+        fact: String,
+    },
+    /// goal fact is neither in world state nor produced by any action
+    UnreachableGoalFact {
+        fact: String,
+        value: bool,
+    },
+    GoalAlreadySatisfied,
+    /// outcome probabilities are outside 0..=1 or do not add up to 1
+    InvalidProbabilities,
+    /// htn subtask names an action or task which does not exist
+    UnknownSubtask {
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigIssue {
+    pub severity: Severity,
+    /// JSON path of the offending value, e.g. `$.goals[0].state.hasWeapon`
+    pub path: String,
+    #[serde(flatten)]
+    pub kind: IssueKind,
+}
+
+impl ConfigIssue {
+    pub fn error(path: String, kind: IssueKind) -> Self {
+        ConfigIssue {
+            severity: Severity::Error,
+            path,
+            kind,
+        }
+    }
+
+    pub fn warning(path: String, kind: IssueKind) -> Self {
+        ConfigIssue {
+            severity: Severity::Warning,
+            path,
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        let message = match &self.kind {
+            IssueKind::EmptyActions => "no actions defined".to_string(),
+            IssueKind::EmptyGoals => "no goals defined".to_string(),
+            IssueKind::EmptyWorldState => "world state is empty".to_string(),
+            IssueKind::EmptyGoalState => "goal has no state to reach".to_string(),
+            IssueKind::DuplicateAction { name } => format!("duplicate action \"{}\"", name),
+            IssueKind::NegativeCost { cost } => format!("negative cost {}", cost),
+            IssueKind::CostTooHigh { cost, max } => {
+                format!("cost {} is above the planner limit {}", cost, max)
+            }
+            IssueKind::ZeroCost => "cost is 0, plans may pick it arbitrarily".to_string(),
+            IssueKind::UnknownFact { fact } => {
+                format!(
+                    "fact \"{}\" is not in world state and no action sets it",
+                    fact
+                )
+            }
+            IssueKind::UnreachableGoalFact { fact, value } => {
+                format!("goal fact \"{}\" can never become {}", fact, value)
+            }
+            IssueKind::GoalAlreadySatisfied => "goal is already met by world state".to_string(),
+            IssueKind::InvalidProbabilities => {
+                "outcome probabilities have to be within 0..1 and add up to 1".to_string()
+            }
+            IssueKind::UnknownSubtask { name } => format!("subtask \"{}\" does not exist", name),
+        };
+
+        write!(f, "{} at {}: {}", level, self.path, message)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ValidationReport {
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ValidationReport {
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+    }
+}
+
+pub fn action_path(name: &str) -> String {
+    format!("$.actions[{:?}]", name)
+}
+
+/// Goal facts whose value no action and not the world state ever gives
+fn unreachable_facts<'g>(goal: &'g Goal, reachable: &HashSet<(&str, bool)>) -> Vec<&'g String> {
+    sorted_keys(&goal.state)
+        .into_iter()
+        .filter(|fact| !reachable.contains(&(fact.as_str(), goal.state[*fact])))
+        .collect()
+}
+
+/// Action of a config which lists its actions, kept as sent so negative costs
+/// and repeated names can be reported at their list index
+#[derive(Debug, Clone)]
+pub struct ListedAction {
+    pub name: String,
+    pub cost: i64,
+    pub action: Gaction,
+}
+
+/// Action under validation, with the path issues about it point at
+struct ActionEntry<'a> {
+    path: String,
+    name: &'a str,
+    cost: i64,
+    action: &'a Gaction,
+}
+
+fn sorted_keys<V>(map: &HashMap<String, V>) -> Vec<&String> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    keys
+}
+
+impl PlanConfig {
+    /// Checks the config for values the planner silently accepts but can not use.
+    /// Issues are ordered by path so reports are stable between runs.
+    pub fn validate(&self) -> ValidationReport {
+        let entries = sorted_keys(&self.actions)
+            .into_iter()
+            .map(|name| {
+                let action = &self.actions[name];
+                ActionEntry {
+                    path: action_path(name),
+                    name,
+                    cost: i64::try_from(action.cost).unwrap_or(i64::MAX),
+                    action,
+                }
+            })
+            .collect();
+        self.check(entries)
+    }
+
+    /// `validate` with `actions` in place of the action map, for configs sent
+    /// with a list of actions. Action issues point at list indexes.
+    pub fn validate_listed(&self, actions: &[ListedAction]) -> ValidationReport {
+        let entries = actions
+            .iter()
+            .enumerate()
+            .map(|(index, listed)| ActionEntry {
+                path: format!("$.actions[{}]", index),
+                name: &listed.name,
+                cost: listed.cost,
+                action: &listed.action,
+            })
+            .collect();
+        self.check(entries)
+    }
+
+    fn check(&self, actions: Vec<ActionEntry>) -> ValidationReport {
+        let mut issues: Vec<ConfigIssue> = vec![];
+
+        if actions.is_empty() {
+            issues.push(ConfigIssue::error(
+                "$.actions".to_string(),
+                IssueKind::EmptyActions,
+            ));
+        }
+        if self.goals.is_empty() {
+            issues.push(ConfigIssue::error(
+                "$.goals".to_string(),
+                IssueKind::EmptyGoals,
+            ));
+        }
+        if self.worldState.is_empty() {
+            issues.push(ConfigIssue::warning(
+                "$.worldState".to_string(),
+                IssueKind::EmptyWorldState,
+            ));
+        }
+
+        // every value a fact can ever have: initial one plus whatever actions write
+        let mut reachable: HashSet<(&str, bool)> = HashSet::new();
+        for (fact, value) in &self.worldState {
+            reachable.insert((fact, *value));
+        }
+        for ActionEntry { action, .. } in &actions {
+            for (fact, value) in &action.post_state {
+                reachable.insert((fact, *value));
+            }
+            for outcome in &action.outcomes {
+                for (fact, value) in &outcome.post_state {
+                    reachable.insert((fact, *value));
+                }
+            }
+        }
+        let known =
+            |fact: &str| reachable.contains(&(fact, true)) || reachable.contains(&(fact, false));
+
+        let mut names: HashSet<&str> = HashSet::new();
+        for ActionEntry {
+            path,
+            name,
+            cost,
+            action,
+        } in &actions
+        {
+            if !names.insert(name) {
+                issues.push(ConfigIssue::error(
+                    format!("{}.name", path),
+                    IssueKind::DuplicateAction {
+                        name: name.to_string(),
+                    },
+                ));
+            }
+
+            match usize::try_from(*cost) {
+                Err(_) => issues.push(ConfigIssue::error(
+                    format!("{}.cost", path),
+                    IssueKind::NegativeCost { cost: *cost },
+                )),
+                Ok(cost) if cost > MAX_PLAN_COST => issues.push(ConfigIssue::error(
+                    format!("{}.cost", path),
+                    IssueKind::CostTooHigh {
+                        cost,
+                        max: MAX_PLAN_COST,
+                    },
+                )),
+                Ok(0) => issues.push(ConfigIssue::warning(
+                    format!("{}.cost", path),
+                    IssueKind::ZeroCost,
+                )),
+                Ok(_) => {}
+            }
+
+            if !action.outcomes.is_empty() {
+                let sum: f64 = action.outcomes.iter().map(|o| o.probability).sum();
+                let in_range = action
+                    .outcomes
+                    .iter()
+                    .all(|o| (0.0..=1.0).contains(&o.probability));
+                if !in_range || (sum - 1.0).abs() > 1e-6 {
+                    issues.push(ConfigIssue::error(
+                        format!("{}.outcomes", path),
+                        IssueKind::InvalidProbabilities,
+                    ));
+                }
+            }
+
+            for fact in sorted_keys(&action.pre_state) {
+                if !known(fact) {
+                    issues.push(ConfigIssue::warning(
+                        format!("{}.pre_state.{}", path, fact),
+                        IssueKind::UnknownFact {
+                            fact: fact.to_string(),
+                        },
+                    ));
+                }
+            }
+        }
+
+        let unreachable = |goal| unreachable_facts(goal, &reachable);
+        // with `GoalMode::Any` one reachable goal is enough, the others only warn
+        let any_reachable = self.goals.iter().any(|goal| unreachable(goal).is_empty());
+        let unreachable_severity = match self.goal_mode {
+            GoalMode::Any if any_reachable => Severity::Warning,
+            _ => Severity::Error,
+        };
+
+        for (goal_index, goal) in self.goals.iter().enumerate() {
+            let path = format!("$.goals[{}]", goal_index);
+
+            if goal.state.is_empty() {
+                issues.push(ConfigIssue::warning(
+                    format!("{}.state", path),
+                    IssueKind::EmptyGoalState,
+                ));
+                continue;
+            }
+
+            for fact in unreachable(goal) {
+                issues.push(ConfigIssue {
+                    severity: unreachable_severity,
+                    path: format!("{}.state.{}", path, fact),
+                    kind: IssueKind::UnreachableGoalFact {
+                        fact: fact.to_string(),
+                        value: goal.state[fact],
+                    },
+                });
+            }
+
+            if crate::configs::hasmap_contains(&self.worldState, &goal.state) {
+                issues.push(ConfigIssue::warning(path, IssueKind::GoalAlreadySatisfied));
+            }
+        }
+
+        for name in sorted_keys(&self.tasks) {
+            for (method_index, method) in self.tasks[name].methods.iter().enumerate() {
+                for (subtask_index, subtask) in method.subtasks.iter().enumerate() {
+                    let missing = match subtask {
+                        Subtask::Action(action) if !names.contains(action.as_str()) => action,
+                        Subtask::Task(task) if !self.tasks.contains_key(task) => task,
+                        _ => continue,
+                    };
+
+                    issues.push(ConfigIssue::error(
+                        format!(
+                            "$.tasks[{:?}].methods[{}].subtasks[{}]",
+                            name, method_index, subtask_index
+                        ),
+                        IssueKind::UnknownSubtask {
+                            name: missing.clone(),
+                        },
+                    ));
+                }
+            }
+        }
+
+        ValidationReport { issues }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migration::load_versioned;
+
+    fn config_from(json: &str) -> PlanConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn sample_config_has_no_errors() -> Result<(), String> {
+        let contents = std::fs::read_to_string("./actions.json").map_err(|e| e.to_string())?;
+        let config: PlanConfig = load_versioned(&contents).map_err(|e| e.to_string())?;
+
+        let report = config.validate();
+        assert!(!report.has_errors(), "{:?}", report.issues);
+        Ok(())
+    }
+
+    #[test]
+    fn reports_issues_with_paths() {
+        let config = config_from(
+            r#"{
+                "actions": {
+                    "expensive": { "cost": 20000, "pre_state": { "ghost": true }, "post_state": { "a": true } },
+                    "free": { "cost": 0, "pre_state": {}, "post_state": { "b": true } }
+                },
+                "goals": [
+                    { "name": "fly", "state": { "canFly": true } },
+                    { "name": "nothing", "state": {} },
+                    { "name": "done", "state": { "a": false } }
+                ],
+                "worldState": { "a": false },
+                "goal_mode": "all"
+            }"#,
+        );
+
+        let report = config.validate();
+        let found: Vec<(Severity, &str)> = report
+            .issues
+            .iter()
+            .map(|i| (i.severity, i.path.as_str()))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (Severity::Error, "$.actions[\"expensive\"].cost"),
+                (
+                    Severity::Warning,
+                    "$.actions[\"expensive\"].pre_state.ghost"
+                ),
+                (Severity::Warning, "$.actions[\"free\"].cost"),
+                (Severity::Error, "$.goals[0].state.canFly"),
+                (Severity::Warning, "$.goals[1].state"),
+                (Severity::Warning, "$.goals[2]"),
+            ]
+        );
+        assert_eq!(
+            report.issues[3].kind,
+            IssueKind::UnreachableGoalFact {
+                fact: "canFly".to_string(),
+                value: true
+            }
+        );
+        assert!(report.has_errors());
+        assert_eq!(report.warnings().count(), 4);
+    }
+
+    #[test]
+    fn reports_empty_config() {
+        let config = config_from(r#"{ "actions": {}, "goals": [], "worldState": {} }"#);
+        let kinds: Vec<IssueKind> = config
+            .validate()
+            .issues
+            .into_iter()
+            .map(|i| i.kind)
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                IssueKind::EmptyActions,
+                IssueKind::EmptyGoals,
+                IssueKind::EmptyWorldState
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_subtasks() {
+        let config = config_from(
+            r#"{
+                "actions": { "rest": { "cost": 1, "pre_state": {}, "post_state": { "rested": true } } },
+                "goals": [{ "name": "rested", "state": { "rested": true } }],
+                "worldState": { "rested": false },
+                "tasks": {
+                    "Idle": { "methods": [{ "name": "nap", "subtasks": [{ "action": "rest" }, { "action": "sleep" }, { "task": "Dream" }] }] }
+                }
+            }"#,
+        );
+
+        let issues = config.validate().issues;
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].path, "$.tasks[\"Idle\"].methods[0].subtasks[1]");
+        assert_eq!(
+            issues[1].kind,
+            IssueKind::UnknownSubtask {
+                name: "Dream".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn unreachable_goals_only_warn_while_another_is_reachable() {
+        let config = |goal_mode: &str, rest: &str| {
+            config_from(&format!(
+                r#"{{
+                    "actions": {{ "rest": {{ "cost": 1, "pre_state": {{}}, "post_state": {{ "rested": true }} }} }},
+                    "goals": [
+                        {{ "name": "fly", "state": {{ "canFly": true }} }},
+                        {{ "name": "rest", "state": {{ "rested": {} }} }}
+                    ],
+                    "worldState": {{ "rested": true }},
+                    "goal_mode": "{}"
+                }}"#,
+                rest, goal_mode
+            ))
+        };
+        let severities = |config: PlanConfig| -> Vec<(Severity, String)> {
+            config
+                .validate()
+                .issues
+                .into_iter()
+                .filter(|i| matches!(i.kind, IssueKind::UnreachableGoalFact { .. }))
+                .map(|i| (i.severity, i.path))
+                .collect()
+        };
+
+        assert_eq!(
+            severities(config("any", "true")),
+            vec![(Severity::Warning, "$.goals[0].state.canFly".to_string())]
+        );
+        assert_eq!(
+            severities(config("all", "true")),
+            vec![(Severity::Error, "$.goals[0].state.canFly".to_string())]
+        );
+        assert_eq!(
+            severities(config("any", "false")),
+            vec![
+                (Severity::Error, "$.goals[0].state.canFly".to_string()),
+                (Severity::Error, "$.goals[1].state.rested".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_listed_actions_at_their_index() {
+        let config = config_from(
+            r#"{
+                "actions": {},
+                "goals": [{ "name": "armed", "state": { "hasWeapon": true } }],
+                "worldState": { "hasWeapon": false }
+            }"#,
+        );
+        let listed = |name: &str, cost: i64, fact: &str| ListedAction {
+            name: name.to_string(),
+            cost,
+            action: Gaction {
+                post_state: HashMap::from([(fact.to_string(), true)]),
+                ..Default::default()
+            },
+        };
+        let actions = vec![
+            listed("steal", -3, "hasMoney"),
+            listed("work", 2, "hasMoney"),
+            listed("work", 5, "hasWeapon"),
+        ];
+
+        let issues = config.validate_listed(&actions).issues;
+        assert_eq!(
+            issues,
+            vec![
+                ConfigIssue::error(
+                    "$.actions[0].cost".to_string(),
+                    IssueKind::NegativeCost { cost: -3 }
+                ),
+                ConfigIssue::error(
+                    "$.actions[2].name".to_string(),
+                    IssueKind::DuplicateAction {
+                        name: "work".to_string()
+                    }
+                ),
+            ]
+        );
+    }
+}
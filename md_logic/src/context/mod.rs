@@ -1,8 +1,8 @@
 extern crate serde_json;
-use serde_json::Number;
 use serde_json::Value as JsonValue;
 
 use crate::expression_parser::operand::Operand;
+use crate::number::Decimal;
 
 pub fn get_context_var(name: &String, context: &serde_json::Value) -> serde_json::Value {
     let v: Vec<&str> = name.split('.').collect();
@@ -18,19 +18,46 @@ pub fn get_context_var(name: &String, context: &serde_json::Value) -> serde_json
     cur.clone()
 }
 
+/// Value at the dotted path `name`, `None` when a part of the path is missing
+pub fn lookup<'c>(name: &str, context: &'c JsonValue) -> Option<&'c JsonValue> {
+    name.split('.')
+        .try_fold(context, |cur, key| cur.as_object()?.get(key))
+}
+
 pub fn var_to_operand(name: &String, context: &serde_json::Value) -> Operand {
     let v = get_context_var(name, &context);
     match v {
         JsonValue::String(s) => Operand::Primitive(JsonValue::String(s)), //Operand::String(s),
-        JsonValue::Number(n) => {
-            let n_value = n.as_f64().unwrap_or(0.0);
-
-            Operand::Primitive(JsonValue::Number(Number::from_f64(n_value).unwrap()))
-        }
+        JsonValue::Number(n) => Decimal::from_number(&n)
+            .map_or(Operand::Primitive(JsonValue::Number(n)), Operand::Number),
+        JsonValue::Bool(b) => Operand::Primitive(JsonValue::Bool(b)),
+        JsonValue::Array(items) => Operand::Primitive(JsonValue::Array(items)),
         _ => Operand::Primitive(JsonValue::Null),
     }
 }
 
+/// What a variable missing from the context evaluates to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// missing variables are null
+    #[default]
+    Lenient,
+    /// missing variables are an error, variables set to null are still null
+    Strict,
+}
+
+/// Operand of the variable `name`, following `strictness` when it is not in the context
+pub fn resolve_var(
+    name: &str,
+    context: &JsonValue,
+    strictness: Strictness,
+) -> Result<Operand, String> {
+    if strictness == Strictness::Strict && lookup(name, context).is_none() {
+        return Err(format!("unknown variable \"{}\"", name));
+    }
+    Ok(var_to_operand(&name.to_string(), context))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -46,10 +73,7 @@ mod tests {
         assert_eq!(c, Operand::Primitive(JsonValue::String("Fall".to_owned())));
 
         let num = var_to_operand(&"count".to_owned(), &context);
-        assert_eq!(
-            num,
-            Operand::Primitive(JsonValue::Number(Number::from_f64(1.0).unwrap()))
-        );
+        assert_eq!(num, Operand::Number(Decimal::from(1)));
 
         let missing_val = var_to_operand(&"NoExist.subParam".to_owned(), &context);
         assert_eq!(missing_val, Operand::Primitive(JsonValue::Null));
@@ -86,4 +110,36 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn resolves_nested_paths_by_strictness() -> Result<(), String> {
+        let context = serde_json::json!({
+            "order": { "customer": { "name": "Ann", "vip": true }, "total": null }
+        });
+
+        assert_eq!(
+            resolve_var("order.customer.name", &context, Strictness::Strict)?,
+            Operand::Primitive(JsonValue::String("Ann".to_owned()))
+        );
+        assert_eq!(
+            resolve_var("order.customer.vip", &context, Strictness::Strict)?,
+            Operand::Primitive(JsonValue::Bool(true))
+        );
+        assert_eq!(
+            resolve_var("order.total", &context, Strictness::Strict)?,
+            Operand::Primitive(JsonValue::Null)
+        );
+
+        for missing in ["order.customer.age", "order.customer.name.first", "nothing"] {
+            assert_eq!(
+                resolve_var(missing, &context, Strictness::Lenient)?,
+                Operand::Primitive(JsonValue::Null)
+            );
+            assert_eq!(
+                resolve_var(missing, &context, Strictness::Strict),
+                Err(format!("unknown variable \"{}\"", missing))
+            );
+        }
+        Ok(())
+    }
 }
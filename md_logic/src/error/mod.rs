@@ -0,0 +1,260 @@
+use std::fmt;
+
+/// Characters `start..end` of an expression, or of a line of the markdown source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
+
+/// Where an error is, the parts that are not known are `None`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Location {
+    /// data row of the table, from 1 as in the hit policy errors
+    pub row: Option<usize>,
+    /// table column, from 1
+    pub column: Option<usize>,
+    /// line of the markdown source, from 1
+    pub line: Option<usize>,
+    /// characters in `line`, or in the expression when there is no line
+    pub span: Option<Span>,
+}
+
+/// Error of parsing or running a table, or of parsing an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum MdLogicError {
+    /// text where something else belongs, as `expected ")", found end of expression`
+    Unexpected {
+        expected: String,
+        found: String,
+        location: Location,
+    },
+    /// expression text that is wrong otherwise, as an invalid date
+    Syntax { message: String, location: Location },
+    /// table layout, definition rows and column metadata
+    Table { message: String, location: Location },
+    /// running a table against a context
+    Evaluation { message: String, location: Location },
+}
+
+impl MdLogicError {
+    pub fn unexpected(expected: &str, found: &str) -> Self {
+        MdLogicError::Unexpected {
+            expected: expected.to_string(),
+            found: found.to_string(),
+            location: Location::default(),
+        }
+    }
+
+    pub fn syntax(message: String) -> Self {
+        MdLogicError::Syntax {
+            message,
+            location: Location::default(),
+        }
+    }
+
+    pub fn table(message: String) -> Self {
+        MdLogicError::Table {
+            message,
+            location: Location::default(),
+        }
+    }
+
+    pub fn evaluation(message: String) -> Self {
+        MdLogicError::Evaluation {
+            message,
+            location: Location::default(),
+        }
+    }
+
+    pub fn location(&self) -> &Location {
+        match self {
+            MdLogicError::Unexpected { location, .. }
+            | MdLogicError::Syntax { location, .. }
+            | MdLogicError::Table { location, .. }
+            | MdLogicError::Evaluation { location, .. } => location,
+        }
+    }
+
+    pub fn location_mut(&mut self) -> &mut Location {
+        match self {
+            MdLogicError::Unexpected { location, .. }
+            | MdLogicError::Syntax { location, .. }
+            | MdLogicError::Table { location, .. }
+            | MdLogicError::Evaluation { location, .. } => location,
+        }
+    }
+
+    /// Sets the characters the error is about
+    pub fn at(mut self, span: Span) -> Self {
+        self.location_mut().span = Some(span);
+        self
+    }
+
+    /// Sets the data row, for errors found in a cell
+    pub fn at_row(mut self, row: usize) -> Self {
+        self.location_mut().row = Some(row);
+        self
+    }
+
+    /// Moves the span by `offset` characters, for an expression inside a larger text
+    pub fn shifted(mut self, offset: usize) -> Self {
+        if let Some(span) = &mut self.location_mut().span {
+            *span = Span::new(span.start + offset, span.end + offset);
+        }
+        self
+    }
+
+    /// What went wrong, without the location
+    pub fn message(&self) -> String {
+        match self {
+            MdLogicError::Unexpected {
+                expected, found, ..
+            } => format!("expected {}, found {}", expected, found),
+            MdLogicError::Syntax { message, .. }
+            | MdLogicError::Table { message, .. }
+            | MdLogicError::Evaluation { message, .. } => message.clone(),
+        }
+    }
+
+    /// The error with the line of `source` it is on, the span underlined:
+    ///
+    /// ```text
+    /// error: expected ")", found end of expression
+    ///  --> row 1, column 1, line 5
+    ///   |
+    /// 5 | | (1 + 2 | "Stew" |
+    ///   |   ^^^^^^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let location = self.location();
+        let mut rendered = format!("error: {}", self.message());
+        let place = describe(location);
+        if !place.is_empty() {
+            rendered += &format!("\n --> {}", place);
+        }
+
+        let text = match location.line.and_then(|line| source.lines().nth(line - 1)) {
+            Some(text) => text,
+            None => return rendered,
+        };
+        let number = location.line.unwrap_or_default().to_string();
+        let gutter = " ".repeat(number.len());
+        rendered += &format!("\n{} |\n{} | {}", gutter, number, text);
+
+        if let Some(span) = location.span {
+            // tabs stay tabs so the carets line up
+            let indent: String = text
+                .chars()
+                .take(span.start)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let carets = "^".repeat(span.end.saturating_sub(span.start).max(1));
+            rendered += &format!("\n{} | {}{}", gutter, indent, carets);
+        }
+        rendered
+    }
+}
+
+/// `row 2, column 1, line 7`, with the parts that are known
+fn describe(location: &Location) -> String {
+    let mut parts = vec![];
+    if let Some(row) = location.row {
+        parts.push(format!("row {}", row));
+    }
+    if let Some(column) = location.column {
+        parts.push(format!("column {}", column));
+    }
+    if let Some(line) = location.line {
+        parts.push(format!("line {}", line));
+    }
+    parts.join(", ")
+}
+
+impl fmt::Display for MdLogicError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let location = self.location();
+        let place = describe(location);
+        if !place.is_empty() {
+            write!(f, "{}: ", place)?;
+        }
+        write!(f, "{}", self.message())?;
+        match location.span {
+            Some(span) if location.line.is_none() => write!(f, " at {}", span.start),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for MdLogicError {}
+
+impl From<MdLogicError> for String {
+    fn from(error: MdLogicError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Character offset of `part` in `text`, `part` being a slice of `text`
+pub fn char_offset(text: &str, part: &str) -> usize {
+    let bytes = (part.as_ptr() as usize).saturating_sub(text.as_ptr() as usize);
+    text.get(..bytes).map_or(0, |before| before.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_the_source_line() {
+        let source = "| a |\n|---|\n\t| (1 + 2 | b |";
+        let mut error = MdLogicError::unexpected("\")\"", "end of expression");
+        *error.location_mut() = Location {
+            row: Some(1),
+            column: Some(1),
+            line: Some(3),
+            span: Some(Span::new(3, 9)),
+        };
+        assert_eq!(
+            error.render(source),
+            "error: expected \")\", found end of expression\n \
+             --> row 1, column 1, line 3\n  \
+             |\n\
+             3 | \t| (1 + 2 | b |\n  \
+             | \t  ^^^^^^"
+        );
+        assert_eq!(
+            error.to_string(),
+            "row 1, column 1, line 3: expected \")\", found end of expression"
+        );
+    }
+
+    #[test]
+    fn renders_without_a_line() {
+        let error =
+            MdLogicError::syntax("invalid date \"2026-13-01\"".to_string()).at(Span::new(4, 14));
+        assert_eq!(
+            error.render("a = 2026-13-01"),
+            "error: invalid date \"2026-13-01\""
+        );
+        assert_eq!(error.to_string(), "invalid date \"2026-13-01\" at 4");
+        assert_eq!(
+            error.clone().shifted(2).location().span,
+            Some(Span::new(6, 16))
+        );
+
+        let error = MdLogicError::table("table has no data rows".to_string());
+        assert_eq!(String::from(error), "table has no data rows");
+    }
+
+    #[test]
+    fn finds_offsets_of_slices() {
+        let text = "é, [1..5]";
+        assert_eq!(char_offset(text, &text[4..]), 3);
+    }
+}
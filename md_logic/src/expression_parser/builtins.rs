@@ -0,0 +1,383 @@
+use super::environment::Function;
+use super::operand::Operand;
+use super::temporal::{Date, DateTime, Duration};
+use crate::number::{self, Decimal};
+use serde_json::Value;
+
+/// Functions of every `Environment`. Arguments of the wrong type give null,
+/// a wrong number of arguments is an error.
+pub const FUNCTIONS: [(&str, Function); 18] = [
+    ("min", min),
+    ("max", max),
+    ("abs", abs),
+    ("floor", floor),
+    ("ceil", ceil),
+    ("round", round),
+    ("sum", sum),
+    ("count", count),
+    ("contains", contains),
+    ("starts with", starts_with),
+    ("upper case", upper_case),
+    ("lower case", lower_case),
+    ("string length", string_length),
+    ("substring", substring),
+    ("date", date),
+    ("date and time", date_and_time),
+    ("duration", duration),
+    ("years and months duration", years_and_months_duration),
+];
+
+fn null() -> Operand {
+    Operand::Primitive(Value::Null)
+}
+
+fn number_operand(n: Decimal) -> Operand {
+    Operand::Number(n)
+}
+
+fn number(o: &Operand) -> Option<Decimal> {
+    o.number()
+}
+
+fn integer(o: &Operand) -> Option<i64> {
+    number(o)?.to_integer()
+}
+
+fn string(o: &Operand) -> Option<&str> {
+    match o {
+        Operand::Primitive(Value::String(s)) => Some(s),
+        _ => None,
+    }
+}
+
+fn expect_args(args: &[Operand], min: usize, max: usize) -> Result<(), String> {
+    if (min..=max).contains(&args.len()) {
+        return Ok(());
+    }
+    let expected = if min == max {
+        min.to_string()
+    } else {
+        format!("{} to {}", min, max)
+    };
+    Err(format!(
+        "expected {} arguments, found {}",
+        expected,
+        args.len()
+    ))
+}
+
+/// `f(list)` and `f(a, b, ...)` both give the items
+fn items(args: &[Operand]) -> Vec<Operand> {
+    match args {
+        [Operand::Primitive(Value::Array(values))] => {
+            values.iter().cloned().map(Operand::Primitive).collect()
+        }
+        _ => args.to_vec(),
+    }
+}
+
+/// Numbers of `f(list)` or `f(a, b, ...)`, `None` when one item is no number
+fn numbers(args: &[Operand]) -> Option<Vec<Decimal>> {
+    items(args).iter().map(number).collect()
+}
+
+fn min(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 1, usize::MAX)?;
+    Ok(numbers(args)
+        .and_then(|n| n.into_iter().min())
+        .map_or(null(), number_operand))
+}
+
+fn max(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 1, usize::MAX)?;
+    Ok(numbers(args)
+        .and_then(|n| n.into_iter().max())
+        .map_or(null(), number_operand))
+}
+
+fn sum(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 1, usize::MAX)?;
+    Ok(numbers(args)
+        .and_then(|n| n.into_iter().try_fold(Decimal::ZERO, Decimal::checked_add))
+        .map_or(null(), number_operand))
+}
+
+fn count(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 1, usize::MAX)?;
+    Ok(number_operand(Decimal::from(items(args).len() as i64)))
+}
+
+fn unary_number(args: &[Operand], f: fn(Decimal) -> Option<Decimal>) -> Result<Operand, String> {
+    expect_args(args, 1, 1)?;
+    Ok(number(&args[0]).and_then(f).map_or(null(), number_operand))
+}
+
+fn abs(args: &[Operand]) -> Result<Operand, String> {
+    unary_number(args, Decimal::abs)
+}
+
+fn floor(args: &[Operand]) -> Result<Operand, String> {
+    unary_number(args, Decimal::floor)
+}
+
+fn ceil(args: &[Operand]) -> Result<Operand, String> {
+    unary_number(args, Decimal::ceil)
+}
+
+/// `round(n)` to an integer, `round(n, scale)` to `scale` decimals, halves away from zero
+fn round(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 1, 2)?;
+    let scale = match args.get(1) {
+        Some(scale) => integer(scale),
+        None => Some(0),
+    };
+    Ok(match (number(&args[0]), scale) {
+        (Some(n), Some(scale)) => n.round(scale).map_or(null(), number_operand),
+        _ => null(),
+    })
+}
+
+/// `contains(string, match)` or `contains(list, item)`
+fn contains(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 2, 2)?;
+    Ok(match (&args[0], &args[1]) {
+        (Operand::Primitive(Value::String(s)), Operand::Primitive(Value::String(m))) => {
+            Operand::Primitive(Value::Bool(s.contains(m.as_str())))
+        }
+        (Operand::Primitive(Value::Array(list)), item) => match item.to_value() {
+            Some(item) => {
+                Operand::Primitive(Value::Bool(list.iter().any(|v| number::equal(v, &item))))
+            }
+            None => null(),
+        },
+        _ => null(),
+    })
+}
+
+fn starts_with(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 2, 2)?;
+    Ok(match (string(&args[0]), string(&args[1])) {
+        (Some(s), Some(prefix)) => Operand::Primitive(Value::Bool(s.starts_with(prefix))),
+        _ => null(),
+    })
+}
+
+fn unary_string(args: &[Operand], f: fn(&str) -> Operand) -> Result<Operand, String> {
+    expect_args(args, 1, 1)?;
+    Ok(string(&args[0]).map_or(null(), f))
+}
+
+fn upper_case(args: &[Operand]) -> Result<Operand, String> {
+    unary_string(args, |s| {
+        Operand::Primitive(Value::String(s.to_uppercase()))
+    })
+}
+
+fn lower_case(args: &[Operand]) -> Result<Operand, String> {
+    unary_string(args, |s| {
+        Operand::Primitive(Value::String(s.to_lowercase()))
+    })
+}
+
+fn string_length(args: &[Operand]) -> Result<Operand, String> {
+    unary_string(args, |s| {
+        number_operand(Decimal::from(s.chars().count() as i64))
+    })
+}
+
+/// `substring(string, start, length)`, `start` counts from 1, or from the
+/// end when negative, without `length` the rest of the string is taken
+fn substring(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 2, 3)?;
+    let chars: Vec<char> = match string(&args[0]) {
+        Some(s) => s.chars().collect(),
+        None => return Ok(null()),
+    };
+    let length = match args.get(2).map(integer) {
+        Some(Some(length)) if length >= 0 => length as usize,
+        Some(_) => return Ok(null()),
+        None => chars.len(),
+    };
+
+    let start = match integer(&args[1]) {
+        Some(start) if start > 0 && start as usize <= chars.len() => start as usize - 1,
+        Some(start) if start < 0 && start.unsigned_abs() as usize <= chars.len() => {
+            chars.len() - start.unsigned_abs() as usize
+        }
+        _ => return Ok(null()),
+    };
+
+    let end = start + length.min(chars.len() - start);
+    Ok(Operand::Primitive(Value::String(
+        chars[start..end].iter().collect(),
+    )))
+}
+
+/// `date("2026-12-01")`, `date(year, month, day)` or the date of a date and time
+fn date(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 1, 3)?;
+    let date = match args {
+        [Operand::Primitive(Value::String(s))] => Date::parse(s).ok(),
+        [Operand::Date(date)] => Some(*date),
+        [Operand::DateTime(date_time)] => Some(date_time.date()),
+        [year, month, day] => match (integer(year), integer(month), integer(day)) {
+            (Some(year), Some(month), Some(day)) => Date::from_ymd(year, month, day),
+            _ => None,
+        },
+        _ => None,
+    };
+    Ok(date.map_or(null(), Operand::Date))
+}
+
+/// `date and time("2026-12-01T10:00:00Z")`, or midnight of a date
+fn date_and_time(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 1, 1)?;
+    let date_time = match &args[0] {
+        Operand::Primitive(Value::String(s)) => DateTime::parse(s).ok(),
+        Operand::Date(date) => Some(DateTime::from(*date)),
+        Operand::DateTime(date_time) => Some(*date_time),
+        _ => None,
+    };
+    Ok(date_time.map_or(null(), Operand::DateTime))
+}
+
+/// `duration("P1DT2H")`
+fn duration(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 1, 1)?;
+    Ok(match &args[0] {
+        Operand::Primitive(Value::String(s)) => {
+            Duration::parse(s).map_or(null(), Operand::Duration)
+        }
+        duration @ Operand::Duration(_) => duration.clone(),
+        _ => null(),
+    })
+}
+
+/// Whole years and months from the first date to the second, as an age
+fn years_and_months_duration(args: &[Operand]) -> Result<Operand, String> {
+    expect_args(args, 2, 2)?;
+    let date = |o: &Operand| match o {
+        Operand::Date(date) => Some(*date),
+        Operand::DateTime(date_time) => Some(date_time.date()),
+        _ => None,
+    };
+    Ok(match (date(&args[0]), date(&args[1])) {
+        (Some(from), Some(to)) => Operand::Duration(Duration::YearsMonths(from.months_until(&to))),
+        _ => null(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression_parser::environment::Environment;
+    use crate::expression_parser::executor::interpret;
+    use crate::expression_parser::tokenizer::Tokenizer;
+    use serde_json::json;
+
+    fn eval(formula: &str) -> Result<Value, String> {
+        let context = json!({ "prices": [4, 12, 7], "name": "Roastbeef" });
+        let mut tokenizer = Tokenizer::new(formula);
+        tokenizer.parse()?;
+        let postfix = tokenizer.to_postfix()?;
+        match interpret(&postfix, &context, &Environment::default())?.as_slice() {
+            [operand] => operand
+                .to_value()
+                .ok_or(format!("expected a value, got {:?}", operand)),
+            other => Err(format!("expected one value, got {:?}", other)),
+        }
+    }
+
+    #[test]
+    fn evaluates_number_functions() -> Result<(), String> {
+        assert_eq!(eval("min(3, 1, 2)")?, json!(1));
+        assert_eq!(eval("max(prices)")?, json!(12));
+        assert_eq!(eval("sum(prices) + 1")?, json!(24));
+        assert_eq!(eval("count(prices)")?, json!(3));
+        assert_eq!(eval("abs(2 - 5)")?, json!(3));
+        assert_eq!(eval("floor(2.7)")?, json!(2));
+        assert_eq!(eval("ceil(2.1)")?, json!(3));
+        assert_eq!(eval("round(2.5)")?, json!(3));
+        assert_eq!(eval("round(1.256, 2)")?, json!(1.26));
+        assert_eq!(eval("round(2.675, 2)")?, json!(2.68));
+        assert_eq!(eval("sum(0.1, 0.2) = 0.3")?, json!(true));
+        assert_eq!(eval("min(prices, 1)")?, Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn evaluates_string_functions() -> Result<(), String> {
+        assert_eq!(eval("contains(name, \"beef\")")?, json!(true));
+        assert_eq!(eval("contains(prices, 12)")?, json!(true));
+        assert_eq!(eval("starts with(name, \"Roast\")")?, json!(true));
+        assert_eq!(eval("upper case(name)")?, json!("ROASTBEEF"));
+        assert_eq!(eval("lower  case(\"Stew\")")?, json!("stew"));
+        assert_eq!(eval("string length(name) > 8")?, json!(true));
+        assert_eq!(eval("substring(name, 6)")?, json!("beef"));
+        assert_eq!(eval("substring(name, 0 - 4, 2)")?, json!("be"));
+        assert_eq!(eval("substring(name, 0)")?, Value::Null);
+        assert_eq!(eval("upper case(1)")?, Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn evaluates_date_functions() -> Result<(), String> {
+        assert_eq!(eval("date(\"2026-12-01\") = 2026-12-01")?, json!(true));
+        assert_eq!(eval("date(2026, 2, 29)")?, Value::Null);
+        assert_eq!(
+            eval("date(date and time(\"2026-12-01T23:30:00-01:00\")) = 2026-12-01")?,
+            json!(true)
+        );
+        assert_eq!(
+            eval("date and time(2026-12-01) < @\"2026-12-01T00:00:01\"")?,
+            json!(true)
+        );
+        assert_eq!(eval("duration(\"P1D\") * 7 = @\"P1W\"")?, json!(true));
+        assert_eq!(
+            eval("years and months duration(2008-10-20, 2026-10-20) >= duration(\"P18Y\")")?,
+            json!(true)
+        );
+        assert_eq!(eval("duration(\"one day\")")?, Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn nests_calls_in_expressions() -> Result<(), String> {
+        assert_eq!(eval("max(min(1, 2) * 10, abs(0 - 6))")?, json!(10));
+        assert_eq!(
+            eval("not contains(name, \"Stew\") and count(prices) = 3")?,
+            json!(true)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_wrong_calls() {
+        assert_eq!(
+            eval("abs(1, 2)"),
+            Err("abs: expected 1 arguments, found 2".to_string())
+        );
+        assert_eq!(eval("vat(10)"), Err("unknown function \"vat\"".to_string()));
+    }
+
+    #[test]
+    fn calls_registered_functions() -> Result<(), String> {
+        let mut environment = Environment::default();
+        environment.register("gross  price", |args| match args {
+            [Operand::Number(n)] => {
+                let gross = Decimal::parse("1.25").and_then(|vat| n.checked_mul(vat));
+                Ok(gross.map_or(null(), number_operand))
+            }
+            _ => Err("expected a number".to_string()),
+        });
+
+        let mut tokenizer = Tokenizer::new("gross price(8) = 10");
+        tokenizer.parse()?;
+        let postfix = tokenizer.to_postfix()?;
+        assert_eq!(
+            interpret(&postfix, &Value::Null, &environment)?,
+            vec![Operand::Primitive(Value::Bool(true))]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use super::builtins;
+use super::operand::Operand;
+use crate::context::Strictness;
+use std::collections::HashMap;
+
+/// Rust function callable from expressions, given the evaluated arguments
+pub type Function = fn(&[Operand]) -> Result<Operand, String>;
+
+/// How expressions are evaluated: the treatment of missing variables and
+/// the functions they can call
+#[derive(Debug, Clone)]
+pub struct Environment {
+    pub strictness: Strictness,
+    functions: HashMap<String, Function>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::new(Strictness::default())
+    }
+}
+
+impl Environment {
+    /// Environment with the standard library functions
+    pub fn new(strictness: Strictness) -> Self {
+        let mut environment = Environment {
+            strictness,
+            functions: HashMap::new(),
+        };
+        for (name, function) in builtins::FUNCTIONS {
+            environment.register(name, function);
+        }
+        environment
+    }
+
+    /// Adds `function` as `name`, replacing a function of the same name.
+    /// Names may be several words, as `string length`.
+    pub fn register(&mut self, name: &str, function: Function) {
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.functions.insert(name, function);
+    }
+
+    pub fn call(&self, name: &str, args: &[Operand]) -> Result<Operand, String> {
+        let function = self
+            .functions
+            .get(name)
+            .ok_or_else(|| format!("unknown function \"{}\"", name))?;
+        function(args).map_err(|e| format!("{}: {}", name, e))
+    }
+}
@@ -1,75 +1,169 @@
+use super::environment::Environment;
 use super::operand::{Operand, Operator};
-use super::tokenizer::Tokenizer;
-use serde_json::{Number, Value};
+use crate::context::resolve_var;
+use serde_json::Value;
 
-pub fn interpret(postfix: &Vec<Operand>) -> Vec<Operand> {
-    let mut stack: Vec<Operand> = Vec::with_capacity(postfix.len());
+/// Evaluates a postfix expression, variables are looked up in `context` and
+/// functions in `environment`. The right operand of `and` and `or` is only
+/// evaluated when the left one does not decide the result.
+pub fn interpret(
+    postfix: &[Operand],
+    context: &Value,
+    environment: &Environment,
+) -> Result<Vec<Operand>, String> {
+    let starts = expression_starts(postfix)?;
+    let mut results: Vec<Operand> = vec![];
 
-    for p in postfix {
-        match p {
-            Operand::OperatorToken(o) => {
-                let r = stack.pop().unwrap();
-                let l = stack.pop().unwrap();
+    // a postfix with several complete expressions evaluates to several results
+    let mut end = postfix.len();
+    while end > 0 {
+        results.push(evaluate(postfix, &starts, end - 1, context, environment)?);
+        end = starts[end - 1];
+    }
 
-                match o {
-                    Operator::Plus => stack.push(l + r),
+    results.reverse();
+    Ok(results)
+}
 
-                    Operator::Substract => stack.push(l - r),
+/// For every token, where the expression ending with it starts
+fn expression_starts(postfix: &[Operand]) -> Result<Vec<usize>, String> {
+    let mut open: Vec<usize> = Vec::with_capacity(postfix.len());
+    let mut starts: Vec<usize> = Vec::with_capacity(postfix.len());
 
-                    Operator::G => stack.push(Operand::Primitive(Value::Bool(l > r))),
+    for (index, p) in postfix.iter().enumerate() {
+        let arity = match p {
+            Operand::OperatorToken(o) if o.is_unary() => 1,
+            Operand::OperatorToken(_) => 2,
+            Operand::Function(_, arity) => *arity,
+            _ => 0,
+        };
+        if open.len() < arity {
+            return Err(match p {
+                Operand::OperatorToken(o) => format!("missing operand for {:?}", o),
+                Operand::Function(name, _) => format!("missing argument for {}", name),
+                _ => format!("missing operand for {:?}", p),
+            });
+        }
 
-                    Operator::GE => stack.push(Operand::Primitive(Value::Bool(l >= r))),
+        let start = match arity {
+            0 => index,
+            _ => open[open.len() - arity],
+        };
+        open.truncate(open.len() - arity);
+        open.push(start);
+        starts.push(start);
+    }
 
-                    Operator::L => stack.push(Operand::Primitive(Value::Bool(l < r))),
+    Ok(starts)
+}
 
-                    Operator::LE => stack.push(Operand::Primitive(Value::Bool(l <= r))),
+/// Evaluates the expression ending at `index`
+fn evaluate(
+    postfix: &[Operand],
+    starts: &[usize],
+    index: usize,
+    context: &Value,
+    environment: &Environment,
+) -> Result<Operand, String> {
+    let o = match &postfix[index] {
+        Operand::OperatorToken(o) => o,
+        Operand::Variable(var_name) => {
+            return resolve_var(var_name, context, environment.strictness)
+        }
+        Operand::Function(name, arity) => {
+            let mut args = Vec::with_capacity(*arity);
+            let mut end = index;
+            for _ in 0..*arity {
+                args.push(evaluate(postfix, starts, end - 1, context, environment)?);
+                end = starts[end - 1];
+            }
+            args.reverse();
+            return environment.call(name, &args);
+        }
+        p => return Ok(p.clone()),
+    };
 
-                    Operator::E => stack.push(Operand::Primitive(Value::Bool(l == r))),
+    // operators always have an operand before them, `expression_starts` checked it
+    let right = index - 1;
+    if o.is_unary() {
+        let r = evaluate(postfix, starts, right, context, environment)?;
+        return Ok(match r {
+            Operand::Primitive(Value::Bool(b)) => Operand::Primitive(Value::Bool(!b)),
+            _ => Operand::Primitive(Value::Null),
+        });
+    }
 
-                    Operator::NE => stack.push(Operand::Primitive(Value::Bool(l != r))),
+    let l = evaluate(postfix, starts, starts[right] - 1, context, environment)?;
+    match (o, &l) {
+        (Operator::And, Operand::Primitive(Value::Bool(false)))
+        | (Operator::Or, Operand::Primitive(Value::Bool(true))) => return Ok(l),
+        _ => {}
+    }
+    let r = evaluate(postfix, starts, right, context, environment)?;
 
-                    Operator::Multiply => stack.push(l * r),
+    let result = match o {
+        Operator::Plus => l + r,
 
-                    Operator::Division => stack.push(l / r),
-                }
-            }
-            Operand::Variable(_var_name) => {
-                // Todo - add json context to this function, and extract var_name from it
-                // temp hack
-                stack.push(Operand::Primitive(Value::Number(
-                    Number::from_f64(2.0).unwrap(),
-                )));
-            }
-            _ => {
-                stack.push(p.clone().to_owned());
-            }
-        }
-    }
+        Operator::Substract => l - r,
+
+        Operator::G => Operand::Primitive(Value::Bool(l > r)),
+
+        Operator::GE => Operand::Primitive(Value::Bool(l >= r)),
+
+        Operator::L => Operand::Primitive(Value::Bool(l < r)),
+
+        Operator::LE => Operand::Primitive(Value::Bool(l <= r)),
+
+        Operator::E => Operand::Primitive(Value::Bool(l.equals(&r))),
+
+        Operator::NE => Operand::Primitive(Value::Bool(!l.equals(&r))),
 
-    stack
+        Operator::Multiply => l * r,
+
+        Operator::Division => l / r,
+
+        Operator::And | Operator::Or => logical(o, &l, &r),
+
+        // unary, evaluated above
+        Operator::Not => Operand::Primitive(Value::Null),
+    };
+    Ok(result)
+}
+
+/// Three valued `and` and `or` once the left operand did not decide the
+/// result, anything besides booleans counts as unknown
+fn logical(o: &Operator, l: &Operand, r: &Operand) -> Operand {
+    // the value deciding the result on its own, `true` for `or`
+    let decisive = *o == Operator::Or;
+    let value = match (l, r) {
+        (_, Operand::Primitive(Value::Bool(b))) if *b == decisive => Value::Bool(decisive),
+        (Operand::Primitive(Value::Bool(_)), Operand::Primitive(Value::Bool(_))) => {
+            Value::Bool(!decisive)
+        }
+        _ => Value::Null,
+    };
+    Operand::Primitive(value)
 }
 
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::context::Strictness;
+    use crate::expression_parser::tokenizer::Tokenizer;
+    use crate::number::Decimal;
 
     fn postfix_for(formula: &str) -> Result<Vec<Operand>, String> {
         let mut tokenizer = Tokenizer::new(&formula);
         tokenizer.parse()?;
-        tokenizer.to_postfix()
+        Ok(tokenizer.to_postfix()?)
     }
 
     #[test]
     fn interpreter_succeeds_adding() -> Result<(), String> {
         let postfix = postfix_for("2+1");
-        let formula_result = interpret(&postfix?);
-        assert_eq!(
-            formula_result,
-            [Operand::Primitive(Value::Number(
-                Number::from_f64(3.0).unwrap()
-            ))]
-        );
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
+        assert_eq!(formula_result, [Operand::Number(Decimal::from(3))]);
         Ok(())
     }
 
@@ -77,7 +171,7 @@ mod tests {
     fn interpreter_succeeds_adding_string() -> Result<(), String> {
         let postfix = postfix_for("\"hello\"+\"world\"");
 
-        let formula_result = interpret(&postfix?);
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
         assert_eq!(
             formula_result,
             [Operand::Primitive(Value::String("helloworld".to_string()))]
@@ -88,20 +182,15 @@ mod tests {
     #[test]
     fn interpreter_succeeds_substracting() -> Result<(), String> {
         let postfix = postfix_for("2-1");
-        let formula_result = interpret(&postfix?);
-        assert_eq!(
-            formula_result,
-            [Operand::Primitive(Value::Number(
-                Number::from_f64(1.0).unwrap()
-            ))]
-        );
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
+        assert_eq!(formula_result, [Operand::Number(Decimal::from(1))]);
         Ok(())
     }
 
     #[test]
     fn interpreter_succeeds_comparing_numbers_greater() -> Result<(), String> {
         let postfix = postfix_for("2>1");
-        let formula_result = interpret(&postfix?);
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
         assert_eq!(formula_result, [Operand::Primitive(Value::Bool(true))]);
         Ok(())
     }
@@ -109,7 +198,7 @@ mod tests {
     #[test]
     fn interpreter_succeeds_comparing_numbers_less() -> Result<(), String> {
         let postfix = postfix_for("20<1");
-        let formula_result = interpret(&postfix?);
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
         assert_eq!(formula_result, [Operand::Primitive(Value::Bool(false))]);
         Ok(())
     }
@@ -117,7 +206,7 @@ mod tests {
     #[test]
     fn interpreter_succeeds_comparing_non_eq_numbers() -> Result<(), String> {
         let postfix = postfix_for("20!=20");
-        let formula_result = interpret(&postfix?);
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
         assert_eq!(formula_result, [Operand::Primitive(Value::Bool(false))]);
         Ok(())
     }
@@ -125,7 +214,7 @@ mod tests {
     #[test]
     fn interpreter_succeeds_comparing_numbers_less_eq() -> Result<(), String> {
         let postfix = postfix_for("20<=20");
-        let formula_result = interpret(&postfix?);
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
         assert_eq!(formula_result, [Operand::Primitive(Value::Bool(true))]);
         Ok(())
     }
@@ -133,7 +222,7 @@ mod tests {
     #[test]
     fn interpreter_succeeds_comparing_numbers_ne() -> Result<(), String> {
         let postfix = postfix_for("20!=20");
-        let formula_result = interpret(&postfix?);
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
         assert_eq!(formula_result, [Operand::Primitive(Value::Bool(false))]);
         Ok(())
     }
@@ -141,33 +230,23 @@ mod tests {
     #[test]
     fn interpreter_succeeds_multiply() -> Result<(), String> {
         let postfix = postfix_for("20*2");
-        let formula_result = interpret(&postfix?);
-        assert_eq!(
-            formula_result,
-            [Operand::Primitive(Value::Number(
-                Number::from_f64(40.0).unwrap()
-            ))]
-        );
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
+        assert_eq!(formula_result, [Operand::Number(Decimal::from(40))]);
         Ok(())
     }
 
     #[test]
     fn interpreter_succeeds_divide() -> Result<(), String> {
         let postfix = postfix_for("20/2");
-        let formula_result = interpret(&postfix?);
-        assert_eq!(
-            formula_result,
-            [Operand::Primitive(Value::Number(
-                Number::from_f64(10.0).unwrap()
-            ))]
-        );
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
+        assert_eq!(formula_result, [Operand::Number(Decimal::from(10))]);
         Ok(())
     }
 
     #[test]
     fn interpreter_succeeds_divide_by_zero() -> Result<(), String> {
         let postfix = postfix_for("20/0");
-        let formula_result = interpret(&postfix?);
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
         assert_eq!(formula_result, [Operand::Primitive(Value::Null)]);
         Ok(())
     }
@@ -175,22 +254,134 @@ mod tests {
     #[test]
     fn interpreter_succeeds_equality_check() -> Result<(), String> {
         let postfix = postfix_for("2+1=4-1");
-        let formula_result = interpret(&postfix?);
+        let formula_result = interpret(&postfix?, &Value::Null, &Environment::default())?;
         assert_eq!(formula_result, [Operand::Primitive(Value::Bool(true))]);
         Ok(())
     }
 
+    #[test]
+    fn interpreter_calculates_exact_decimals() -> Result<(), String> {
+        let context = serde_json::json!({ "price": 19.99, "count": 3.0 });
+        let cases = vec![
+            ("0.1 + 0.2 = 0.3", serde_json::json!(true)),
+            ("count = 3", serde_json::json!(true)),
+            ("price * count", serde_json::json!(59.97)),
+            ("1 - 0.9", serde_json::json!(0.1)),
+            ("10.0 / 4", serde_json::json!(2.5)),
+            ("7.50 * 2", serde_json::json!(15)),
+            ("2 / 3 * 3 = 2", serde_json::json!(true)),
+            ("1 / 3 + 1 / 3 + 1 / 3", serde_json::json!(1)),
+            ("price / 3 * 3 = price", serde_json::json!(true)),
+            ("2 / 3", serde_json::json!(0.6666666666666666)),
+        ];
+        for (formula, expected) in cases {
+            let formula_result =
+                interpret(&postfix_for(formula)?, &context, &Environment::default())?;
+            let values: Vec<_> = formula_result.iter().map(Operand::to_value).collect();
+            assert_eq!(values, [Some(expected)], "{}", formula);
+        }
+        Ok(())
+    }
+
     #[test]
     fn interpreter_succeeds_sum_with_variable() -> Result<(), String> {
         let postfix = postfix_for("2+extraValue");
+        let context = serde_json::json!({ "extraValue": 2 });
+
+        let formula_result = interpret(&postfix?, &context, &Environment::default())?;
+        assert_eq!(formula_result, [Operand::Number(Decimal::from(4))]);
+        Ok(())
+    }
+
+    #[test]
+    fn interpreter_resolves_nested_variables() -> Result<(), String> {
+        let context = serde_json::json!({
+            "order": { "total": 40, "customer": { "country": "NL" } }
+        });
 
-        let formula_result = interpret(&postfix?);
+        let postfix = postfix_for("order.total*2>=80")?;
         assert_eq!(
-            formula_result,
-            [Operand::Primitive(Value::Number(
-                Number::from_f64(4.0).unwrap()
-            ))]
+            interpret(&postfix, &context, &Environment::new(Strictness::Strict))?,
+            [Operand::Primitive(Value::Bool(true))]
+        );
+
+        let postfix = postfix_for("order.customer.country=\"NL\"")?;
+        assert_eq!(
+            interpret(&postfix, &context, &Environment::new(Strictness::Strict))?,
+            [Operand::Primitive(Value::Bool(true))]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn interpreter_follows_strictness_for_missing_variables() -> Result<(), String> {
+        let context = serde_json::json!({ "order": { "total": 40 } });
+        let postfix = postfix_for("order.discount+1")?;
+
+        assert_eq!(
+            interpret(&postfix, &context, &Environment::default())?,
+            [Operand::Primitive(Value::Null)]
+        );
+        assert_eq!(
+            interpret(&postfix, &context, &Environment::new(Strictness::Strict)),
+            Err("unknown variable \"order.discount\"".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn interpreter_fails_on_missing_operand() -> Result<(), String> {
+        let postfix = postfix_for("+11")?;
+        assert!(interpret(&postfix, &Value::Null, &Environment::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn interpreter_applies_logical_precedence() -> Result<(), String> {
+        let cases = [
+            ("1 < 2 and 3 > 4 or 5 = 5", Value::Bool(true)),
+            ("1 < 2 and (3 > 4 or 5 = 6)", Value::Bool(false)),
+            ("not 1 > 2 and 2 > 1", Value::Bool(true)),
+            ("!(1 < 2 || false)", Value::Bool(false)),
+            ("true && !false", Value::Bool(true)),
+            ("1 + 1 = 2 && 2 * 2 = 4", Value::Bool(true)),
+            ("true and \"yes\"", Value::Null),
+            ("false or \"yes\"", Value::Null),
+            ("\"yes\" or true", Value::Bool(true)),
+        ];
+
+        for (formula, expected) in cases {
+            let postfix = postfix_for(formula)?;
+            assert_eq!(
+                interpret(&postfix, &Value::Null, &Environment::default())?,
+                [Operand::Primitive(expected)],
+                "{}",
+                formula
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn interpreter_short_circuits() -> Result<(), String> {
+        let run = |formula: &str| -> Result<Vec<Operand>, String> {
+            interpret(
+                &postfix_for(formula)?,
+                &Value::Null,
+                &Environment::new(Strictness::Strict),
+            )
+        };
+
+        assert_eq!(
+            run("false and missing > 1")?,
+            [Operand::Primitive(Value::Bool(false))]
+        );
+        assert_eq!(
+            run("1 = 1 or missing")?,
+            [Operand::Primitive(Value::Bool(true))]
         );
+        assert!(run("true and missing").is_err());
+        assert!(run("false or missing").is_err());
         Ok(())
     }
 }
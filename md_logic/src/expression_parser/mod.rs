@@ -1,3 +1,6 @@
+pub mod builtins;
+pub mod environment;
 pub mod executor;
 pub mod operand;
+pub mod temporal;
 pub mod tokenizer;
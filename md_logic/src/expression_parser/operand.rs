@@ -1,5 +1,7 @@
+use super::temporal::{self, Date, DateTime, Duration};
+use crate::number::{self, Decimal};
 use serde::Serialize;
-use serde_json::{Number, Value};
+use serde_json::Value;
 use std::ops::Add;
 use std::ops::Div;
 use std::ops::Mul;
@@ -17,33 +19,97 @@ pub enum Operator {
     GE,
     E,
     NE,
+    And,
+    Or,
+    Not,
+}
+
+impl Operator {
+    /// `not` takes only the operand on its right
+    pub fn is_unary(&self) -> bool {
+        *self == Operator::Not
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            Operator::L | Operator::G | Operator::LE | Operator::GE | Operator::E | Operator::NE
+        )
+    }
 }
 
 #[derive(PartialEq, Debug, Clone, Serialize)]
 pub enum Operand {
     Primitive(Value),
+    /// numbers stay exact between operations, they become json only in results
+    Number(Decimal),
     Variable(String),
     OperatorToken(Operator),
     OpenParen,
     CloseParen,
+    /// separates the arguments of a function call
+    Comma,
+    /// function name and argument count, the count is known after `infix_to_postfix`
+    Function(String, usize),
+    Date(Date),
+    DateTime(DateTime),
+    Duration(Duration),
+}
+
+impl Operand {
+    /// `=` of expressions, numbers are equal by value so `10.0` equals `10`
+    pub fn equals(&self, other: &Operand) -> bool {
+        match (self, other) {
+            (Operand::Primitive(a), Operand::Primitive(b)) => number::equal(a, b),
+            _ => match (self.number(), other.number()) {
+                (Some(a), Some(b)) => a == b,
+                _ => self == other,
+            },
+        }
+    }
+
+    /// Exact value of a number, also of one given as a json number
+    pub fn number(&self) -> Option<Decimal> {
+        match self {
+            Operand::Number(n) => Some(*n),
+            Operand::Primitive(Value::Number(n)) => Decimal::from_number(n),
+            _ => None,
+        }
+    }
+
+    /// The operand as json, for results
+    pub fn to_value(&self) -> Option<Value> {
+        match self {
+            Operand::Primitive(value) => Some(value.clone()),
+            Operand::Number(n) => Some(n.to_value()),
+            _ => None,
+        }
+    }
+
+    /// `f` of two numbers, `None` when one of them isn't a number
+    fn calculate(
+        &self,
+        other: &Operand,
+        f: fn(Decimal, Decimal) -> Option<Decimal>,
+    ) -> Option<Operand> {
+        let (a, b) = (self.number()?, other.number()?);
+        Some(f(a, b).map_or(Operand::Primitive(Value::Null), Operand::Number))
+    }
 }
 
 impl Add for Operand {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
+        if let Some(sum) = self.calculate(&other, Decimal::checked_add) {
+            return sum;
+        }
         match (self, other) {
-            (Operand::Primitive(p1), Operand::Primitive(p2)) => match (p1, p2) {
-                (Value::Number(n1), Value::Number(n2)) => {
-                    let res = n1.as_f64().unwrap_or(0.0) + n2.as_f64().unwrap_or(0.0);
-                    Operand::Primitive(Value::Number(Number::from_f64(res).unwrap()))
-                }
-                (Value::String(s1), Value::String(s2)) => {
-                    Operand::Primitive(Value::String(format!("{}{}", s1, s2)))
-                }
-                (_, _1) => Operand::Primitive(Value::Null),
-            },
-            (_, _1) => Operand::Primitive(Value::Null),
+            (Operand::Primitive(Value::String(s1)), Operand::Primitive(Value::String(s2))) => {
+                Operand::Primitive(Value::String(format!("{}{}", s1, s2)))
+            }
+            (Operand::Primitive(_), Operand::Primitive(_)) => Operand::Primitive(Value::Null),
+            (l, r) => temporal::add(&l, &r).unwrap_or(Operand::Primitive(Value::Null)),
         }
     }
 }
@@ -52,15 +118,12 @@ impl Sub for Operand {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self {
+        if let Some(result) = self.calculate(&other, Decimal::checked_sub) {
+            return result;
+        }
         match (self, other) {
-            (Operand::Primitive(p1), Operand::Primitive(p2)) => match (p1, p2) {
-                (Value::Number(n1), Value::Number(n2)) => {
-                    let res = n1.as_f64().unwrap_or(0.0) - n2.as_f64().unwrap_or(0.0);
-                    Operand::Primitive(Value::Number(Number::from_f64(res).unwrap()))
-                }
-                (_, _1) => Operand::Primitive(Value::Null),
-            },
-            (_, _1) => Operand::Primitive(Value::Null),
+            (Operand::Primitive(_), Operand::Primitive(_)) => Operand::Primitive(Value::Null),
+            (l, r) => temporal::subtract(&l, &r).unwrap_or(Operand::Primitive(Value::Null)),
         }
     }
 }
@@ -69,15 +132,12 @@ impl Mul for Operand {
     type Output = Self;
 
     fn mul(self, other: Self) -> Self {
+        if let Some(result) = self.calculate(&other, Decimal::checked_mul) {
+            return result;
+        }
         match (self, other) {
-            (Operand::Primitive(p1), Operand::Primitive(p2)) => match (p1, p2) {
-                (Value::Number(n1), Value::Number(n2)) => {
-                    let res = n1.as_f64().unwrap_or(0.0) * n2.as_f64().unwrap_or(0.0);
-                    Operand::Primitive(Value::Number(Number::from_f64(res).unwrap()))
-                }
-                (_, _1) => Operand::Primitive(Value::Null),
-            },
-            (_, _1) => Operand::Primitive(Value::Null),
+            (Operand::Primitive(_), Operand::Primitive(_)) => Operand::Primitive(Value::Null),
+            (l, r) => temporal::multiply(&l, &r).unwrap_or(Operand::Primitive(Value::Null)),
         }
     }
 }
@@ -86,19 +146,13 @@ impl Div for Operand {
     type Output = Self;
 
     fn div(self, other: Self) -> Self {
+        // null when dividing by zero
+        if let Some(result) = self.calculate(&other, Decimal::checked_div) {
+            return result;
+        }
         match (self, other) {
-            (Operand::Primitive(p1), Operand::Primitive(p2)) => match (p1, p2) {
-                (Value::Number(n1), Value::Number(n2)) => {
-                    let n2_unwrapped = n2.as_f64().unwrap_or(0.0);
-                    if n2_unwrapped == 0.0 {
-                        return Operand::Primitive(Value::Null);
-                    }
-                    let res = n1.as_f64().unwrap_or(0.0) / n2_unwrapped;
-                    Operand::Primitive(Value::Number(Number::from_f64(res).unwrap()))
-                }
-                (_, _1) => Operand::Primitive(Value::Null),
-            },
-            (_, _1) => Operand::Primitive(Value::Null),
+            (Operand::Primitive(_), Operand::Primitive(_)) => Operand::Primitive(Value::Null),
+            (l, r) => temporal::divide(&l, &r).unwrap_or(Operand::Primitive(Value::Null)),
         }
     }
 }
@@ -106,22 +160,13 @@ impl Div for Operand {
 impl std::cmp::Ord for Operand {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         match (self, other) {
-            (Operand::Primitive(p1), Operand::Primitive(p2)) => match (p1, p2) {
-                (Value::Number(n1_base), Value::Number(n2_base)) => {
-                    let n1 = n1_base.as_f64().unwrap_or(0.0);
-                    let n2 = n2_base.as_f64().unwrap_or(0.0);
-                    if n1 > n2 {
-                        return std::cmp::Ordering::Greater;
-                    }
-                    if n1 < n2 {
-                        return std::cmp::Ordering::Less;
-                    }
-
-                    return std::cmp::Ordering::Equal;
-                }
-                (_, _1) => std::cmp::Ordering::Equal,
+            (Operand::Primitive(Value::Number(n1)), Operand::Primitive(Value::Number(n2))) => {
+                number::compare(n1, n2).unwrap_or(std::cmp::Ordering::Equal)
+            }
+            _ => match (self.number(), other.number()) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                _ => temporal::compare(self, other).unwrap_or(std::cmp::Ordering::Equal),
             },
-            (_, _1) => std::cmp::Ordering::Equal,
         }
     }
 }
@@ -0,0 +1,708 @@
+use super::operand::Operand;
+use crate::number::Decimal;
+use serde::{Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// Calendar date without a time zone, as `2026-12-01`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    /// since 1970-01-01
+    days: i64,
+}
+
+/// Date and time of day with the UTC offset it was written with, as
+/// `2026-12-01T10:30:00+02:00`. Without an offset the time counts as UTC.
+/// Date-times are equal and ordered by the instant they stand for.
+#[derive(Debug, Clone, Copy)]
+pub struct DateTime {
+    /// wall clock time in milliseconds since 1970-01-01T00:00:00
+    local: i64,
+    /// minutes ahead of UTC
+    offset: Option<i64>,
+}
+
+/// ISO-8601 duration. Years and months, as `P1Y2M`, and days and time, as
+/// `P1DT2H`, are separate kinds as a month has no fixed number of days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Duration {
+    YearsMonths(i64),
+    /// milliseconds
+    DaysTime(i64),
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses a number of exactly `digits` ASCII digits
+fn fixed_number(text: &str, digits: usize) -> Option<i64> {
+    if text.len() != digits || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+impl Date {
+    /// `None` for days which do not exist, as `2026-02-30`, and years past 9999
+    pub fn from_ymd(year: i64, month: i64, day: i64) -> Option<Date> {
+        if !(0..=9999).contains(&year)
+            || !(1..=12).contains(&month)
+            || day < 1
+            || day > days_in_month(year, month)
+        {
+            return None;
+        }
+
+        // days from civil, counting years from March so leap days come last
+        let y = if month <= 2 { year - 1 } else { year };
+        let era = y.div_euclid(400);
+        let year_of_era = y - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        Some(Date {
+            days: era * 146_097 + day_of_era - 719_468,
+        })
+    }
+
+    pub fn year_month_day(&self) -> (i64, i64, i64) {
+        let z = self.days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        (year, month, day)
+    }
+
+    /// `YYYY-MM-DD`
+    pub fn parse(text: &str) -> Result<Date, String> {
+        let invalid = || format!("\"{}\" is no date, expected YYYY-MM-DD", text);
+        let mut parts = text.trim().splitn(3, '-');
+        let mut next = |digits| parts.next().and_then(|p| fixed_number(p, digits));
+        match (next(4), next(2), next(2)) {
+            (Some(year), Some(month), Some(day)) => {
+                Date::from_ymd(year, month, day).ok_or_else(invalid)
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Same day `months` later, the last day of the month when it is shorter
+    fn add_months(self, months: i64) -> Option<Date> {
+        let (year, month, day) = self.year_month_day();
+        let total = (year * 12 + month - 1).checked_add(months)?;
+        let (year, month) = (total.div_euclid(12), total.rem_euclid(12) + 1);
+        Date::from_ymd(year, month, day.min(days_in_month(year, month)))
+    }
+
+    /// `None` outside of the years `from_ymd` accepts
+    fn from_days(days: i64) -> Option<Date> {
+        let date = Date { days };
+        let (year, _, _) = date.year_month_day();
+        (0..=9999).contains(&year).then_some(date)
+    }
+
+    fn add_days(self, days: i64) -> Option<Date> {
+        Date::from_days(self.days.checked_add(days)?)
+    }
+
+    /// Whole months from `self` to `to`, negative when `to` is earlier
+    pub fn months_until(&self, to: &Date) -> i64 {
+        let (y1, m1, d1) = self.year_month_day();
+        let (y2, m2, d2) = to.year_month_day();
+        let months = (y2 - y1) * 12 + m2 - m1;
+        if months > 0 && d2 < d1 {
+            months - 1
+        } else if months < 0 && d2 > d1 {
+            months + 1
+        } else {
+            months
+        }
+    }
+}
+
+impl DateTime {
+    /// `YYYY-MM-DDTHH:MM:SS`, seconds and fractions of them are optional,
+    /// followed by `Z` or an offset as `+02:00` or none
+    pub fn parse(text: &str) -> Result<DateTime, String> {
+        let text = text.trim();
+        let invalid = || {
+            format!(
+                "\"{}\" is no date and time, expected YYYY-MM-DDTHH:MM:SS",
+                text
+            )
+        };
+
+        let (date, time) = text.split_once('T').ok_or_else(invalid)?;
+        let date = Date::parse(date).map_err(|_| invalid())?;
+
+        let (time, offset) = if let Some(time) = time.strip_suffix('Z') {
+            (time, Some(0))
+        } else if let Some(at) = time.rfind(['+', '-']) {
+            let (sign, offset) = (&time[at..at + 1], &time[at + 1..]);
+            let (hours, minutes) = offset.split_once(':').ok_or_else(invalid)?;
+            let minutes = match (fixed_number(hours, 2), fixed_number(minutes, 2)) {
+                (Some(h), Some(m)) if h <= 14 && m < 60 => h * 60 + m,
+                _ => return Err(invalid()),
+            };
+            (
+                &time[..at],
+                Some(if sign == "-" { -minutes } else { minutes }),
+            )
+        } else {
+            (time, None)
+        };
+
+        let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+        let millis = match fraction {
+            "" => 0,
+            f if f.len() <= 9 && f.bytes().all(|b| b.is_ascii_digit()) => {
+                format!("{:0<3}", &f[..f.len().min(3)]).parse().unwrap_or(0)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let mut parts = time.split(':');
+        let mut next = || parts.next().map(|p| fixed_number(p, 2));
+        let (hours, minutes, seconds) = match (next(), next(), next(), next()) {
+            (Some(Some(h)), Some(Some(m)), None, None) => (h, m, 0),
+            (Some(Some(h)), Some(Some(m)), Some(Some(s)), None) => (h, m, s),
+            _ => return Err(invalid()),
+        };
+        if hours > 23 || minutes > 59 || seconds > 59 {
+            return Err(invalid());
+        }
+
+        Ok(DateTime {
+            local: date.days * MILLIS_PER_DAY
+                + ((hours * 60 + minutes) * 60 + seconds) * 1000
+                + millis,
+            offset,
+        })
+    }
+
+    fn instant(&self) -> i64 {
+        self.local - self.offset.unwrap_or(0) * 60_000
+    }
+
+    /// Date of the wall clock time
+    pub fn date(&self) -> Date {
+        Date {
+            days: self.local.div_euclid(MILLIS_PER_DAY),
+        }
+    }
+
+    fn with_date(self, date: Date) -> DateTime {
+        DateTime {
+            local: date.days * MILLIS_PER_DAY + self.local.rem_euclid(MILLIS_PER_DAY),
+            offset: self.offset,
+        }
+    }
+
+    fn add_millis(self, millis: i64) -> Option<DateTime> {
+        let local = self.local.checked_add(millis)?;
+        Date::from_days(local.div_euclid(MILLIS_PER_DAY))?;
+        Some(DateTime {
+            local,
+            offset: self.offset,
+        })
+    }
+}
+
+/// Midnight of `date`, without offset
+impl From<Date> for DateTime {
+    fn from(date: Date) -> Self {
+        DateTime {
+            local: date.days * MILLIS_PER_DAY,
+            offset: None,
+        }
+    }
+}
+
+impl PartialEq for DateTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.instant() == other.instant()
+    }
+}
+
+impl Eq for DateTime {}
+
+impl PartialOrd for DateTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DateTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.instant().cmp(&other.instant())
+    }
+}
+
+impl Duration {
+    /// `PnYnMnWnDTnHnMnS` with an optional leading `-`, as `P18Y` or `PT1.5S`.
+    /// Years and months can not be mixed with days and time.
+    pub fn parse(text: &str) -> Result<Duration, String> {
+        let text = text.trim();
+        let invalid = || format!("\"{}\" is no ISO-8601 duration, as P1Y2M or P1DT2H", text);
+
+        let (negative, rest) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+        let (date_part, time_part) = match rest.split_once('T') {
+            Some((_, "")) => return Err(invalid()),
+            Some((date, time)) => (date, time),
+            None => (rest, ""),
+        };
+        if date_part.is_empty() && time_part.is_empty() {
+            return Err(invalid());
+        }
+
+        // milliseconds of each designator, `None` for years and months
+        let date_units: [(char, Option<i64>); 4] = [
+            ('Y', None),
+            ('M', None),
+            ('W', Some(7 * MILLIS_PER_DAY)),
+            ('D', Some(MILLIS_PER_DAY)),
+        ];
+        let time_units: [(char, Option<i64>); 3] = [
+            ('H', Some(3_600_000)),
+            ('M', Some(60_000)),
+            ('S', Some(1000)),
+        ];
+
+        let mut months: i64 = 0;
+        let mut millis: i64 = 0;
+        let mut has_months = false;
+        let mut has_millis = false;
+        for (part, units) in [(date_part, &date_units[..]), (time_part, &time_units[..])] {
+            let mut rest = part;
+            let mut unit_index = 0;
+            while !rest.is_empty() {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_digit() && c != '.')
+                    .ok_or_else(invalid)?;
+                let (number, designator) = (&rest[..end], rest[end..].chars().next());
+
+                // designators in order, each at most once
+                let offset = units[unit_index..]
+                    .iter()
+                    .position(|(d, _)| Some(*d) == designator)
+                    .ok_or_else(invalid)?;
+                let (_, unit) = units[unit_index + offset];
+                unit_index += offset + 1;
+                rest = &rest[end + 1..];
+
+                match unit {
+                    None => {
+                        let n: i64 = fixed_number(number, number.len()).ok_or_else(invalid)?;
+                        let factor = if designator == Some('Y') { 12 } else { 1 };
+                        months = n
+                            .checked_mul(factor)
+                            .and_then(|n| months.checked_add(n))
+                            .ok_or_else(invalid)?;
+                        has_months = true;
+                    }
+                    Some(unit) => {
+                        if number.contains('.') && designator != Some('S') {
+                            return Err(invalid());
+                        }
+                        let n = Decimal::parse(number)
+                            .and_then(|n| n.checked_mul(Decimal::from(unit)))
+                            .and_then(|n| n.round(0))
+                            .and_then(Decimal::to_integer)
+                            .ok_or_else(invalid)?;
+                        millis = millis.checked_add(n).ok_or_else(invalid)?;
+                        has_millis = true;
+                    }
+                }
+            }
+        }
+
+        let sign = if negative { -1 } else { 1 };
+        match (has_months, has_millis) {
+            (true, true) => Err(format!(
+                "\"{}\" mixes years and months with days and time",
+                text
+            )),
+            (true, false) => Ok(Duration::YearsMonths(sign * months)),
+            _ => Ok(Duration::DaysTime(sign * millis)),
+        }
+    }
+
+    /// Duration with its months or milliseconds mapped by `f`, rounded to whole units
+    fn map(self, f: impl Fn(Decimal) -> Option<Decimal>) -> Option<Duration> {
+        let mapped = |n: i64| f(Decimal::from(n))?.round(0)?.to_integer();
+        Some(match self {
+            Duration::YearsMonths(months) => Duration::YearsMonths(mapped(months)?),
+            Duration::DaysTime(millis) => Duration::DaysTime(mapped(millis)?),
+        })
+    }
+}
+
+impl PartialOrd for Duration {
+    /// durations of different kinds are not comparable
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Duration::YearsMonths(a), Duration::YearsMonths(b))
+            | (Duration::DaysTime(a), Duration::DaysTime(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (year, month, day) = self.year_month_day();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = self.local.rem_euclid(MILLIS_PER_DAY);
+        let seconds = time / 1000;
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}",
+            self.date(),
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )?;
+        if time % 1000 != 0 {
+            write!(f, ".{:03}", time % 1000)?;
+        }
+        match self.offset {
+            None => Ok(()),
+            Some(0) => write!(f, "Z"),
+            Some(offset) => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                write!(
+                    f,
+                    "{}{:02}:{:02}",
+                    sign,
+                    offset.abs() / 60,
+                    offset.abs() % 60
+                )
+            }
+        }
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Duration::YearsMonths(months) => {
+                let sign = if months < 0 { "-" } else { "" };
+                let months = months.unsigned_abs();
+                write!(f, "{}P", sign)?;
+                if months >= 12 {
+                    write!(f, "{}Y", months / 12)?;
+                }
+                if months % 12 != 0 || months == 0 {
+                    write!(f, "{}M", months % 12)?;
+                }
+                Ok(())
+            }
+            Duration::DaysTime(millis) => {
+                let sign = if millis < 0 { "-" } else { "" };
+                let millis = millis.unsigned_abs();
+                let days = millis / MILLIS_PER_DAY as u64;
+                let time = millis % MILLIS_PER_DAY as u64;
+                write!(f, "{}P", sign)?;
+                if days > 0 {
+                    write!(f, "{}D", days)?;
+                }
+                if time == 0 {
+                    return if days == 0 { write!(f, "T0S") } else { Ok(()) };
+                }
+
+                write!(f, "T")?;
+                let (hours, minutes) = (time / 3_600_000, time / 60_000 % 60);
+                let (seconds, fraction) = (time / 1000 % 60, time % 1000);
+                if hours > 0 {
+                    write!(f, "{}H", hours)?;
+                }
+                if minutes > 0 {
+                    write!(f, "{}M", minutes)?;
+                }
+                match (seconds, fraction) {
+                    (0, 0) => Ok(()),
+                    (s, 0) => write!(f, "{}S", s),
+                    (s, fraction) => {
+                        let fraction = format!("{:03}", fraction);
+                        write!(f, "{}.{}S", s, fraction.trim_end_matches('0'))
+                    }
+                }
+            }
+        }
+    }
+}
+
+macro_rules! serialize_as_string {
+    ($($t:ty),*) => {
+        $(impl Serialize for $t {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        })*
+    };
+}
+
+serialize_as_string!(Date, DateTime, Duration);
+
+/// Value of a literal as `@"2026-12-01"`, `@"2026-12-01T10:00:00Z"` or `@"P1D"`
+pub fn parse_literal(text: &str) -> Result<Operand, String> {
+    if text.trim_start().trim_start_matches('-').starts_with('P') {
+        Duration::parse(text).map(Operand::Duration)
+    } else if text.contains('T') {
+        DateTime::parse(text).map(Operand::DateTime)
+    } else {
+        Date::parse(text).map(Operand::Date)
+    }
+}
+
+fn number(o: &Operand) -> Option<Decimal> {
+    o.number()
+}
+
+/// `l + r` for dates, date-times and durations
+pub fn add(l: &Operand, r: &Operand) -> Option<Operand> {
+    use Duration::{DaysTime, YearsMonths};
+    match (l, r) {
+        (Operand::Date(date), Operand::Duration(duration))
+        | (Operand::Duration(duration), Operand::Date(date)) => match *duration {
+            YearsMonths(months) => date.add_months(months).map(Operand::Date),
+            DaysTime(millis) => date
+                .add_days(millis.div_euclid(MILLIS_PER_DAY))
+                .map(Operand::Date),
+        },
+        (Operand::DateTime(date_time), Operand::Duration(duration))
+        | (Operand::Duration(duration), Operand::DateTime(date_time)) => match *duration {
+            YearsMonths(months) => date_time
+                .date()
+                .add_months(months)
+                .map(|date| Operand::DateTime(date_time.with_date(date))),
+            DaysTime(millis) => date_time.add_millis(millis).map(Operand::DateTime),
+        },
+        (Operand::Duration(a), Operand::Duration(b)) => match (*a, *b) {
+            (YearsMonths(a), YearsMonths(b)) => a.checked_add(b).map(YearsMonths),
+            (DaysTime(a), DaysTime(b)) => a.checked_add(b).map(DaysTime),
+            _ => None,
+        }
+        .map(Operand::Duration),
+        _ => None,
+    }
+}
+
+/// `l - r`, two dates or date-times give the days and time between them
+pub fn subtract(l: &Operand, r: &Operand) -> Option<Operand> {
+    let date_time = |o: &Operand| match o {
+        Operand::Date(date) => Some(DateTime::from(*date)),
+        Operand::DateTime(date_time) => Some(*date_time),
+        _ => None,
+    };
+    match (l, r) {
+        (Operand::Date(a), Operand::Date(b)) => Some(Operand::Duration(Duration::DaysTime(
+            (a.days - b.days) * MILLIS_PER_DAY,
+        ))),
+        (Operand::Date(_) | Operand::DateTime(_), Operand::Date(_) | Operand::DateTime(_)) => {
+            let (a, b) = (date_time(l)?, date_time(r)?);
+            Some(Operand::Duration(Duration::DaysTime(
+                a.instant().checked_sub(b.instant())?,
+            )))
+        }
+        (_, Operand::Duration(duration)) => {
+            add(l, &Operand::Duration(duration.map(Decimal::checked_neg)?))
+        }
+        _ => None,
+    }
+}
+
+/// `duration * number` in either order
+pub fn multiply(l: &Operand, r: &Operand) -> Option<Operand> {
+    match (l, r) {
+        (Operand::Duration(duration), n) | (n, Operand::Duration(duration)) => {
+            let factor = number(n)?;
+            duration
+                .map(|d| d.checked_mul(factor))
+                .map(Operand::Duration)
+        }
+        _ => None,
+    }
+}
+
+/// `duration / number`, or the ratio of two durations of the same kind
+pub fn divide(l: &Operand, r: &Operand) -> Option<Operand> {
+    use Duration::{DaysTime, YearsMonths};
+    match (l, r) {
+        (Operand::Duration(a), Operand::Duration(b)) => {
+            let (a, b) = match (*a, *b) {
+                (YearsMonths(a), YearsMonths(b)) | (DaysTime(a), DaysTime(b)) => (a, b),
+                _ => return None,
+            };
+            let ratio = Decimal::from(a).checked_div(Decimal::from(b))?;
+            Some(Operand::Number(ratio))
+        }
+        (Operand::Duration(duration), n) => {
+            let divisor = number(n)?;
+            duration
+                .map(|d| d.checked_div(divisor))
+                .map(Operand::Duration)
+        }
+        _ => None,
+    }
+}
+
+/// Order of dates, date-times and durations of the same kind, a date counts
+/// as midnight when compared with a date-time
+pub fn compare(l: &Operand, r: &Operand) -> Option<Ordering> {
+    match (l, r) {
+        (Operand::Date(a), Operand::Date(b)) => Some(a.cmp(b)),
+        (Operand::DateTime(a), Operand::DateTime(b)) => Some(a.cmp(b)),
+        (Operand::Date(a), Operand::DateTime(b)) => Some(DateTime::from(*a).cmp(b)),
+        (Operand::DateTime(a), Operand::Date(b)) => Some(a.cmp(&DateTime::from(*b))),
+        (Operand::Duration(a), Operand::Duration(b)) => a.partial_cmp(b),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn iso(o: Operand) -> String {
+        match o {
+            Operand::Date(date) => date.to_string(),
+            Operand::DateTime(date_time) => date_time.to_string(),
+            Operand::Duration(duration) => duration.to_string(),
+            other => format!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_and_prints_iso_8601() -> Result<(), String> {
+        for text in [
+            "2026-12-01",
+            "2024-02-29",
+            "0001-01-01",
+            "2026-12-01T10:30:00",
+            "2026-12-01T10:30:00.250Z",
+            "2026-12-01T23:59:59-05:30",
+            "P18Y",
+            "P1Y2M",
+            "P0M",
+            "-P3D",
+            "P1DT2H30M",
+            "PT1.5S",
+            "PT0S",
+        ] {
+            assert_eq!(iso(parse_literal(text)?), text);
+        }
+        assert_eq!(
+            Duration::parse("P2W")?,
+            Duration::DaysTime(14 * MILLIS_PER_DAY)
+        );
+        assert_eq!(Duration::parse("PT90M")?.to_string(), "PT1H30M");
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_iso_8601() {
+        for text in [
+            "2026-02-29",
+            "2026-13-01",
+            "26-12-01",
+            "2026-12-01T24:00:00",
+            "2026-12-01T10",
+            "2026-12-01T10:00:00+2",
+            "P",
+            "PT",
+            "P1H",
+            "P1M1Y",
+            "P1Y1D",
+            "P1.5D",
+            "P99999999999999999999Y",
+        ] {
+            assert!(parse_literal(text).is_err(), "{}", text);
+        }
+    }
+
+    fn date(text: &str) -> Operand {
+        parse_literal(text).unwrap_or(Operand::Primitive(Value::Null))
+    }
+
+    #[test]
+    fn calculates_with_dates() {
+        assert_eq!(
+            subtract(&date("2026-03-01"), &date("2026-02-01")),
+            Some(date("P28D"))
+        );
+        assert_eq!(
+            add(&date("2024-01-31"), &date("P1M")),
+            Some(date("2024-02-29"))
+        );
+        assert_eq!(
+            subtract(&date("2026-01-01"), &date("P1D")),
+            Some(date("2025-12-31"))
+        );
+        assert_eq!(
+            add(&date("2026-12-01T23:00:00Z"), &date("PT2H")),
+            Some(date("2026-12-02T01:00:00Z"))
+        );
+        assert_eq!(
+            subtract(&date("2026-12-01T12:00:00+02:00"), &date("2026-12-01")),
+            Some(date("PT10H"))
+        );
+        assert_eq!(
+            multiply(&date("P1D"), &Operand::Primitive(serde_json::json!(1.5))),
+            Some(date("P1DT12H"))
+        );
+        assert_eq!(add(&date("P1Y"), &date("P1D")), None);
+        assert_eq!(add(&date("9999-12-31"), &date("P1D")), None);
+    }
+
+    #[test]
+    fn compares_dates() {
+        assert_eq!(
+            compare(
+                &date("2026-12-01T10:00:00+02:00"),
+                &date("2026-12-01T08:00:00Z")
+            ),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(
+            compare(&date("2026-12-01"), &date("2026-12-01T00:00:01")),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            compare(&date("P1Y"), &date("P11M")),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(compare(&date("P1Y"), &date("P365D")), None);
+        assert_eq!(
+            Date::parse("2008-10-20").and_then(|d| Ok(d.months_until(&Date::parse("2026-10-19")?))),
+            Ok(215)
+        );
+    }
+}
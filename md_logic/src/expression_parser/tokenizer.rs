@@ -1,9 +1,12 @@
-use serde_json::{Number, Value};
-use std::iter::Enumerate;
+use serde_json::Value;
 use std::iter::Peekable;
-use std::str::Chars;
+use std::str::CharIndices;
 
+use super::builtins;
 use super::operand::{Operand, Operator};
+use super::temporal::{self, Date};
+use crate::error::{MdLogicError, Span};
+use crate::number::Decimal;
 
 struct TokenRange {
     tracking: bool,
@@ -36,33 +39,55 @@ type Program = Vec<Operand>;
 
 pub struct Tokenizer<'a> {
     expression: &'a str,
-    i: Peekable<Enumerate<Chars<'a>>>,
+    /// characters with their byte offset into `expression`
+    i: Peekable<CharIndices<'a>>,
     operands: Program,
+    /// characters of every operand
+    spans: Vec<Span>,
 }
 
 impl<'a> Tokenizer<'a> {
     pub fn new(expression: &'a str) -> Self {
         return Tokenizer {
             expression,
-            i: expression.chars().enumerate().peekable(),
+            i: expression.char_indices().peekable(),
             operands: Vec::with_capacity(expression.len()),
+            spans: Vec::with_capacity(expression.len()),
         };
     }
 
-    pub fn parse(&mut self) -> Result<(), String> {
+    pub fn parse(&mut self) -> Result<(), MdLogicError> {
         loop {
             self.consume_spaces();
 
             if self.i.peek() == None {
                 break;
             }
+            let start = self.position();
             let n = self.next_operand()?;
             self.operands.push(n);
+            let end = self.position();
+            let span = self.span(start, end);
+            self.spans.push(span);
         }
 
         Ok(())
     }
 
+    /// Byte offset of the next character, the length of the expression at its end
+    fn position(&mut self) -> usize {
+        match self.i.peek() {
+            Some(&(index, _)) => index,
+            None => self.expression.len(),
+        }
+    }
+
+    /// Characters between the byte offsets `start` and `end`
+    fn span(&self, start: usize, end: usize) -> Span {
+        let chars = |offset: usize| self.expression[..offset].chars().count();
+        Span::new(chars(start), chars(end))
+    }
+
     fn consume_spaces(&mut self) {
         loop {
             match self.i.peek() {
@@ -74,7 +99,7 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
-    fn next_operand(&mut self) -> Result<Operand, String> {
+    fn next_operand(&mut self) -> Result<Operand, MdLogicError> {
         while let Some(&(_index, c)) = self.i.peek() {
             if c == '(' {
                 self.i.next();
@@ -82,92 +107,53 @@ impl<'a> Tokenizer<'a> {
             } else if c == ')' {
                 self.i.next();
                 return Ok(Operand::CloseParen);
+            } else if c == ',' {
+                self.i.next();
+                return Ok(Operand::Comma);
+            } else if c == '?' {
+                self.i.next();
+                return Ok(Operand::Variable(INPUT_PLACEHOLDER.to_string()));
             } else if c == '"' {
                 return self.consume_string();
+            } else if c == '@' {
+                return self.consume_temporal();
             } else if check_if_operand(&c) {
                 return self.consume_variable();
             } else if check_if_operator(&c) {
                 return self.consume_operator();
             } else if check_if_digit(&c) {
+                if let Some(date) = self.consume_date()? {
+                    return Ok(date);
+                }
                 return self.consume_number();
             } else {
-                return Err(format!("unknown symbol at index {}, {:?}", _index, c));
+                return Err(MdLogicError::unexpected(
+                    "a value, name or operator",
+                    &format!("{:?}", c),
+                )
+                .at(self.span(_index, _index + c.len_utf8())));
             }
         }
 
-        Err("Reached end - unprocessed statements found".to_string())
+        let end = self.position();
+        Err(MdLogicError::unexpected("an operand", "end of expression").at(self.span(end, end)))
+    }
+    pub fn to_postfix(self) -> Result<Vec<Operand>, MdLogicError> {
+        infix_to_postfix(self.operands, &self.spans)
     }
-    pub fn to_postfix(self) -> Result<Vec<Operand>, String> {
-        let mut stack: Vec<Operand> = Vec::with_capacity(50);
-        let mut postfix: Vec<Operand> = Vec::with_capacity(self.operands.len());
-
-        for o in self.operands {
-            match o {
-                Operand::Primitive(_) | Operand::Variable(_) => {
-                    postfix.push(o);
-                }
-                Operand::OpenParen => {
-                    stack.push(o);
-                }
-                Operand::CloseParen => {
-                    let mut found = false;
-                    while let Some(s_item) = stack.pop() {
-                        match s_item {
-                            Operand::OpenParen => {
-                                found = true;
-                                break;
-                            }
-                            _ => {
-                                postfix.push(s_item);
-                            }
-                        }
-                    }
-
-                    if found == false {
-                        return Err("no matching opening paren".to_string());
-                    }
-                }
-                Operand::OperatorToken(ref t) => {
-                    if stack.len() == 0 {
-                        stack.push(o);
-                    } else {
-                        loop {
-                            if let Some(Operand::OpenParen) = stack.last() {
-                                stack.push(o);
-                                break;
-                            } else if let Some(Operand::OperatorToken(so)) = stack.last() {
-                                if precedence(so) >= precedence(&t) {
-                                    if let Some(poped_stack_item) = stack.pop() {
-                                        postfix.push(poped_stack_item);
-                                    } else {
-                                        return Err("stack underflow".to_string());
-                                    }
-                                } else {
-                                    stack.push(o);
-                                    break;
-                                }
-                            } else {
-                                stack.push(o);
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        while let Some(s_item) = stack.pop() {
-            if s_item == Operand::OpenParen {
-                return Err("no matching closing paren".to_string());
-            }
 
-            postfix.push(s_item);
-        }
+    /// Parsed operands, in the order they were written
+    pub fn into_operands(self) -> Vec<Operand> {
+        self.operands
+    }
 
-        return Ok(postfix);
+    /// Characters of the expression each operand was parsed from
+    pub fn spans(&self) -> &[Span] {
+        &self.spans
     }
 
-    fn consume_string(&mut self) -> Result<Operand, String> {
+    fn consume_string(&mut self) -> Result<Operand, MdLogicError> {
+        let start = self.position();
         self.i.next();
         let mut is_closed = false;
         let mut range = TokenRange::new();
@@ -177,66 +163,103 @@ impl<'a> Tokenizer<'a> {
                 is_closed = true;
                 range.set_end(_index);
                 break;
-            } else {
-                range.set_start(_index);
             }
         }
 
         if is_closed {
-            let str_literal = &self.expression[range.started_at..range.ended_at];
+            let str_literal = &self.expression[start + 1..range.ended_at];
 
             return Ok(Operand::Primitive(Value::String(str_literal.to_string())));
         } else {
-            return Err(format!("no closing \" at {}", range.started_at));
+            let end = self.position();
+            return Err(MdLogicError::unexpected("closing \"", "end of expression")
+                .at(self.span(start, end)));
         }
     }
 
-    fn consume_operator(&mut self) -> Result<Operand, String> {
-        let mut range = TokenRange::new();
+    /// `@"2026-12-01"`, `@"2026-12-01T10:00:00Z"` or `@"P1D"`
+    fn consume_temporal(&mut self) -> Result<Operand, MdLogicError> {
+        let start = self.position();
+        self.i.next();
+        match self.i.peek() {
+            Some(&(_, '"')) => {}
+            Some(&(index, c)) => {
+                return Err(MdLogicError::unexpected("\" after @", &format!("{:?}", c))
+                    .at(self.span(index, index + c.len_utf8())))
+            }
+            None => {
+                return Err(MdLogicError::unexpected("\" after @", "end of expression")
+                    .at(self.span(start, start + 1)))
+            }
+        }
 
-        while let Some(&(_index, c)) = self.i.peek() {
-            if check_if_operator(&c) {
-                range.set_start(_index);
-                self.i.next();
-            } else {
-                range.set_end(_index);
-                break;
+        let string = self.consume_string()?;
+        let end = self.position();
+        let span = self.span(start, end);
+        match string {
+            Operand::Primitive(Value::String(text)) => {
+                temporal::parse_literal(&text).map_err(|e| MdLogicError::syntax(e).at(span))
             }
+            _ => Err(MdLogicError::unexpected("a string after @", "another value").at(span)),
+        }
+    }
 
-            range.set_end(_index + 1);
+    /// A date written as `2026-12-01`, which would otherwise be a subtraction
+    fn consume_date(&mut self) -> Result<Option<Operand>, MdLogicError> {
+        let start = match self.i.peek() {
+            Some(&(index, _)) => index,
+            None => return Ok(None),
+        };
+        let rest = &self.expression.as_bytes()[start..];
+        let shape = b"0000-00-00";
+        let is_date = rest.len() >= shape.len()
+            && shape.iter().zip(rest).all(|(s, c)| match s {
+                b'0' => c.is_ascii_digit(),
+                _ => s == c,
+            })
+            && !rest
+                .get(shape.len())
+                .is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'.');
+        if !is_date {
+            return Ok(None);
         }
 
-        let operator = &self.expression[range.started_at..range.ended_at];
+        let text = &self.expression[start..start + shape.len()];
+        let date = Date::parse(text)
+            .map_err(|e| MdLogicError::syntax(e).at(self.span(start, start + shape.len())))?;
+        for _ in 0..shape.len() {
+            self.i.next();
+        }
+        Ok(Some(Operand::Date(date)))
+    }
 
-        let o = match operator {
-            "+" => Some(Operator::Plus),
-            "-" => Some(Operator::Substract),
-            "*" => Some(Operator::Multiply),
-            "/" => Some(Operator::Division),
-            "=" => Some(Operator::E),
-            "!=" => Some(Operator::NE),
-            "<" => Some(Operator::L),
-            "<=" => Some(Operator::LE),
-            ">" => Some(Operator::G),
-            ">=" => Some(Operator::GE),
-            _ => None,
-        };
+    /// Consumes the longest operator, so `<=!` is `<=` followed by `!`
+    fn consume_operator(&mut self) -> Result<Operand, MdLogicError> {
+        let start = self.position();
+        let rest = &self.expression[start..];
 
-        if let Some(token) = o {
-            return Ok(Operand::OperatorToken(token));
-        } else {
-            return Err(format!(
-                "unsupported operator \"{:?}\" at {} ",
-                o, range.started_at
-            ));
+        for (symbol, operator) in OPERATORS {
+            if rest.starts_with(symbol) {
+                for _ in 0..symbol.len() {
+                    self.i.next();
+                }
+                return Ok(Operand::OperatorToken(operator));
+            }
         }
+
+        let unsupported: String = rest.chars().take_while(check_if_operator).collect();
+        let end = start + unsupported.len();
+        Err(
+            MdLogicError::unexpected("an operator", &format!("\"{}\"", unsupported))
+                .at(self.span(start, end)),
+        )
     }
 
-    fn consume_variable(&mut self) -> Result<Operand, String> {
+    fn consume_variable(&mut self) -> Result<Operand, MdLogicError> {
         let mut range = TokenRange::new();
 
         while let Some(&(_index, c)) = self.i.peek() {
-            if check_if_operand(&c) {
+            if check_if_operand(&c) || (range.tracking && check_if_path_char(&c)) {
                 range.set_start(_index);
                 self.i.next();
             } else {
@@ -254,18 +277,72 @@ impl<'a> Tokenizer<'a> {
             return Ok(Operand::Primitive(Value::Bool(variable == "true")));
         }
 
+        match variable {
+            "and" => return Ok(Operand::OperatorToken(Operator::And)),
+            "or" => return Ok(Operand::OperatorToken(Operator::Or)),
+            "not" => return Ok(Operand::OperatorToken(Operator::Not)),
+            _ => {}
+        }
+
         let parts_not_empty = variable.split('.').all(|part| part.len() > 0);
 
         if parts_not_empty == false {
-            return Err(format!(
-                "incorrect path \"{}\" at {}",
-                variable, range.started_at,
-            ));
+            return Err(
+                MdLogicError::syntax(format!("incorrect path \"{}\"", variable))
+                    .at(self.span(range.started_at, range.ended_at + 1)),
+            );
+        }
+
+        if let Some(name) = self.consume_function_name(variable) {
+            return Ok(Operand::Function(name, 0));
         }
         return Ok(Operand::Variable(variable.to_string()));
     }
 
-    fn consume_number(&mut self) -> Result<Operand, String> {
+    /// `first` and the words after it when an opening paren follows them, as
+    /// in `string length(name)`, consuming everything up to the paren. Only
+    /// standard library names, as `date and time`, may contain keywords.
+    fn consume_function_name(&mut self, first: &str) -> Option<String> {
+        let start = match self.i.peek() {
+            Some(&(index, _)) => index,
+            None => return None,
+        };
+        let rest = &self.expression[start..];
+
+        let mut words = vec![first];
+        let mut offset = 0;
+        loop {
+            offset += rest[offset..].len() - rest[offset..].trim_start().len();
+            let word: &str = match rest[offset..].find(|c| !check_if_path_char(&c)) {
+                Some(end) => &rest[offset..offset + end],
+                None => &rest[offset..],
+            };
+
+            if word.is_empty() {
+                break;
+            }
+            if !word.starts_with(|c| check_if_operand(&c)) {
+                return None;
+            }
+            words.push(word);
+            offset += word.len();
+        }
+
+        if !rest[offset..].starts_with('(') {
+            return None;
+        }
+        let name = words.join(" ");
+        let has_keyword = words.iter().any(|w| KEYWORDS.contains(w));
+        if has_keyword && !builtins::FUNCTIONS.iter().any(|(f, _)| *f == name) {
+            return None;
+        }
+        while self.position() < start + offset {
+            self.i.next();
+        }
+        Some(name)
+    }
+
+    fn consume_number(&mut self) -> Result<Operand, MdLogicError> {
         let mut range = TokenRange::new();
         let mut has_dot = false;
 
@@ -294,16 +371,90 @@ impl<'a> Tokenizer<'a> {
         let number = &self.expression[range.started_at..range.ended_at + 1];
 
         if number.len() == 0 {
-            return Err(format!("empty number at {}", range.started_at));
+            let start = range.started_at;
+            return Err(
+                MdLogicError::syntax("empty number".to_string()).at(self.span(start, start))
+            );
         }
 
-        let res_number = number.parse::<f64>().unwrap();
-        let res_operand = Operand::Primitive(Value::Number(Number::from_f64(res_number).unwrap()));
-        return Ok(res_operand);
+        match Decimal::parse(number) {
+            Some(n) => Ok(Operand::Number(n)),
+            None => Err(
+                MdLogicError::unexpected("a number", &format!("\"{}\"", number))
+                    .at(self.span(range.started_at, range.ended_at + 1)),
+            ),
+        }
     }
 
     pub fn insert_start(&mut self, o: Operand) {
         self.operands.insert(0, o);
+        self.spans.insert(0, Span::default());
+    }
+
+    /// Completes a table input cell with the `input` value: `< 10 and > 2` becomes
+    /// `input < 10 and input > 2`, and `"Fall" or not "Winter"` becomes
+    /// `input = "Fall" or not input = "Winter"`. Parts of the cell between
+    /// `and` and `or` which are values are compared for equality, parts which
+    /// already compare, as `guestCount > 2`, are kept. Cells using the `?`
+    /// placeholder, as `starts with(?, "F")`, get it replaced instead.
+    pub fn insert_input(&mut self, input: Operand) {
+        let placeholder = Operand::Variable(INPUT_PLACEHOLDER.to_string());
+        if self.operands.contains(&placeholder) {
+            for o in self.operands.iter_mut().filter(|o| **o == placeholder) {
+                *o = input.clone();
+            }
+            return;
+        }
+
+        // operands with their spans, the inserted ones take the span of the operand they precede
+        let mut parts: Vec<Vec<(Operand, Span)>> = vec![vec![]];
+        let mut depth = 0;
+        for (o, span) in self.operands.drain(..).zip(self.spans.drain(..)) {
+            match &o {
+                Operand::OpenParen => depth += 1,
+                Operand::CloseParen => depth -= 1,
+                Operand::OperatorToken(Operator::And | Operator::Or) if depth == 0 => {
+                    parts.push(vec![(o, span)]);
+                    continue;
+                }
+                _ => {}
+            }
+            if let Some(part) = parts.last_mut() {
+                part.push((o, span));
+            }
+        }
+
+        for mut part in parts {
+            let mut at = part
+                .iter()
+                .position(|(o, _)| {
+                    !matches!(
+                        o,
+                        Operand::OperatorToken(Operator::And | Operator::Or | Operator::Not)
+                    )
+                })
+                .unwrap_or(part.len());
+
+            let compares = part
+                .iter()
+                .any(|(o, _)| matches!(o, Operand::OperatorToken(t) if t.is_comparison()));
+            match part.get(at) {
+                Some((Operand::OperatorToken(t), span)) if t.is_comparison() => {
+                    part.insert(at, (input.clone(), *span));
+                }
+                Some((_, span)) if !compares => {
+                    let span = *span;
+                    part.insert(at, (input.clone(), span));
+                    at += 1;
+                    part.insert(at, (Operand::OperatorToken(Operator::E), span));
+                }
+                _ => {}
+            }
+            for (o, span) in part {
+                self.operands.push(o);
+                self.spans.push(span);
+            }
+        }
     }
 
     pub fn starts_with_operand(&self) -> bool {
@@ -315,41 +466,245 @@ impl<'a> Tokenizer<'a> {
     }
 }
 
+/// Shunting yard conversion of operands in written order to postfix, errors
+/// point at the `spans` of the operands when they are given
+pub fn infix_to_postfix(
+    operands: Vec<Operand>,
+    spans: &[Span],
+) -> Result<Vec<Operand>, MdLogicError> {
+    let at_operand = |error: MdLogicError, index: usize| match spans.get(index) {
+        Some(span) => error.at(*span),
+        None => error,
+    };
+    let count = operands.len();
+    let mut stack: Vec<Operand> = Vec::with_capacity(50);
+    let mut postfix: Vec<Operand> = Vec::with_capacity(operands.len());
+    // for every open paren its index and the commas in it, `None` when it only groups
+    let mut calls: Vec<(usize, Option<usize>)> = vec![];
+    let mut previous: Option<Operand> = None;
+
+    for (index, o) in operands.into_iter().enumerate() {
+        if let Some(Operand::Function(name, _)) = &previous {
+            if o != Operand::OpenParen {
+                let expected = format!("\"(\" after function {}", name);
+                let error = MdLogicError::unexpected(&expected, &describe(&o));
+                return Err(at_operand(error, index));
+            }
+        }
+        let current = o.clone();
+
+        match o {
+            Operand::Primitive(_)
+            | Operand::Number(_)
+            | Operand::Variable(_)
+            | Operand::Date(_)
+            | Operand::DateTime(_)
+            | Operand::Duration(_) => {
+                postfix.push(o);
+            }
+            Operand::Function(..) => {
+                stack.push(o);
+            }
+            Operand::OpenParen => {
+                let is_call = matches!(previous, Some(Operand::Function(..)));
+                calls.push((index, if is_call { Some(0) } else { None }));
+                stack.push(o);
+            }
+            Operand::Comma => {
+                if matches!(previous, None | Some(Operand::OpenParen | Operand::Comma)) {
+                    let error = MdLogicError::unexpected("a function argument", "\",\"");
+                    return Err(at_operand(error, index));
+                }
+                while let Some(Operand::OperatorToken(_)) = stack.last() {
+                    if let Some(s_item) = stack.pop() {
+                        postfix.push(s_item);
+                    }
+                }
+                match calls.last_mut() {
+                    Some((_, Some(commas))) => *commas += 1,
+                    _ => {
+                        let error =
+                            MdLogicError::syntax(", outside of a function call".to_string());
+                        return Err(at_operand(error, index));
+                    }
+                }
+            }
+            Operand::CloseParen => {
+                let mut found = false;
+                while let Some(s_item) = stack.pop() {
+                    match s_item {
+                        Operand::OpenParen => {
+                            found = true;
+                            break;
+                        }
+                        _ => {
+                            postfix.push(s_item);
+                        }
+                    }
+                }
+
+                if found == false {
+                    let error =
+                        MdLogicError::unexpected("an operand or operator", "\")\" without \"(\"");
+                    return Err(at_operand(error, index));
+                }
+
+                if let Some((_, Some(commas))) = calls.pop() {
+                    let arity = match previous {
+                        Some(Operand::OpenParen) => 0,
+                        Some(Operand::Comma) => {
+                            let error = MdLogicError::unexpected("a function argument", "\")\"");
+                            return Err(at_operand(error, index));
+                        }
+                        _ => commas + 1,
+                    };
+                    if let Some(Operand::Function(name, _)) = stack.pop() {
+                        postfix.push(Operand::Function(name, arity));
+                    }
+                }
+            }
+            Operand::OperatorToken(ref t) => {
+                // a prefix operator has no left operand to finish first
+                if stack.len() == 0 || t.is_unary() {
+                    stack.push(o);
+                } else {
+                    loop {
+                        if let Some(Operand::OpenParen) = stack.last() {
+                            stack.push(o);
+                            break;
+                        } else if let Some(Operand::OperatorToken(so)) = stack.last() {
+                            if precedence(so) >= precedence(&t) {
+                                if let Some(poped_stack_item) = stack.pop() {
+                                    postfix.push(poped_stack_item);
+                                } else {
+                                    let error = MdLogicError::syntax("stack underflow".to_string());
+                                    return Err(at_operand(error, index));
+                                }
+                            } else {
+                                stack.push(o);
+                                break;
+                            }
+                        } else {
+                            stack.push(o);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        previous = Some(current);
+    }
+
+    if let Some(Operand::Function(name, _)) = previous {
+        let expected = format!("\"(\" after function {}", name);
+        let error = MdLogicError::unexpected(&expected, "end of expression");
+        return Err(at_operand(error, count - 1));
+    }
+    if let Some((open, _)) = calls.last() {
+        let error = MdLogicError::unexpected("\")\" for this \"(\"", "end of expression");
+        return Err(at_operand(error, *open));
+    }
+    while let Some(s_item) = stack.pop() {
+        postfix.push(s_item);
+    }
+
+    return Ok(postfix);
+}
+
+/// An operand as written, for errors
+fn describe(o: &Operand) -> String {
+    let text = match o {
+        Operand::Primitive(value) => return value.to_string(),
+        Operand::Number(n) => return n.to_string(),
+        Operand::Variable(name) | Operand::Function(name, _) => name.clone(),
+        Operand::OperatorToken(Operator::And) => "and".to_string(),
+        Operand::OperatorToken(Operator::Or) => "or".to_string(),
+        Operand::OperatorToken(Operator::Not) => "not".to_string(),
+        Operand::OperatorToken(operator) => OPERATORS
+            .iter()
+            .find(|(_, o)| o == operator)
+            .map_or(String::new(), |(symbol, _)| symbol.to_string()),
+        Operand::OpenParen => "(".to_string(),
+        Operand::CloseParen => ")".to_string(),
+        Operand::Comma => ",".to_string(),
+        Operand::Date(date) => date.to_string(),
+        Operand::DateTime(date_time) => date_time.to_string(),
+        Operand::Duration(duration) => duration.to_string(),
+    };
+    format!("\"{}\"", text)
+}
+
 fn check_if_operand(c: &char) -> bool {
     (c >= &'a' && c <= &'z') || (c >= &'A' && c <= &'Z')
 }
 
+/// characters of a variable path after its first letter
+fn check_if_path_char(c: &char) -> bool {
+    c.is_ascii_alphanumeric() || ['.', '_'].contains(c)
+}
+
 fn check_if_operator(c: &char) -> bool {
-    ['+', '-', '/', '*', '<', '=', '>', '!'].contains(c)
+    ['+', '-', '/', '*', '<', '=', '>', '!', '&', '|'].contains(c)
 }
 
+/// variable standing for the input value in a table input cell, as in `starts with(?, "A")`
+pub const INPUT_PLACEHOLDER: &str = "?";
+
+/// words which are no variables or function names
+const KEYWORDS: [&str; 5] = ["and", "or", "not", "true", "false"];
+
+/// symbols of operators, two character ones first
+const OPERATORS: [(&str, Operator); 14] = [
+    ("!=", Operator::NE),
+    ("<=", Operator::LE),
+    (">=", Operator::GE),
+    ("&&", Operator::And),
+    ("||", Operator::Or),
+    ("==", Operator::E),
+    ("+", Operator::Plus),
+    ("-", Operator::Substract),
+    ("*", Operator::Multiply),
+    ("/", Operator::Division),
+    ("=", Operator::E),
+    ("<", Operator::L),
+    (">", Operator::G),
+    ("!", Operator::Not),
+];
+
 fn check_if_digit(c: &char) -> bool {
     c >= &'0' && c <= &'9'
 }
 
 /*
-            3
-          ____
-        __|__|___
-        |2 level|      * /
-    ____|_______|____
-    |    1 Level    |  + -
- ______________________
-|  0 other ... =, >, <  |
+                  ____
+                __|__|___
+                |6 level|      * /
+            ____|_______|____
+            |    5 Level    |  + -
+        ____|_______________|____
+        |       4 Level         |  =, !=, >, <, >=, <=
+    ____|_______________________|____
+    |           3 Level             |  not, !
+ ___|_______________________________|___
+|  2 and, &&   ...   1 or, ||          |
 
 */
 
 fn precedence(c: &Operator) -> i32 {
     match c {
-        Operator::Plus | Operator::Substract => 1,
-        Operator::Division | Operator::Multiply => 2,
-        _ => -1,
+        Operator::Or => 1,
+        Operator::And => 2,
+        Operator::Not => 3,
+        Operator::L | Operator::G | Operator::LE | Operator::GE | Operator::E | Operator::NE => 4,
+        Operator::Plus | Operator::Substract => 5,
+        Operator::Division | Operator::Multiply => 6,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::number::Decimal;
 
     #[test]
     fn parses_expression() -> Result<(), String> {
@@ -366,14 +721,14 @@ mod tests {
         assert_eq!(
             postfix,
             vec![
-                Operand::Primitive(Value::Number(Number::from_f64(100.0).unwrap())),
+                Operand::Number(Decimal::from(100)),
                 Operand::Variable("aA".to_string()),
                 Operand::Variable("b".to_string()),
                 Operand::Variable("c".to_string()),
                 Operand::OperatorToken(Operator::Multiply),
                 Operand::OperatorToken(Operator::Plus),
                 Operand::Variable("d".to_string()),
-                Operand::Primitive(Value::Number(Number::from_f64(2.0).unwrap())),
+                Operand::Number(Decimal::from(2)),
                 Operand::OperatorToken(Operator::Multiply),
                 Operand::OperatorToken(Operator::Substract),
                 Operand::OperatorToken(Operator::LE),
@@ -401,7 +756,7 @@ mod tests {
                 Operand::OperatorToken(Operator::Multiply),
                 Operand::OperatorToken(Operator::Plus),
                 Operand::Variable("d".to_string()),
-                Operand::Primitive(Value::Number(Number::from_f64(2.0).unwrap())),
+                Operand::Number(Decimal::from(2)),
                 Operand::OperatorToken(Operator::Multiply),
                 Operand::OperatorToken(Operator::Substract),
             ]
@@ -454,6 +809,40 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn parses_non_ascii_string_literals() -> Result<(), String> {
+        let formula = "name = \"Zoë\" and \"ab\" != \"é\" and \"\" = x";
+        let mut parser = Tokenizer::new(formula);
+        parser.parse()?;
+
+        // spans count characters, not bytes
+        assert_eq!(parser.spans()[2], Span::new(7, 12));
+        assert_eq!(parser.spans()[6], Span::new(25, 28));
+        assert_eq!(
+            parser.into_operands(),
+            vec![
+                Operand::Variable("name".to_string()),
+                Operand::OperatorToken(Operator::E),
+                Operand::Primitive(Value::String("Zoë".to_string())),
+                Operand::OperatorToken(Operator::And),
+                Operand::Primitive(Value::String("ab".to_string())),
+                Operand::OperatorToken(Operator::NE),
+                Operand::Primitive(Value::String("é".to_string())),
+                Operand::OperatorToken(Operator::And),
+                Operand::Primitive(Value::String("".to_string())),
+                Operand::OperatorToken(Operator::E),
+                Operand::Variable("x".to_string()),
+            ]
+        );
+
+        let error = Tokenizer::new("\"é\" # 1").parse().err();
+        assert_eq!(
+            error.map(|e| e.location().span),
+            Some(Some(Span::new(4, 5)))
+        );
+        Ok(())
+    }
+
     #[test]
     fn fail_string_missing_quote() -> Result<(), String> {
         let formula = "\"miss you";
@@ -485,9 +874,7 @@ mod tests {
 
         assert_eq!(
             postfix?,
-            vec![Operand::Primitive(Value::Number(
-                Number::from_f64(101.001).unwrap()
-            )),]
+            vec![Operand::Number(Decimal::parse("101.001").ok_or("number")?)]
         );
         Ok(())
     }
@@ -517,6 +904,170 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn succeeds_variable_path() -> Result<(), String> {
+        let mut parser = Tokenizer::new("order.customer_2.name");
+        parser.parse()?;
+
+        assert_eq!(
+            parser.to_postfix()?,
+            vec![Operand::Variable("order.customer_2.name".to_string())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_logical_operators() -> Result<(), String> {
+        let mut parser = Tokenizer::new("a>=5&&!b or not c");
+        parser.parse()?;
+
+        assert_eq!(
+            parser.to_postfix()?,
+            vec![
+                Operand::Variable("a".to_string()),
+                Operand::Number(Decimal::from(5)),
+                Operand::OperatorToken(Operator::GE),
+                Operand::Variable("b".to_string()),
+                Operand::OperatorToken(Operator::Not),
+                Operand::OperatorToken(Operator::And),
+                Operand::Variable("c".to_string()),
+                Operand::OperatorToken(Operator::Not),
+                Operand::OperatorToken(Operator::Or),
+            ]
+        );
+
+        let mut parser = Tokenizer::new("a |& b");
+        assert_eq!(
+            parser.parse(),
+            Err(MdLogicError::unexpected("an operator", "\"|&\"").at(Span::new(2, 4)))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn inserts_input_into_each_condition() -> Result<(), String> {
+        let mut parser = Tokenizer::new(">= 5 and < 10 or not 20 or size = 1");
+        parser.parse()?;
+        parser.insert_input(Operand::Variable("x".to_string()));
+
+        let number = |n: i64| Operand::Number(Decimal::from(n));
+        let x = || Operand::Variable("x".to_string());
+        assert_eq!(
+            parser.operands,
+            vec![
+                x(),
+                Operand::OperatorToken(Operator::GE),
+                number(5),
+                Operand::OperatorToken(Operator::And),
+                x(),
+                Operand::OperatorToken(Operator::L),
+                number(10),
+                Operand::OperatorToken(Operator::Or),
+                Operand::OperatorToken(Operator::Not),
+                x(),
+                Operand::OperatorToken(Operator::E),
+                number(20),
+                Operand::OperatorToken(Operator::Or),
+                Operand::Variable("size".to_string()),
+                Operand::OperatorToken(Operator::E),
+                number(1),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_function_calls() -> Result<(), String> {
+        let mut parser = Tokenizer::new("max(1, 2 + 3) * string length (name)");
+        parser.parse()?;
+
+        let number = |n: i64| Operand::Number(Decimal::from(n));
+        assert_eq!(
+            parser.to_postfix()?,
+            vec![
+                number(1),
+                number(2),
+                number(3),
+                Operand::OperatorToken(Operator::Plus),
+                Operand::Function("max".to_string(), 2),
+                Operand::Variable("name".to_string()),
+                Operand::Function("string length".to_string(), 1),
+                Operand::OperatorToken(Operator::Multiply),
+            ]
+        );
+
+        let mut parser = Tokenizer::new("now() and a");
+        parser.parse()?;
+        assert_eq!(
+            parser.to_postfix()?,
+            vec![
+                Operand::Function("now".to_string(), 0),
+                Operand::Variable("a".to_string()),
+                Operand::OperatorToken(Operator::And),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn parses_temporal_literals() -> Result<(), String> {
+        let mut parser = Tokenizer::new(r#"2026-12-01 + @"P1D" < @"2026-12-02T10:00:00Z""#);
+        parser.parse()?;
+        assert_eq!(
+            parser.into_operands(),
+            vec![
+                temporal::parse_literal("2026-12-01")?,
+                Operand::OperatorToken(Operator::Plus),
+                temporal::parse_literal("P1D")?,
+                Operand::OperatorToken(Operator::L),
+                temporal::parse_literal("2026-12-02T10:00:00Z")?,
+            ]
+        );
+
+        // not shaped as a date, still a subtraction
+        let mut parser = Tokenizer::new("2026-12");
+        parser.parse()?;
+        assert_eq!(parser.into_operands().len(), 3);
+
+        for formula in ["2026-02-30", r#"@"tomorrow""#, "@P1D"] {
+            assert!(Tokenizer::new(formula).parse().is_err(), "{}", formula);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_malformed_calls() -> Result<(), String> {
+        let argument = |found| MdLogicError::unexpected("a function argument", found);
+        for (formula, error) in [
+            ("max(1,)", argument("\")\"").at(Span::new(6, 7))),
+            ("max(, 1)", argument("\",\"").at(Span::new(4, 5))),
+            (
+                "(1, 2)",
+                MdLogicError::syntax(", outside of a function call".to_string())
+                    .at(Span::new(2, 3)),
+            ),
+            (
+                "(1 + 2",
+                MdLogicError::unexpected("\")\" for this \"(\"", "end of expression")
+                    .at(Span::new(0, 1)),
+            ),
+        ] {
+            let mut parser = Tokenizer::new(formula);
+            parser.parse()?;
+            assert_eq!(parser.to_postfix(), Err(error), "{}", formula);
+        }
+
+        let call_without_paren = vec![
+            Operand::Function("f".to_string(), 0),
+            Operand::Variable("a".to_string()),
+        ];
+        assert_eq!(
+            infix_to_postfix(call_without_paren, &[]),
+            Err(MdLogicError::unexpected("\"(\" after function f", "\"a\""))
+        );
+        Ok(())
+    }
+
     #[test]
     fn succeeds_single_boolean() -> Result<(), String> {
         let formula = "true";
@@ -539,9 +1090,7 @@ mod tests {
         let start_with_operand = parser.starts_with_operand();
         assert_eq!(start_with_operand, true);
 
-        parser.insert_start(Operand::Primitive(Value::Number(
-            Number::from_f64(11.0).unwrap(),
-        )));
+        parser.insert_start(Operand::Number(Decimal::from(11)));
 
         let postfix = parser.to_postfix();
         assert!(postfix.is_ok());
@@ -549,8 +1098,8 @@ mod tests {
         assert_eq!(
             postfix?,
             vec![
-                Operand::Primitive(Value::Number(Number::from_f64(11.0).unwrap())),
-                Operand::Primitive(Value::Number(Number::from_f64(10.0).unwrap())),
+                Operand::Number(Decimal::from(11)),
+                Operand::Number(Decimal::from(10)),
                 Operand::OperatorToken(Operator::L)
             ]
         );
@@ -569,7 +1118,7 @@ mod tests {
         parser.insert_start(Operand::OperatorToken(Operator::E));
         parser.insert_start(
             // Operand::Number(11.0)
-            Operand::Primitive(Value::Number(Number::from_f64(11.0).unwrap())),
+            Operand::Number(Decimal::from(11)),
         );
 
         let postfix = parser.to_postfix();
@@ -578,8 +1127,8 @@ mod tests {
         assert_eq!(
             postfix?,
             vec![
-                Operand::Primitive(Value::Number(Number::from_f64(11.0).unwrap())),
-                Operand::Primitive(Value::Number(Number::from_f64(10.0).unwrap())),
+                Operand::Number(Decimal::from(11)),
+                Operand::Number(Decimal::from(10)),
                 Operand::OperatorToken(Operator::E)
             ]
         );
@@ -643,7 +1192,7 @@ fn is_postfix_valid(postfix: &Vec<Operand>) -> bool {
     for p in postfix {
         if let Operand::OperatorToken(_o) = p {
             let right = stack.pop().is_some();
-            let left = stack.pop().is_some();
+            let left = _o.is_unary() || stack.pop().is_some();
 
             if right == left {
                 stack.push(&Operand::Primitive(Value::Null));
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
-use serde_json::{Number, Result, Value};
+use serde_json::{Result, Value};
 
 use crate::context::get_context_var;
+use crate::number::{self, Decimal};
 
 type OrderingOperation = Vec<AllCombined>;
 
@@ -57,7 +58,9 @@ impl Opss {
             }
             Opss::Eq(l) => {
                 if let Some(built_list) = binary_op_vars(l, context) {
-                    return AllCombined::Primitive(Value::Bool(&built_list[0] == &built_list[1]));
+                    return AllCombined::Primitive(Value::Bool(
+                        built_list[0].equals(&built_list[1]),
+                    ));
                 }
                 AllCombined::Primitive(Value::Bool(false))
             }
@@ -75,23 +78,18 @@ impl Opss {
             }
             Opss::Neq(l) => {
                 if let Some(built_list) = binary_op_vars(l, context) {
-                    return AllCombined::Primitive(Value::Bool(&built_list[0] != &built_list[1]));
+                    return AllCombined::Primitive(Value::Bool(
+                        !built_list[0].equals(&built_list[1]),
+                    ));
                 }
                 AllCombined::Primitive(Value::Bool(false))
             }
             Opss::Plus(l) => {
                 if let Some(built_list) = binary_op_vars(l, context) {
+                    if let Some(sum) = calculate(&built_list, Decimal::checked_add) {
+                        return sum;
+                    }
                     match (&built_list[0], &built_list[1]) {
-                        (
-                            AllCombined::Primitive(Value::Number(n1)),
-                            AllCombined::Primitive(Value::Number(n2)),
-                        ) => {
-                            let res = n1.as_f64().unwrap_or(0.0) + n2.as_f64().unwrap_or(0.0);
-
-                            return AllCombined::Primitive(Value::Number(
-                                Number::from_f64(res).unwrap(),
-                            ));
-                        }
                         (
                             AllCombined::Primitive(Value::String(s1)),
                             AllCombined::Primitive(Value::String(s2)),
@@ -101,65 +99,16 @@ impl Opss {
                 }
                 AllCombined::Primitive(Value::Null)
             }
-            Opss::Minus(l) => {
-                if let Some(built_list) = binary_op_vars(l, context) {
-                    match (&built_list[0], &built_list[1]) {
-                        (
-                            AllCombined::Primitive(Value::Number(n1)),
-                            AllCombined::Primitive(Value::Number(n2)),
-                        ) => {
-                            let res = n1.as_f64().unwrap_or(0.0) - n2.as_f64().unwrap_or(0.0);
-
-                            return AllCombined::Primitive(Value::Number(
-                                Number::from_f64(res).unwrap(),
-                            ));
-                        }
-                        (_, _1) => return AllCombined::Primitive(Value::Null),
-                    }
-                }
-                AllCombined::Primitive(Value::Null)
-            }
-            Opss::Multiply(l) => {
-                if let Some(built_list) = binary_op_vars(l, context) {
-                    match (&built_list[0], &built_list[1]) {
-                        (
-                            AllCombined::Primitive(Value::Number(n1)),
-                            AllCombined::Primitive(Value::Number(n2)),
-                        ) => {
-                            let res = n1.as_f64().unwrap_or(0.0) * n2.as_f64().unwrap_or(0.0);
-
-                            return AllCombined::Primitive(Value::Number(
-                                Number::from_f64(res).unwrap(),
-                            ));
-                        }
-                        (_, _1) => return AllCombined::Primitive(Value::Null),
-                    }
-                }
-                AllCombined::Primitive(Value::Null)
-            }
-            Opss::Division(l) => {
-                if let Some(built_list) = binary_op_vars(l, context) {
-                    match (&built_list[0], &built_list[1]) {
-                        (
-                            AllCombined::Primitive(Value::Number(n1)),
-                            AllCombined::Primitive(Value::Number(n2)),
-                        ) => {
-                            let second = n2.as_f64().unwrap_or(0.0);
-                            if second == 0.0 {
-                                return AllCombined::Primitive(Value::Null);
-                            }
-
-                            let res = n1.as_f64().unwrap_or(0.0) / second;
-
-                            return AllCombined::Primitive(Value::Number(
-                                Number::from_f64(res).unwrap(),
-                            ));
-                        }
-                        (_, _1) => return AllCombined::Primitive(Value::Null),
-                    }
-                }
-                AllCombined::Primitive(Value::Null)
-            }
+            Opss::Minus(l) => binary_op_vars(l, context)
+                .and_then(|built_list| calculate(&built_list, Decimal::checked_sub))
+                .unwrap_or(AllCombined::Primitive(Value::Null)),
+            Opss::Multiply(l) => binary_op_vars(l, context)
+                .and_then(|built_list| calculate(&built_list, Decimal::checked_mul))
+                .unwrap_or(AllCombined::Primitive(Value::Null)),
+            // null when dividing by zero
+            Opss::Division(l) => binary_op_vars(l, context)
+                .and_then(|built_list| calculate(&built_list, Decimal::checked_div))
+                .unwrap_or(AllCombined::Primitive(Value::Null)),
             Opss::Var(key) => AllCombined::Primitive(get_context_var(key, &context)),
             Opss::And(l) => {
                 let l_results = execute_combined_list(&l, context);
@@ -179,6 +128,12 @@ impl Opss {
     }
 }
 
+/// `f` of the first two executed rules, `None` when one of them isn't a number
+fn calculate(l: &[AllCombined], f: fn(Decimal, Decimal) -> Option<Decimal>) -> Option<AllCombined> {
+    let (a, b) = (l[0].number()?, l[1].number()?);
+    Some(f(a, b).map_or(AllCombined::Primitive(Value::Null), AllCombined::Number))
+}
+
 fn execute_combined_list(l: &Vec<AllCombined>, context: &Value) -> Vec<AllCombined> {
     l.iter().map(|l_item| l_item.execute(context)).collect()
 }
@@ -189,6 +144,9 @@ pub enum AllCombined {
     Ops(Opss),
     OpList(Vec<AllCombined>),
     Primitive(Value),
+    /// result of arithmetic, kept exact for the next operation
+    #[serde(skip_deserializing)]
+    Number(Decimal),
 }
 
 impl AllCombined {
@@ -200,6 +158,27 @@ impl AllCombined {
             }
             AllCombined::Ops(o) => o.execute(context),
             AllCombined::Primitive(v) => AllCombined::Primitive(v.clone()),
+            AllCombined::Number(n) => AllCombined::Number(*n),
+        }
+    }
+
+    /// Exact value of a number, also of one given as a json number
+    fn number(&self) -> Option<Decimal> {
+        match self {
+            AllCombined::Number(n) => Some(*n),
+            AllCombined::Primitive(Value::Number(n)) => Decimal::from_number(n),
+            _ => None,
+        }
+    }
+
+    /// `=` of executed rules, numbers are equal by value so `10.0` equals `10`
+    pub fn equals(&self, other: &AllCombined) -> bool {
+        match (self, other) {
+            (AllCombined::Primitive(a), AllCombined::Primitive(b)) => number::equal(a, b),
+            _ => match (self.number(), other.number()) {
+                (Some(a), Some(b)) => a == b,
+                _ => self == other,
+            },
         }
     }
 }
@@ -211,20 +190,11 @@ impl std::cmp::Ord for AllCombined {
             (
                 AllCombined::Primitive(Value::Number(n1)),
                 AllCombined::Primitive(Value::Number(n2)),
-            ) => {
-                // Todo, compare as i64 too
-                let n1_num = n1.as_f64().unwrap_or(0.0);
-                let n2_num = n2.as_f64().unwrap_or(0.0);
-                if n1_num > n2_num {
-                    return std::cmp::Ordering::Greater;
-                }
-                if n1_num < n2_num {
-                    return std::cmp::Ordering::Less;
-                }
-
-                return std::cmp::Ordering::Equal;
-            }
-            (_, _1) => std::cmp::Ordering::Equal,
+            ) => number::compare(n1, n2).unwrap_or(std::cmp::Ordering::Equal),
+            _ => match (self.number(), other.number()) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                _ => std::cmp::Ordering::Equal,
+            },
         }
     }
 }
@@ -248,7 +218,7 @@ mod tests {
             (r#" { ">" : [10,3] }"#, true),
             (r#" { "<" : [3,10] }"#, true),
             (r#" { "<" : [30,10] }"#, false),
-            (r#" { "=" : [10.0,10] }"#, false), // different types
+            (r#" { "=" : [10.0,10] }"#, true),
             (r#" { "=" : [10.0,10.0] }"#, true),
             (r#" { "<=" : [10.0,10.0] }"#, true),
             (r#" { "<=" : [12.0,10.0] }"#, false),
@@ -268,6 +238,12 @@ mod tests {
             (r#" { "=" : [{"*": [2,3]}, 6.0] }"#, true),
             (r#" { "=" : [{"/": [6,3]}, 2.0] }"#, true),
             (r#" { "=" : [{"/": [6,0]}, null] }"#, true),
+            (r#" { "=" : [{"+": [0.1,0.2]}, 0.3] }"#, true),
+            (r#" { "=" : [{"*": [19.99,3]}, 59.97] }"#, true),
+            (r#" { "=" : [{"/": [7,2]}, 3.5] }"#, true),
+            (r#" { "=" : [{"*": [{"/": [2,3]}, 3]}, 2] }"#, true),
+            (r#" { ">" : [{"-": [{"/": [1,3]}, 0.3]}, 0.03] }"#, true),
+            (r#" { ">" : [10.5,10] }"#, true),
             (r#" { "=" : [{"var" : "champ.name"}, "Fezzig"] }"#, true),
             (
                 r#" { "=" : [{"var" : "challenger.name"}, "Dread Pirate Roberts"] }"#,
@@ -1,4 +1,6 @@
 pub mod context;
+pub mod error;
 pub mod expression_parser;
 pub mod json_logic;
 pub mod logic_table;
+pub mod number;
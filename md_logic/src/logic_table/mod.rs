@@ -1,62 +1,382 @@
-use crate::context::var_to_operand;
+use crate::context::{get_context_var, resolve_var};
+use crate::error::{char_offset, MdLogicError, Span};
 
+use crate::expression_parser::environment::Environment;
 use crate::expression_parser::executor::interpret;
-use crate::expression_parser::operand::{Operand, Operator};
+use crate::expression_parser::operand::Operand;
+use crate::expression_parser::temporal::{Date, DateTime, Duration};
 use crate::expression_parser::tokenizer::Tokenizer;
+use crate::number::{self, Decimal};
 use serde::Serialize;
 use serde_json::Value;
 
 use std::collections::HashMap;
 
+mod unary_tests;
+
+/// Aggregation of a `COLLECT` hit policy, over the single output column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregation {
+    Sum,
+    Min,
+    Max,
+    /// number of distinct output values
+    Count,
+}
+
+/// How the matching rows of a table make up its result, declared by a
+/// `Hit policy: ...` line above the table. Tables without one are
+/// `Collect`, returning every matching row as tables did before hit policies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitPolicy {
+    /// at most one row may match
+    Unique,
+    /// the first matching row
+    First,
+    /// the matching row with the highest priority output values
+    Priority,
+    /// all matching rows must have the same outputs
+    Any,
+    /// all matching rows, in table order
+    RuleOrder,
+    /// all matching rows, by priority of their output values
+    OutputOrder,
+    /// all matching rows, or their aggregated output
+    Collect(Option<Aggregation>),
+}
+
+impl Default for HitPolicy {
+    fn default() -> Self {
+        HitPolicy::Collect(None)
+    }
+}
+
+impl HitPolicy {
+    /// Parses the full name, as in `COLLECT SUM`, or the DMN abbreviation, as in `C+`
+    pub fn from_declaration(declaration: &str) -> Result<HitPolicy, String> {
+        let words: Vec<String> = declaration
+            .split_whitespace()
+            .map(|w| w.to_uppercase())
+            .collect();
+        let words: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+
+        let policy = match words[..] {
+            ["UNIQUE"] | ["U"] => HitPolicy::Unique,
+            ["FIRST"] | ["F"] => HitPolicy::First,
+            ["PRIORITY"] | ["P"] => HitPolicy::Priority,
+            ["ANY"] | ["A"] => HitPolicy::Any,
+            ["RULE", "ORDER"] | ["R"] => HitPolicy::RuleOrder,
+            ["OUTPUT", "ORDER"] | ["O"] => HitPolicy::OutputOrder,
+            ["COLLECT"] | ["C"] => HitPolicy::Collect(None),
+            ["COLLECT", "SUM"] | ["C+"] => HitPolicy::Collect(Some(Aggregation::Sum)),
+            ["COLLECT", "MIN"] | ["C<"] => HitPolicy::Collect(Some(Aggregation::Min)),
+            ["COLLECT", "MAX"] | ["C>"] => HitPolicy::Collect(Some(Aggregation::Max)),
+            ["COLLECT", "COUNT"] | ["C#"] => HitPolicy::Collect(Some(Aggregation::Count)),
+            _ => return Err(format!("unknown hit policy \"{}\"", declaration.trim())),
+        };
+        Ok(policy)
+    }
+}
+
+/// types of the type row, temporal ones are ISO-8601 strings in the context and outputs
+const COLUMN_TYPES: [&str; 6] = [
+    "string",
+    "number",
+    "boolean",
+    "date",
+    "date and time",
+    "duration",
+];
+
+/// Column metadata from the fourth definition row. The cell holds `##` for
+/// none, or `;` separated items:
+/// - `"Fall", "Winter"` lists the allowed values, for output columns from
+///   highest to lowest priority
+/// - `required`, the input must be set in the context
+/// - `default: "Stew"`, output used for empty cells and when no row matches
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnMeta {
+    /// literals as written in the table
+    pub allowed: Vec<String>,
+    pub default: Option<String>,
+    pub required: bool,
+}
+
+impl ColumnMeta {
+    /// Error spans are characters of `cell`
+    fn parse(cell: &str, column: &str, is_input: bool) -> Result<ColumnMeta, MdLogicError> {
+        let mut meta = ColumnMeta::default();
+        if cell == "##" {
+            return Ok(meta);
+        }
+
+        let span_of = |text: &str| {
+            let start = char_offset(cell, text);
+            Span::new(start, start + text.chars().count())
+        };
+        let literal_in_cell =
+            |text: &str| literal(text).map_err(|e| e.shifted(char_offset(cell, text)));
+
+        let mut default_span = Span::default();
+        for item in split_outside_quotes(cell, ';') {
+            if item.is_empty() {
+                continue;
+            }
+
+            if item == "required" {
+                if !is_input {
+                    let message = format!("output column \"{}\" can not be required", column);
+                    return Err(MdLogicError::table(message).at(span_of(item)));
+                }
+                meta.required = true;
+            } else if let Some(default) = item.strip_prefix("default:") {
+                if is_input {
+                    let message = format!("input column \"{}\" can not have a default", column);
+                    return Err(MdLogicError::table(message).at(span_of(item)));
+                }
+                literal_in_cell(default)?;
+                meta.default = Some(default.trim().to_string());
+                default_span = span_of(default.trim());
+            } else {
+                for value in split_outside_quotes(item, ',') {
+                    literal_in_cell(value)?;
+                    meta.allowed.push(value.to_string());
+                }
+            }
+        }
+
+        if let Some(default) = &meta.default {
+            if !meta.allows(&literal(default)?) {
+                let message = format!(
+                    "column \"{}\": default {} is not an allowed value",
+                    column, default
+                );
+                return Err(MdLogicError::table(message).at(default_span));
+            }
+        }
+        Ok(meta)
+    }
+
+    /// Whether `value` is one of the allowed values, any value is when none are listed
+    pub fn allows(&self, value: &Operand) -> bool {
+        self.allowed.is_empty() || self.position(value).is_some()
+    }
+
+    /// Index of `value` in the allowed values
+    pub fn position(&self, value: &Operand) -> Option<usize> {
+        self.allowed
+            .iter()
+            .position(|a| literal(a).is_ok_and(|a| a.equals(value)))
+    }
+}
+
+/// Splits on `separator` where it is not inside a string literal, trimming the parts
+fn split_outside_quotes(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut in_string = false;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        if c == '"' {
+            in_string = !in_string;
+        } else if c == separator && !in_string {
+            parts.push(text[start..index].trim());
+            start = index + c.len_utf8();
+        }
+    }
+    parts.push(text[start..].trim());
+    parts
+}
+
+/// Splits a table line on `|`, except escaped ones as in `\|\|`
+fn split_row(line: &str) -> Vec<&str> {
+    let mut columns = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        if c == '|' && !escaped {
+            columns.push(&line[start..index]);
+            start = index + 1;
+        }
+        escaped = c == '\\';
+    }
+    columns.push(&line[start..]);
+    columns
+}
+
+/// Value of a cell holding a single literal, as `"Fall"`, `8`, `true` or `2026-12-01`
+fn literal(text: &str) -> Result<Operand, MdLogicError> {
+    let mut parser = Tokenizer::new(text);
+    parser.parse()?;
+    match parser.to_postfix()?.as_slice() {
+        [operand @ (Operand::Primitive(_)
+        | Operand::Number(_)
+        | Operand::Date(_)
+        | Operand::DateTime(_)
+        | Operand::Duration(_))] => Ok(operand.clone()),
+        _ => {
+            let trimmed = text.trim();
+            let start = char_offset(text, trimmed);
+            let found = format!("\"{}\"", trimmed);
+            Err(MdLogicError::unexpected("a single value", &found)
+                .at(Span::new(start, start + trimmed.chars().count())))
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Definition {
     pub inputs: Vec<(String, String)>,
     pub outputs: Vec<(String, String)>,
+    pub input_meta: Vec<ColumnMeta>,
+    pub output_meta: Vec<ColumnMeta>,
 }
 
 #[derive(Debug)]
 pub struct Table {
     pub rows: Vec<Row>,
     pub defs: Definition,
+    pub hit_policy: HitPolicy,
+    /// how variables missing from the context are treated and which functions
+    /// expressions can call, `Environment::default()` after `parse`
+    pub env: Environment,
 }
 
 #[derive(Debug)]
 pub struct Row {
     pub cells: Vec<String>,
+    /// line of the markdown source, from 1
+    pub line: usize,
+    /// for every cell, the character of the line each of its characters is
+    /// at and the one after it ends
+    positions: Vec<Vec<usize>>,
+}
+
+impl Row {
+    /// Text of the cell of `column`, an error at the row when it has fewer cells
+    fn cell(&self, column: usize) -> Result<&String, MdLogicError> {
+        self.cells.get(column).ok_or_else(|| {
+            let message = format!(
+                "row has {} cells, expected {}",
+                self.cells.len(),
+                column + 1
+            );
+            let mut error = MdLogicError::table(message);
+            error.location_mut().line = Some(self.line);
+            error
+        })
+    }
+
+    /// `error` found in the cell of `column`, its span moved from the cell
+    /// text to the source line, the whole cell when it has none
+    fn locate(&self, mut error: MdLogicError, column: usize) -> MdLogicError {
+        let location = error.location_mut();
+        location.column = Some(column + 1);
+        location.line = Some(self.line);
+        if let Some(positions) = self.positions.get(column) {
+            let position = |index: usize| positions[index.min(positions.len() - 1)];
+            let span = location.span.unwrap_or(Span::new(0, usize::MAX));
+            location.span = Some(Span::new(position(span.start), position(span.end)));
+        }
+        error
+    }
+}
+
+/// Character of `line` each character of the trimmed `cell` is at, `\|`
+/// being one character of the cell, and the character after the cell
+fn cell_positions(line: &str, cell: &str) -> Vec<usize> {
+    let cell = cell.trim();
+    let mut position = char_offset(line, cell);
+    let mut positions = vec![];
+    let mut chars = cell.chars().peekable();
+    while let Some(c) = chars.next() {
+        positions.push(position);
+        position += 1;
+        if c == '\\' && chars.peek() == Some(&'|') {
+            chars.next();
+            position += 1;
+        }
+    }
+    positions.push(position);
+    positions
+}
+
+/// `error` about `part` of the source `line` numbered `number`
+fn at_line(mut error: MdLogicError, number: usize, line: &str, part: &str) -> MdLogicError {
+    let start = char_offset(line, part);
+    let location = error.location_mut();
+    location.line = Some(number);
+    location.span = Some(Span::new(start, start + part.chars().count()));
+    error
 }
 
-pub fn parse(contents: &str) -> Result<Table, String> {
+pub fn parse(contents: &str) -> Result<Table, MdLogicError> {
     let mut table: Table = Table {
         rows: vec![],
         defs: Definition {
             inputs: vec![],
             outputs: vec![],
+            input_meta: vec![],
+            output_meta: vec![],
         },
+        hit_policy: HitPolicy::default(),
+        env: Environment::default(),
     };
 
     let mut current_line = 0;
     let mut definition_rows: Vec<Row> = vec![];
 
-    for untrimmed_line in contents.lines() {
+    for (line_index, untrimmed_line) in contents.lines().enumerate() {
+        let line_number = line_index + 1;
         let line = untrimmed_line.trim();
         if line.len() == 0 {
             continue;
         }
 
-        let mut columns: Vec<&str> = line.split("|").collect();
+        if !line.starts_with('|') {
+            match line.split_once(':') {
+                Some((key, policy))
+                    if current_line == 0 && key.trim().eq_ignore_ascii_case("hit policy") =>
+                {
+                    table.hit_policy = HitPolicy::from_declaration(policy).map_err(|e| {
+                        at_line(
+                            MdLogicError::table(e),
+                            line_number,
+                            untrimmed_line,
+                            policy.trim(),
+                        )
+                    })?;
+                    continue;
+                }
+                _ => {
+                    let error = MdLogicError::unexpected("a table row", &format!("\"{}\"", line));
+                    return Err(at_line(error, line_number, untrimmed_line, line));
+                }
+            }
+        }
+
+        let mut columns = split_row(line);
 
         // note - split by | will also create/have empty column on left first place, and most right
         if columns.len() < 4 {
-            return Err("incorrect table column size - need at least 1 in, 1 out".to_owned());
+            let error = MdLogicError::table(
+                "incorrect table column size - need at least 1 in, 1 out".to_owned(),
+            );
+            return Err(at_line(error, line_number, untrimmed_line, line));
         }
 
         columns.pop();
         columns.remove(0);
 
-        let mut row = Row { cells: vec![] };
+        let mut row = Row {
+            cells: vec![],
+            line: line_number,
+            positions: vec![],
+        };
 
         for column_content in columns {
-            row.cells.push(column_content.trim().to_string());
+            row.cells.push(column_content.trim().replace("\\|", "|"));
+            row.positions
+                .push(cell_positions(untrimmed_line, column_content));
         }
 
         // 4 is a number of required definition rows
@@ -70,28 +390,53 @@ pub fn parse(contents: &str) -> Result<Table, String> {
     }
 
     if table.rows.len() == 0 {
-        return Err("table has incorrect data row size".to_owned());
+        return Err(MdLogicError::table(
+            "table has incorrect data row size".to_owned(),
+        ));
     }
 
     if definition_rows.len() != 4 {
-        return Err("table definitions are not correct".to_string());
+        return Err(MdLogicError::table(
+            "table definitions are not correct".to_string(),
+        ));
     }
 
     let header_row: usize = 0;
     let io_row: usize = 1;
     let type_row: usize = 2;
+    let meta_row: usize = 3;
 
     for col_index in 0..definition_rows[header_row].cells.len() {
-        let io_def = &definition_rows[io_row].cells[col_index];
-        let column_variable = &definition_rows[header_row].cells[col_index];
-        let type_variable = &definition_rows[type_row].cells[col_index];
+        let io_def = definition_rows[io_row].cell(col_index)?;
+        let column_variable = definition_rows[header_row].cell(col_index)?;
+        let type_variable = definition_rows[type_row].cell(col_index)?;
+        let meta_cell = definition_rows[meta_row].cell(col_index)?;
+
+        if !COLUMN_TYPES.contains(&type_variable.as_str()) {
+            let message = format!(
+                "column \"{}\" has unknown type \"{}\", known are {}",
+                column_variable,
+                type_variable,
+                COLUMN_TYPES.join(", ")
+            );
+            return Err(definition_rows[type_row].locate(MdLogicError::table(message), col_index));
+        }
+        let locate_meta = |e| definition_rows[meta_row].locate(e, col_index);
 
         if io_def.starts_with("-") && io_def.ends_with("-") {
+            table
+                .defs
+                .input_meta
+                .push(ColumnMeta::parse(meta_cell, column_variable, true).map_err(locate_meta)?);
             table
                 .defs
                 .inputs
                 .push((column_variable.clone(), type_variable.clone()));
         } else if io_def.ends_with("-:") {
+            table
+                .defs
+                .output_meta
+                .push(ColumnMeta::parse(meta_cell, column_variable, false).map_err(locate_meta)?);
             table
                 .defs
                 .outputs
@@ -99,39 +444,94 @@ pub fn parse(contents: &str) -> Result<Table, String> {
         }
     }
 
+    let offset = table.defs.inputs.len();
+    for (row_index, row) in table.rows.iter().enumerate() {
+        let columns = table.defs.outputs.iter().zip(&table.defs.output_meta);
+        for (out_index, ((name, _), meta)) in columns.enumerate() {
+            let cell = row
+                .cell(offset + out_index)
+                .map_err(|e| e.at_row(row_index + 1))?;
+            // cells with expressions are checked when the table runs
+            if literal(cell).is_ok_and(|value| !meta.allows(&value)) {
+                let message = format!("{} is not an allowed value of \"{}\"", cell, name);
+                let error = MdLogicError::table(message).at_row(row_index + 1);
+                return Err(row.locate(error, offset + out_index));
+            }
+        }
+    }
+
+    if let HitPolicy::Collect(Some(aggregation)) = table.hit_policy {
+        if table.defs.outputs.len() != 1 {
+            return Err(MdLogicError::table(format!(
+                "hit policy COLLECT {:?} needs exactly one output column, found {}",
+                aggregation,
+                table.defs.outputs.len()
+            )));
+        }
+    }
+
     Ok(table)
 }
 
-#[derive(Serialize)]
-pub struct TableOutputs {
-    pub list: Vec<HashMap<String, Operand>>,
+/// Output values of one row by output column name
+pub type Outputs = HashMap<String, Value>;
+
+/// Result of `run_table`, its shape depends on the hit policy
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TableOutputs {
+    /// `UNIQUE`, `FIRST`, `PRIORITY` and `ANY`, `None` when no row matched
+    Single(Option<Outputs>),
+    /// `RULE ORDER`, `OUTPUT ORDER` and `COLLECT`
+    List(Vec<Outputs>),
+    /// `COLLECT` with an aggregation, `Null` when no row matched
+    Aggregate(Value),
 }
 
-pub fn run_table(table: &Table, context: &serde_json::Value) -> Result<TableOutputs, String> {
-    let mut outputs: Vec<HashMap<String, Operand>> = vec![];
+pub fn run_table(table: &Table, context: &serde_json::Value) -> Result<TableOutputs, MdLogicError> {
+    // input values converted to the column types
+    let mut inputs: Vec<Operand> = Vec::with_capacity(table.defs.inputs.len());
+    let columns = table.defs.inputs.iter().zip(&table.defs.input_meta);
+    for (column, ((name, value_type), meta)) in columns.enumerate() {
+        let input_error = |message: String| {
+            let mut error = MdLogicError::evaluation(message);
+            error.location_mut().column = Some(column + 1);
+            error
+        };
+        let value = get_context_var(name, context);
+        if value.is_null() && meta.required {
+            return Err(input_error(format!("input \"{}\" is required", name)));
+        }
+
+        let input = resolve_var(name, context, table.env.strictness).map_err(input_error)?;
+        let input = parse_temporal(input, value_type)
+            .map_err(|e| input_error(format!("input \"{}\": {}", name, e)))?;
+        if !value.is_null() && !meta.allows(&input) {
+            return Err(input_error(format!(
+                "input \"{}\" is {}, allowed are {}",
+                name,
+                value,
+                meta.allowed.join(", ")
+            )));
+        }
+        inputs.push(input);
+    }
+
+    let mut matched: Vec<(usize, Outputs)> = vec![];
     let mut row_is_true;
 
     for row_index in 0..table.rows.len() {
         row_is_true = true;
 
+        let row = &table.rows[row_index];
+        let locate = |error: MdLogicError, column| row.locate(error.at_row(row_index + 1), column);
         for col_index in 0..table.defs.inputs.len() {
-            let (var_name, _var_type) = &table.defs.inputs[col_index];
-            let input_operand = var_to_operand(var_name, &context);
-            let column_value = &table.rows[row_index].cells[col_index];
-            let mut parser = Tokenizer::new(&column_value);
-
-            parser.parse()?;
-
-            let start_with_operand = parser.starts_with_operand();
-            if !start_with_operand {
-                parser.insert_start(input_operand);
-                parser.insert_start(Operand::OperatorToken(Operator::E));
-            } else {
-                parser.insert_start(input_operand);
-            }
-
-            let expression = parser.to_postfix()?;
-            let expr_result = interpret(&expression);
+            let column_value = &row.cells[col_index];
+            let expression = unary_tests::input_test(column_value, &inputs[col_index])
+                .map_err(|e| locate(e, col_index))?;
+            let expression = bind_inputs(expression, table, &inputs);
+            let expr_result = interpret(&expression, context, &table.env)
+                .map_err(|e| locate(MdLogicError::evaluation(e), col_index))?;
             if let Some(
                 // Operand::Boolean(true)
                 Operand::Primitive(Value::Bool(true)),
@@ -145,34 +545,306 @@ pub fn run_table(table: &Table, context: &serde_json::Value) -> Result<TableOutp
         }
 
         if row_is_true {
-            let mut output_result: HashMap<String, Operand> = HashMap::new();
+            let mut output_result: Outputs = HashMap::new();
 
             let offset = table.defs.inputs.len();
             for col_index in 0..table.defs.outputs.len() {
-                let column_output_value = &table.rows[row_index].cells[col_index + offset];
+                let column_output_value = &row.cells[col_index + offset];
                 let (out_key, _operand_type) = &table.defs.outputs[col_index];
                 output_result.insert(
                     out_key.to_owned(),
-                    Operand::Primitive(Value::String(column_output_value.to_owned())),
+                    evaluate_output(table, col_index, column_output_value, context, &inputs)
+                        .map_err(|e| locate(e, col_index + offset))?,
                 );
             }
 
-            outputs.push(output_result);
+            matched.push((row_index, output_result));
+        }
+    }
+
+    if matched.is_empty() {
+        if let Some(defaults) = default_outputs(table, context, &inputs)? {
+            matched.push((table.rows.len(), defaults));
+        }
+    }
+
+    apply_hit_policy(table, matched)
+}
+
+/// Evaluates the output cell of column `out_index`, an empty cell takes the
+/// column default, and converts the result to the column type
+fn evaluate_output(
+    table: &Table,
+    out_index: usize,
+    cell: &str,
+    context: &Value,
+    inputs: &[Operand],
+) -> Result<Value, MdLogicError> {
+    let (name, value_type) = &table.defs.outputs[out_index];
+    let meta = &table.defs.output_meta[out_index];
+    let expression = match &meta.default {
+        Some(default) if cell.is_empty() => default.as_str(),
+        _ => cell,
+    };
+    if expression.is_empty() {
+        return Ok(Value::Null);
+    }
+
+    let mut parser = Tokenizer::new(expression);
+    parser.parse()?;
+    let postfix = bind_inputs(parser.to_postfix()?, table, inputs);
+
+    let results = interpret(&postfix, context, &table.env).map_err(MdLogicError::evaluation)?;
+    let value = match results.as_slice() {
+        [operand] => coerce(operand.clone(), value_type),
+        _ => Err(format!("\"{}\" is not a single value", expression)),
+    }
+    .map_err(|e| MdLogicError::evaluation(format!("output \"{}\": {}", name, e)))?;
+
+    if value != Operand::Primitive(Value::Null) && !meta.allows(&value) {
+        return Err(MdLogicError::evaluation(format!(
+            "output \"{}\" is {}, allowed are {}",
+            name,
+            output_value(value),
+            meta.allowed.join(", ")
+        )));
+    }
+    Ok(output_value(value))
+}
+
+/// Replaces variables of input columns with their values converted to the column type
+fn bind_inputs(postfix: Vec<Operand>, table: &Table, inputs: &[Operand]) -> Vec<Operand> {
+    postfix
+        .into_iter()
+        .map(|o| {
+            let column = match &o {
+                Operand::Variable(name) => table.defs.inputs.iter().position(|(n, _)| n == name),
+                _ => None,
+            };
+            column.map_or(o, |column| inputs[column].clone())
+        })
+        .collect()
+}
+
+/// Parses ISO-8601 strings for the temporal types of the type row, other values are kept
+fn parse_temporal(value: Operand, value_type: &str) -> Result<Operand, String> {
+    let text = match &value {
+        Operand::Primitive(Value::String(text)) => text,
+        _ => return Ok(value),
+    };
+    match value_type {
+        "date" => Date::parse(text).map(Operand::Date),
+        "date and time" => DateTime::parse(text).map(Operand::DateTime),
+        "duration" => Duration::parse(text).map(Operand::Duration),
+        _ => Ok(value),
+    }
+}
+
+/// Converts an evaluated value to a type of the type row, null stays as is
+fn coerce(value: Operand, value_type: &str) -> Result<Operand, String> {
+    let value = parse_temporal(value, value_type)?;
+    let string = |s: String| Operand::Primitive(Value::String(s));
+    let coerced = match (value_type, value) {
+        (_, value @ Operand::Primitive(Value::Null))
+        | ("string", value @ Operand::Primitive(Value::String(_)))
+        | ("number", value @ (Operand::Number(_) | Operand::Primitive(Value::Number(_))))
+        | ("boolean", value @ Operand::Primitive(Value::Bool(_)))
+        | ("date", value @ Operand::Date(_))
+        | ("date and time", value @ Operand::DateTime(_))
+        | ("duration", value @ Operand::Duration(_)) => return Ok(value),
+        ("string", Operand::Number(n)) => Some(string(n.to_string())),
+        ("string", Operand::Primitive(Value::Number(n))) => Some(string(n.to_string())),
+        ("string", Operand::Primitive(Value::Bool(b))) => Some(string(b.to_string())),
+        ("string", value @ (Operand::Date(_) | Operand::DateTime(_) | Operand::Duration(_))) => {
+            Some(Operand::Primitive(output_value(value)))
+        }
+        ("number", Operand::Primitive(Value::String(s))) => {
+            Decimal::parse(s.trim()).map(Operand::Number)
+        }
+        ("boolean", Operand::Primitive(Value::String(s))) => s
+            .trim()
+            .parse::<bool>()
+            .ok()
+            .map(|b| Operand::Primitive(Value::Bool(b))),
+        ("date", Operand::DateTime(date_time)) => return Ok(Operand::Date(date_time.date())),
+        ("date and time", Operand::Date(date)) => {
+            return Ok(Operand::DateTime(DateTime::from(date)))
         }
+        (_, value) => {
+            return Err(format!(
+                "can not convert {} to {}",
+                output_value(value),
+                value_type
+            ))
+        }
+    };
+    coerced.ok_or_else(|| format!("can not convert to {}", value_type))
+}
+
+/// JSON of an evaluated value, dates and durations as ISO-8601 strings
+fn output_value(value: Operand) -> Value {
+    match value {
+        Operand::Primitive(value) => value,
+        Operand::Number(n) => n.to_value(),
+        Operand::Date(date) => Value::String(date.to_string()),
+        Operand::DateTime(date_time) => Value::String(date_time.to_string()),
+        Operand::Duration(duration) => Value::String(duration.to_string()),
+        _ => Value::Null,
+    }
+}
+
+/// Outputs used when no row matches, `None` when no output column has a default
+fn default_outputs(
+    table: &Table,
+    context: &Value,
+    inputs: &[Operand],
+) -> Result<Option<Outputs>, MdLogicError> {
+    if table.defs.output_meta.iter().all(|m| m.default.is_none()) {
+        return Ok(None);
     }
 
-    Ok(TableOutputs { list: outputs })
+    let mut defaults = Outputs::new();
+    for (out_index, (name, _)) in table.defs.outputs.iter().enumerate() {
+        defaults.insert(
+            name.clone(),
+            evaluate_output(table, out_index, "", context, inputs)?,
+        );
+    }
+    Ok(Some(defaults))
 }
 
+fn apply_hit_policy(
+    table: &Table,
+    mut matched: Vec<(usize, Outputs)>,
+) -> Result<TableOutputs, MdLogicError> {
+    let result = match table.hit_policy {
+        HitPolicy::Unique => {
+            if matched.len() > 1 {
+                let message = format!(
+                    "hit policy UNIQUE: rows {} and {} both match",
+                    matched[0].0 + 1,
+                    matched[1].0 + 1
+                );
+                return Err(MdLogicError::evaluation(message).at_row(matched[1].0 + 1));
+            }
+            TableOutputs::Single(matched.pop().map(|(_, outputs)| outputs))
+        }
+        HitPolicy::First => {
+            TableOutputs::Single(matched.into_iter().next().map(|(_, outputs)| outputs))
+        }
+        HitPolicy::Priority => TableOutputs::Single(
+            matched
+                .into_iter()
+                .min_by_key(|(_, outputs)| priority(table, outputs))
+                .map(|(_, outputs)| outputs),
+        ),
+        HitPolicy::Any => {
+            if let Some((other, _)) = matched.iter().find(|(_, o)| *o != matched[0].1) {
+                let message = format!(
+                    "hit policy ANY: rows {} and {} match with different outputs",
+                    matched[0].0 + 1,
+                    other + 1
+                );
+                return Err(MdLogicError::evaluation(message).at_row(other + 1));
+            }
+            TableOutputs::Single(matched.into_iter().next().map(|(_, outputs)| outputs))
+        }
+        HitPolicy::RuleOrder | HitPolicy::Collect(None) => {
+            TableOutputs::List(matched.into_iter().map(|(_, outputs)| outputs).collect())
+        }
+        HitPolicy::OutputOrder => {
+            matched.sort_by_key(|(_, outputs)| priority(table, outputs));
+            TableOutputs::List(matched.into_iter().map(|(_, outputs)| outputs).collect())
+        }
+        HitPolicy::Collect(Some(aggregation)) => {
+            let (name, _) = &table.defs.outputs[0];
+            let values: Vec<&Value> = matched
+                .iter()
+                .filter_map(|(_, outputs)| outputs.get(name))
+                .collect();
+            TableOutputs::Aggregate(
+                aggregate(aggregation, name, &values).map_err(MdLogicError::evaluation)?,
+            )
+        }
+    };
+
+    Ok(result)
+}
+
+/// Position of each output value in its column's priority list, unlisted
+/// values come after all listed ones
+fn priority(table: &Table, outputs: &Outputs) -> Vec<usize> {
+    table
+        .defs
+        .outputs
+        .iter()
+        .zip(&table.defs.output_meta)
+        .map(|((name, value_type), meta)| {
+            outputs
+                .get(name)
+                .and_then(|value| {
+                    parse_temporal(Operand::Primitive(value.clone()), value_type).ok()
+                })
+                .and_then(|value| meta.position(&value))
+                .unwrap_or(meta.allowed.len())
+        })
+        .collect()
+}
+
+fn aggregate(aggregation: Aggregation, name: &str, values: &[&Value]) -> Result<Value, String> {
+    if aggregation == Aggregation::Count {
+        let mut distinct: Vec<&Value> = vec![];
+        for value in values {
+            if !distinct.iter().any(|d| number::equal(d, value)) {
+                distinct.push(value);
+            }
+        }
+        return Ok(Decimal::from(distinct.len() as i64).to_value());
+    }
+
+    let mut numbers: Vec<Decimal> = vec![];
+    for value in values {
+        match value {
+            Value::Number(n) if Decimal::from_number(n).is_some() => {
+                numbers.extend(Decimal::from_number(n))
+            }
+            _ => {
+                return Err(format!(
+                    "hit policy COLLECT {:?}: output \"{}\" is not a number: {}",
+                    aggregation, name, value
+                ))
+            }
+        }
+    }
+
+    if numbers.is_empty() {
+        return Ok(Value::Null);
+    }
+
+    let result = match aggregation {
+        Aggregation::Sum => numbers
+            .into_iter()
+            .try_fold(Decimal::ZERO, Decimal::checked_add),
+        Aggregation::Min => numbers.into_iter().min(),
+        Aggregation::Max => numbers.into_iter().max(),
+        Aggregation::Count => Some(Decimal::from(numbers.len() as i64)),
+    };
+    Ok(result.map_or(Value::Null, Decimal::to_value))
+}
+
+#[cfg(test)]
 mod tests {
     use super::*;
+    use crate::context::Strictness;
+    use crate::error::Location;
+    use serde_json::json;
     use std::fs;
 
     fn get_test_table() -> Result<Table, String> {
         let contents = fs::read_to_string("./samples/table.md")
             .expect("Something went wrong reading the TEST file");
 
-        parse(&contents)
+        Ok(parse(&contents)?)
     }
 
     #[test]
@@ -199,17 +871,12 @@ mod tests {
         "#;
 
         let context: serde_json::Value = serde_json::from_str(json_str).unwrap();
-        let TableOutputs { list } = run_table(&table, &context)?;
-
-        assert_eq!(list.len(), 1);
+        let firs_res = only_match(run_table(&table, &context)?)?.ok_or("no row matched")?;
 
-        let firs_res = &list[0];
         assert_eq!(firs_res.contains_key("desiredDish"), true);
         assert_eq!(
             firs_res.get("desiredDish"),
-            Some(&Operand::Primitive(Value::String(
-                "\"Spaceribs\"".to_owned()
-            )))
+            Some(&Value::String("Spaceribs".to_owned()))
         );
 
         Ok(())
@@ -231,4 +898,580 @@ mod tests {
             Err(_) => Ok(()),
         }
     }
+
+    fn run_with_policy(policy: &str, guest_count: u32) -> Result<TableOutputs, String> {
+        let contents = format!(
+            r#"
+        Hit policy: {}
+        | guestCount | dish        | price           |
+        |------------|------------:|----------------:|
+        | number     | string      | number          |
+        | ##         | ##          | 5, 12, 20       |
+        | > 4        | "Spaceribs" | 20              |
+        | > 6        | "Roastbeef" | 12              |
+        | > 6        | "Stew"      | 5               |
+        "#,
+            policy
+        );
+        let table = parse(&contents)?;
+        let context = serde_json::json!({ "guestCount": guest_count });
+        Ok(run_table(&table, &context)?)
+    }
+
+    fn dish(outputs: &Outputs) -> Option<&Value> {
+        outputs.get("dish")
+    }
+
+    /// The matching row of a table without a hit policy, which matches at most one
+    fn only_match(result: TableOutputs) -> Result<Option<Outputs>, String> {
+        match result {
+            TableOutputs::List(mut list) if list.len() <= 1 => Ok(list.pop()),
+            other => Err(format!("expected at most one result, got {:?}", other)),
+        }
+    }
+
+    fn string_operand(s: &str) -> Operand {
+        Operand::Primitive(Value::String(s.to_string()))
+    }
+
+    fn string_value(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn parses_hit_policy_declaration() -> Result<(), String> {
+        assert_eq!(get_test_table()?.hit_policy, HitPolicy::Collect(None));
+        assert_eq!(
+            HitPolicy::from_declaration(" rule order ")?,
+            HitPolicy::RuleOrder
+        );
+        assert_eq!(
+            HitPolicy::from_declaration("C#")?,
+            HitPolicy::Collect(Some(Aggregation::Count))
+        );
+        assert!(HitPolicy::from_declaration("COLLECT AVG").is_err());
+
+        let two_outputs = r#"
+        Hit policy: COLLECT SUM
+        | guestCount | dish   | price  |
+        |------------|-------:|-------:|
+        | number     | string | number |
+        | ##         | ##     | ##     |
+        | > 4        | "Stew" | 5      |
+        "#;
+        assert!(parse(two_outputs).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn single_result_policies() -> Result<(), String> {
+        assert!(run_with_policy("UNIQUE", 8).is_err());
+        match run_with_policy("UNIQUE", 5)? {
+            TableOutputs::Single(Some(outputs)) => {
+                assert_eq!(dish(&outputs), Some(&string_value("Spaceribs")))
+            }
+            other => return Err(format!("unexpected {:?}", other)),
+        }
+        assert_eq!(run_with_policy("U", 2)?, TableOutputs::Single(None));
+
+        match run_with_policy("FIRST", 8)? {
+            TableOutputs::Single(Some(outputs)) => {
+                assert_eq!(dish(&outputs), Some(&string_value("Spaceribs")))
+            }
+            other => return Err(format!("unexpected {:?}", other)),
+        }
+
+        // the price column lists "5" first, so the cheapest dish wins
+        match run_with_policy("PRIORITY", 8)? {
+            TableOutputs::Single(Some(outputs)) => {
+                assert_eq!(dish(&outputs), Some(&string_value("Stew")))
+            }
+            other => return Err(format!("unexpected {:?}", other)),
+        }
+
+        assert!(run_with_policy("ANY", 8).is_err());
+        assert!(matches!(
+            run_with_policy("ANY", 5)?,
+            TableOutputs::Single(Some(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn multiple_result_policies() -> Result<(), String> {
+        let dishes = |result: TableOutputs| match result {
+            TableOutputs::List(list) => Ok(list
+                .iter()
+                .filter_map(|outputs| match dish(outputs) {
+                    Some(Value::String(s)) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<String>>()),
+            other => Err(format!("expected a list, got {:?}", other)),
+        };
+
+        assert_eq!(
+            dishes(run_with_policy("RULE ORDER", 8)?)?,
+            vec!["Spaceribs", "Roastbeef", "Stew"]
+        );
+        assert_eq!(
+            dishes(run_with_policy("OUTPUT ORDER", 8)?)?,
+            vec!["Stew", "Roastbeef", "Spaceribs"]
+        );
+        assert_eq!(dishes(run_with_policy("COLLECT", 5)?)?.len(), 1);
+
+        // tables without a hit policy return every match, as before hit policies
+        let table = parse(
+            r#"
+        | guestCount | dish        |
+        |------------|------------:|
+        | number     | string      |
+        | ##         | ##          |
+        | > 4        | "Spaceribs" |
+        | > 6        | "Roastbeef" |
+        | > 10       | "Stew"      |
+        "#,
+        )?;
+        assert_eq!(
+            dishes(run_table(&table, &json!({ "guestCount": 8 }))?)?,
+            vec!["Spaceribs", "Roastbeef"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn aggregating_policies() -> Result<(), String> {
+        let contents = |policy: &str| {
+            format!(
+                r#"
+            Hit policy: {}
+            | guestCount | price  |
+            |------------|-------:|
+            | number     | number |
+            | ##         | ##     |
+            | > 4        | 20     |
+            | > 6        | 12     |
+            | > 6        | 12     |
+            "#,
+                policy
+            )
+        };
+        let context = serde_json::json!({ "guestCount": 8 });
+        let run = |policy: &str| run_table(&parse(&contents(policy))?, &context);
+
+        assert_eq!(run("COLLECT SUM")?, TableOutputs::Aggregate(json!(44)));
+        assert_eq!(run("C<")?, TableOutputs::Aggregate(json!(12)));
+        assert_eq!(run("C>")?, TableOutputs::Aggregate(json!(20)));
+        // distinct values only
+        assert_eq!(run("C#")?, TableOutputs::Aggregate(json!(2)));
+
+        let nobody = serde_json::json!({ "guestCount": 1 });
+        assert_eq!(
+            run_table(&parse(&contents("C+"))?, &nobody)?,
+            TableOutputs::Aggregate(Value::Null)
+        );
+        Ok(())
+    }
+
+    const DISHES: &str = r#"
+        | season                      | guestCount | desiredDish                                        |
+        |-----------------------------|------------|---------------------------------------------------:|
+        | string                      | number     | string                                             |
+        | "Fall", "Winter"; required  | ##         | "Spaceribs", "Roastbeef", "Stew"; default: "Stew" |
+        | "Fall"                      | 8          | "Spaceribs"                                        |
+        | "Winter"                    | 8          |                                                    |
+        "#;
+
+    fn desired_dish(context: serde_json::Value) -> Result<Option<Value>, String> {
+        let outputs = only_match(run_table(&parse(DISHES)?, &context)?)?;
+        Ok(outputs.and_then(|o| o.get("desiredDish").cloned()))
+    }
+
+    #[test]
+    fn parses_column_metadata() -> Result<(), String> {
+        let table = parse(DISHES)?;
+        assert_eq!(
+            table.defs.input_meta[0],
+            ColumnMeta {
+                allowed: vec!["\"Fall\"".to_string(), "\"Winter\"".to_string()],
+                default: None,
+                required: true,
+            }
+        );
+        assert_eq!(table.defs.input_meta[1], ColumnMeta::default());
+        assert_eq!(table.defs.output_meta[0].allowed.len(), 3);
+        assert_eq!(
+            table.defs.output_meta[0].default,
+            Some("\"Stew\"".to_string())
+        );
+        assert!(table.defs.output_meta[0].allows(&string_operand("Stew")));
+        assert!(!table.defs.output_meta[0].allows(&string_operand("Pizza")));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_metadata() {
+        let with_meta = |input: &str, output: &str, dish: &str| {
+            format!(
+                r#"
+            | season | desiredDish |
+            |--------|------------:|
+            | string | string      |
+            | {}     | {}          |
+            | "Fall" | {}          |
+            "#,
+                input, output, dish
+            )
+        };
+
+        assert!(parse(&with_meta("##", "##", "\"Stew\"")).is_ok());
+        assert!(parse(&with_meta("\"a, b\", \"c\"", "##", "\"Stew\"")).is_ok());
+        assert!(parse(&with_meta("##", "required", "\"Stew\"")).is_err());
+        assert!(parse(&with_meta("default: \"Fall\"", "##", "\"Stew\"")).is_err());
+        assert!(parse(&with_meta("season + 1", "##", "\"Stew\"")).is_err());
+        assert!(parse(&with_meta("##", "\"Stew\"; default: \"Pizza\"", "\"Stew\"")).is_err());
+        assert!(parse(&with_meta("##", "\"Stew\", \"Roastbeef\"", "\"Pizza\"")).is_err());
+    }
+
+    #[test]
+    fn enforces_metadata() -> Result<(), String> {
+        assert_eq!(
+            desired_dish(serde_json::json!({ "season": "Fall", "guestCount": 8 }))?,
+            Some(string_value("Spaceribs"))
+        );
+        // empty output cell
+        assert_eq!(
+            desired_dish(serde_json::json!({ "season": "Winter", "guestCount": 8 }))?,
+            Some(string_value("Stew"))
+        );
+        // no row matches
+        assert_eq!(
+            desired_dish(serde_json::json!({ "season": "Fall", "guestCount": 2 }))?,
+            Some(string_value("Stew"))
+        );
+
+        let missing = desired_dish(serde_json::json!({ "guestCount": 8 }));
+        assert_eq!(
+            missing,
+            Err("column 1: input \"season\" is required".to_string())
+        );
+        let not_allowed = desired_dish(serde_json::json!({ "season": "Summer" }));
+        assert!(not_allowed.is_err_and(|e| e.contains("\"Summer\"")));
+        Ok(())
+    }
+
+    #[test]
+    fn evaluates_typed_outputs() -> Result<(), String> {
+        let table = parse(
+            r#"
+            Hit policy: FIRST
+            | guestCount | desiredDish        | portions       | vegetarian | label           |
+            |------------|-------------------:|---------------:|-----------:|----------------:|
+            | number     | string             | number         | boolean    | string          |
+            | ##         | ##                 | ##             | ##         | ##              |
+            | > 10       | "Stew"             | guestCount * 2 | "true"     | guestCount      |
+            | > 4        | "Spare" + "ribs"   | 8              | false      | "for " + "many" |
+            "#,
+        )?;
+
+        let outputs = match run_table(&table, &serde_json::json!({ "guestCount": 12 }))? {
+            TableOutputs::Single(Some(outputs)) => outputs,
+            other => return Err(format!("unexpected {:?}", other)),
+        };
+        assert_eq!(outputs["desiredDish"], string_value("Stew"));
+        assert_eq!(outputs["portions"], json!(24));
+        assert_eq!(outputs["vegetarian"], Value::Bool(true));
+        assert_eq!(outputs["label"], string_value("12"));
+
+        let outputs = match run_table(&table, &serde_json::json!({ "guestCount": 6 }))? {
+            TableOutputs::Single(Some(outputs)) => outputs,
+            other => return Err(format!("unexpected {:?}", other)),
+        };
+        assert_eq!(
+            serde_json::to_value(&outputs).map_err(|e| e.to_string())?,
+            serde_json::json!({
+                "desiredDish": "Spareribs",
+                "portions": 8,
+                "vegetarian": false,
+                "label": "for many"
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_outputs_of_wrong_type() -> Result<(), String> {
+        let table = |output_type: &str, cell: &str| {
+            parse(&format!(
+                r#"
+                | guestCount | portions |
+                |------------|---------:|
+                | number     | {}       |
+                | ##         | ##       |
+                | > 4        | {}       |
+                "#,
+                output_type, cell
+            ))
+        };
+        let context = serde_json::json!({ "guestCount": 6 });
+
+        assert!(table("time", "8").is_err());
+        assert!(run_table(&table("date", "8")?, &context).is_err());
+        assert!(run_table(&table("date", "\"2026-13-01\"")?, &context).is_err());
+        assert!(run_table(&table("number", "\"many\"")?, &context).is_err());
+        assert!(run_table(&table("boolean", "8")?, &context).is_err());
+        assert!(run_table(&table("number", "\"8\"")?, &context).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn strict_tables_reject_unknown_variables() -> Result<(), String> {
+        let mut table = parse(
+            r#"
+            | order.guests | order.dish           |
+            |--------------|---------------------:|
+            | number       | string               |
+            | ##           | ##                   |
+            | > 4          | order.customer.name  |
+            "#,
+        )?;
+        let context = serde_json::json!({ "order": { "guests": 6 } });
+
+        assert_eq!(
+            run_table(&table, &context)?,
+            TableOutputs::List(vec![Outputs::from([(
+                "order.dish".to_string(),
+                Value::Null
+            )])])
+        );
+
+        table.env.strictness = Strictness::Strict;
+        assert_eq!(
+            run_table(&table, &context).map_err(|e| e.to_string()),
+            Err("row 1, column 2, line 6: unknown variable \"order.customer.name\"".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn combines_input_conditions() -> Result<(), String> {
+        let table = parse(
+            r#"
+            Hit policy: FIRST
+            | season                       | guestCount               | desiredDish |
+            |------------------------------|--------------------------|------------:|
+            | string                       | number                   | string      |
+            | ##                           | ##                       | ##          |
+            | "Fall" or "Winter"           | >= 5 and < 10            | "Spareribs" |
+            | not "Summer" && !"Spring"    | >= 10 \|\| guestCount = 1 | "Roastbeef" |
+            | "Summer"                     | not > 4                  | "Salad"     |
+            "#,
+        )?;
+
+        let dish = |season: &str, guest_count: u32| {
+            let context = serde_json::json!({ "season": season, "guestCount": guest_count });
+            match run_table(&table, &context)? {
+                TableOutputs::Single(outputs) => {
+                    Ok(outputs.and_then(|o| o.get("desiredDish").cloned()))
+                }
+                other => Err(format!("unexpected {:?}", other)),
+            }
+        };
+
+        assert_eq!(dish("Winter", 8)?, Some(string_value("Spareribs")));
+        assert_eq!(dish("Fall", 12)?, Some(string_value("Roastbeef")));
+        assert_eq!(dish("Fall", 4)?, None);
+        assert_eq!(dish("Spring", 12)?, None);
+        assert_eq!(dish("Summer", 3)?, Some(string_value("Salad")));
+        Ok(())
+    }
+
+    const SEASONAL_DISHES: &str = r#"
+        Hit policy: First
+        | season             | guestCount | dish         |
+        |--------------------|------------|-------------:|
+        | string             | number     | string       |
+        | ##                 | ##         | ##           |
+        | not("Summer")      | [1..4]     | "Stew"       |
+        | "Fall", "Winter"   | (4..20]    | "Roastbeef"  |
+        | -                  | > 20       | "Spaceribs"  |
+        | -                  | -          | "Salad"      |
+        "#;
+
+    #[test]
+    fn evaluates_unary_tests() -> Result<(), String> {
+        let table = parse(SEASONAL_DISHES)?;
+        let dish_for = |season: &str, guests: u32| -> Result<Option<Value>, String> {
+            let context = serde_json::json!({ "season": season, "guestCount": guests });
+            match run_table(&table, &context)? {
+                TableOutputs::Single(outputs) => Ok(outputs.and_then(|o| o.get("dish").cloned())),
+                other => Err(format!("expected a single result, got {:?}", other)),
+            }
+        };
+
+        assert_eq!(dish_for("Winter", 4)?, Some(string_value("Stew")));
+        assert_eq!(dish_for("Summer", 4)?, Some(string_value("Salad")));
+        assert_eq!(dish_for("Fall", 5)?, Some(string_value("Roastbeef")));
+        assert_eq!(dish_for("Spring", 5)?, Some(string_value("Salad")));
+        assert_eq!(dish_for("Summer", 30)?, Some(string_value("Spaceribs")));
+        Ok(())
+    }
+
+    #[test]
+    fn calls_functions_in_cells() -> Result<(), String> {
+        let mut table = parse(
+            r#"
+        | name                       | label                              | price               |
+        |----------------------------|-----------------------------------:|--------------------:|
+        | string                     | string                             | number              |
+        | ##                         | ##                                 | ##                  |
+        | starts with(?, "Roast")    | upper case(substring(name, 1, 5))  | gross(round(9.6))   |
+        "#,
+        )?;
+        table.env.register("gross", |args| match args {
+            [Operand::Number(n)] => Ok(n
+                .checked_mul(Decimal::from(2))
+                .map_or(Operand::Primitive(Value::Null), Operand::Number)),
+            _ => Err("expected a number".to_string()),
+        });
+
+        let outputs = only_match(run_table(
+            &table,
+            &serde_json::json!({ "name": "Roastbeef" }),
+        )?)?
+        .ok_or("no row matched")?;
+        assert_eq!(outputs.get("label"), Some(&string_value("ROAST")));
+        assert_eq!(outputs.get("price"), Some(&json!(20)));
+        Ok(())
+    }
+
+    const DELIVERIES: &str = r#"
+        | orderDate                | birthDate                                          | shipBy             | waitingTime        | label              |
+        |--------------------------|----------------------------------------------------|-------------------:|-------------------:|-------------------:|
+        | date                     | date                                               | date               | duration           | string             |
+        | required                 | ##                                                 | ##                 | ##                 | ##                 |
+        | < 2026-12-01             | years and months duration(?, orderDate) >= @"P18Y" | orderDate + @"P1M" | @"2026-12-01" - orderDate | orderDate + @"P1M" |
+        | [2026-12-01..2026-12-24] | -                                                  | @"2026-12-24"      | "P2D"              | "christmas"        |
+        "#;
+
+    #[test]
+    fn evaluates_dates_and_durations() -> Result<(), String> {
+        let table = parse(DELIVERIES)?;
+        let run = |order_date: &str| {
+            let context = serde_json::json!({
+                "orderDate": order_date,
+                "birthDate": "2008-10-20",
+            });
+            only_match(run_table(&table, &context)?)
+        };
+
+        let early = run("2026-10-20")?.ok_or("no early delivery")?;
+        assert_eq!(early.get("shipBy"), Some(&string_value("2026-11-20")));
+        assert_eq!(early.get("waitingTime"), Some(&string_value("P42D")));
+        assert_eq!(early.get("label"), Some(&string_value("2026-11-20")));
+
+        // not yet 18 years old on the order date
+        assert_eq!(run("2026-10-19")?, None);
+
+        let christmas = run("2026-12-24")?.ok_or("no christmas delivery")?;
+        assert_eq!(christmas.get("waitingTime"), Some(&string_value("P2D")));
+        assert_eq!(run("2026-12-25")?, None);
+
+        assert_eq!(
+            run("24.12.2026"),
+            Err(
+                "column 1: input \"orderDate\": \"24.12.2026\" is no date, expected YYYY-MM-DD"
+                    .to_string()
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn reports_rows_with_missing_cells() -> Result<(), String> {
+        let contents = r#"
+        | guestCount | season   | dish     |
+        |------------|----------|---------:|
+        | number     | string   | string   |
+        | ##         | ##       | ##       |
+        | > 4        | "Fall"   | "Stew"   |
+        | <= 4       | "Fall"   |
+        "#;
+        let error = parse(contents).err().ok_or("data row should be short")?;
+        assert_eq!(error.message(), "row has 2 cells, expected 3");
+        assert_eq!(
+            *error.location(),
+            Location {
+                row: Some(2),
+                column: None,
+                line: Some(7),
+                span: None,
+            }
+        );
+
+        let contents = r#"
+        | guestCount | season   | dish     |
+        |------------|----------|---------:|
+        | number     | string   | string   |
+        | ##         | ##       |
+        | > 4        | "Fall"   | "Stew"   |
+        "#;
+        let error = parse(contents).err().ok_or("meta row should be short")?;
+        assert_eq!(error.message(), "row has 2 cells, expected 3");
+        assert_eq!(error.location().line, Some(5));
+        Ok(())
+    }
+
+    #[test]
+    fn locates_errors_in_the_source() -> Result<(), String> {
+        let contents = r#"
+        | guestCount | dish                  |
+        |------------|----------------------:|
+        | number     | string                |
+        | ##         | "a\|b", 3 4            |
+        | > 4        | "Stew"                |
+        "#;
+        let error = parse(contents).err().ok_or("metadata should be broken")?;
+        assert_eq!(error.message(), "expected a single value, found \"3 4\"");
+        // the escaped pipe is two characters of the source line
+        assert_eq!(
+            *error.location(),
+            Location {
+                row: None,
+                column: Some(2),
+                line: Some(5),
+                span: Some(Span::new(31, 34)),
+            }
+        );
+
+        let contents = r#"
+        | guestCount | price     |
+        |------------|----------:|
+        | number     | number    |
+        | ##         | ##        |
+        | > 4        | (1 + 2    |
+        "#;
+        let table = parse(contents)?;
+        let error = run_table(&table, &serde_json::json!({ "guestCount": 6 }))
+            .err()
+            .ok_or("cell should be broken")?;
+        let expected = [
+            r#"error: expected ")" for this "(", found end of expression"#,
+            " --> row 1, column 2, line 6",
+            "  |",
+            "6 |         | > 4        | (1 + 2    |",
+            "  |                        ^",
+        ];
+        assert_eq!(error.render(contents), expected.join("\n"));
+
+        let error = parse("| a | b |\n|---|--:|\n| number | text |\n| ## | ## |\n| 1 | 2 |")
+            .err()
+            .ok_or("type should be unknown")?;
+        assert_eq!(
+            error.to_string(),
+            "column 2, line 3: column \"b\" has unknown type \"text\", known are string, \
+             number, boolean, date, date and time, duration"
+        );
+        Ok(())
+    }
 }
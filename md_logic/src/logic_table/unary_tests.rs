@@ -0,0 +1,319 @@
+use crate::error::{char_offset, MdLogicError, Span};
+use crate::expression_parser::operand::{Operand, Operator};
+use crate::expression_parser::tokenizer::{infix_to_postfix, Tokenizer};
+use serde_json::Value;
+
+/// Operands of a cell with their spans in it
+#[derive(Default)]
+struct Infix {
+    operands: Vec<Operand>,
+    spans: Vec<Span>,
+}
+
+impl Infix {
+    fn push(&mut self, o: Operand, span: Span) {
+        self.operands.push(o);
+        self.spans.push(span);
+    }
+
+    /// Operands of `parser`, which parsed `text` of `cell`
+    fn extend(&mut self, parser: Tokenizer, cell: &str, text: &str) {
+        let offset = char_offset(cell, text);
+        let spans = parser.spans().iter();
+        self.spans
+            .extend(spans.map(|s| Span::new(s.start + offset, s.end + offset)));
+        self.operands.extend(parser.into_operands());
+    }
+}
+
+/// Characters of `text` in `cell`
+fn span_of(cell: &str, text: &str) -> Span {
+    let start = char_offset(cell, text);
+    Span::new(start, start + text.chars().count())
+}
+
+/// Compiles an input cell into a postfix expression testing `input`.
+/// Cells are DMN unary tests:
+/// - `-` or an empty cell matches any input
+/// - `"Fall", "Winter"` matches when one of the tests does
+/// - `not("Summer")` matches when none of the tests does
+/// - `[1..10]`, `(5..20]` or `]5..20]` matches inputs in the interval, `(` and
+///   `]` at the start, `)` and `[` at the end exclude the endpoint
+/// - `< 10`, `"Fall"` or `>= 5 and < 10` compare the input as expressions do
+/// - `starts with(?, "Fall")` uses `?` for the input instead of comparing it
+///
+/// Error spans are characters of `cell`.
+pub fn input_test(cell: &str, input: &Operand) -> Result<Vec<Operand>, MdLogicError> {
+    let tests = cell.trim();
+    if tests.is_empty() || tests == "-" {
+        return Ok(vec![Operand::Primitive(Value::Bool(true))]);
+    }
+
+    let mut infix = Infix::default();
+    match negated_tests(tests) {
+        Some(inner) => {
+            let span = span_of(cell, tests);
+            infix.push(Operand::OperatorToken(Operator::Not), span);
+            infix.push(Operand::OpenParen, span);
+            positive_tests(&mut infix, cell, inner, input)?;
+            infix.push(Operand::CloseParen, span);
+        }
+        None => positive_tests(&mut infix, cell, tests, input)?,
+    }
+
+    infix_to_postfix(infix.operands, &infix.spans)
+}
+
+/// Tests inside `not(...)`, `None` when the cell is not negated as a whole
+fn negated_tests(cell: &str) -> Option<&str> {
+    let inner = cell.strip_prefix("not")?.trim_start().strip_prefix('(')?;
+    let inner = inner.strip_suffix(')')?;
+
+    // `not(a) or not(b)` starts and ends like a negated list, but is not one
+    let balanced = split_tests(inner)
+        .iter()
+        .all(|test| interval(test).is_some() || is_balanced(test));
+    balanced.then_some(inner)
+}
+
+fn is_balanced(text: &str) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    for c in text.chars() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => {
+                if depth == 0 {
+                    return false;
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+/// Comma separated tests, matching when any of them does
+fn positive_tests(
+    infix: &mut Infix,
+    cell: &str,
+    tests: &str,
+    input: &Operand,
+) -> Result<(), MdLogicError> {
+    for (index, test) in split_tests(tests).into_iter().enumerate() {
+        let span = span_of(cell, test);
+        if index > 0 {
+            infix.push(Operand::OperatorToken(Operator::Or), span);
+        }
+        infix.push(Operand::OpenParen, span);
+        match interval(test) {
+            Some((start, end)) => {
+                endpoint_test(infix, cell, input, start)?;
+                infix.push(Operand::OperatorToken(Operator::And), span);
+                endpoint_test(infix, cell, input, end)?;
+            }
+            None => {
+                let mut parser = Tokenizer::new(test);
+                parser
+                    .parse()
+                    .map_err(|e| e.shifted(char_offset(cell, test)))?;
+                parser.insert_input(input.clone());
+                infix.extend(parser, cell, test);
+            }
+        }
+        infix.push(Operand::CloseParen, span);
+    }
+    Ok(())
+}
+
+/// Comparison of the input with one end of an interval
+type Endpoint<'a> = (Operator, &'a str);
+
+/// `input <comparison> (endpoint)`
+fn endpoint_test(
+    infix: &mut Infix,
+    cell: &str,
+    input: &Operand,
+    (comparison, endpoint): Endpoint,
+) -> Result<(), MdLogicError> {
+    let span = span_of(cell, endpoint);
+    let mut parser = Tokenizer::new(endpoint);
+    parser.parse().map_err(|e| e.shifted(span.start))?;
+    if parser.spans().is_empty() {
+        return Err(MdLogicError::unexpected("an interval endpoint", "nothing").at(span));
+    }
+
+    infix.push(input.clone(), span);
+    infix.push(Operand::OperatorToken(comparison), span);
+    infix.push(Operand::OpenParen, span);
+    infix.extend(parser, cell, endpoint);
+    infix.push(Operand::CloseParen, span);
+    Ok(())
+}
+
+/// Comparisons of the input with both endpoints, `None` when `test` is no interval
+fn interval(test: &str) -> Option<(Endpoint<'_>, Endpoint<'_>)> {
+    if interval_len(test) != Some(test.len()) {
+        return None;
+    }
+
+    let start = match test.chars().next()? {
+        '[' => Operator::GE,
+        _ => Operator::G,
+    };
+    let end = match test.chars().last()? {
+        ']' => Operator::LE,
+        _ => Operator::L,
+    };
+
+    let inner = &test[1..test.len() - 1];
+    let dots = find_outside_strings(inner, "..")?;
+    Some(((start, &inner[..dots]), (end, &inner[dots + 2..])))
+}
+
+/// Length of the interval at the start of `text`, `None` when it starts with none
+fn interval_len(text: &str) -> Option<usize> {
+    if !text.starts_with(['[', '(', ']']) {
+        return None;
+    }
+    let dots = find_outside_strings(text, "..")?;
+    if comma_position(text) < dots {
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut in_string = false;
+    for (index, c) in text[dots + 2..].char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string && depth > 0 => depth -= 1,
+            ']' | ')' | '[' if !in_string => return Some(dots + 2 + index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits a list of tests on commas outside of strings, parentheses and intervals
+fn split_tests(tests: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut rest = tests;
+    loop {
+        let trimmed = rest.trim_start();
+        let skipped = rest.len() - trimmed.len() + interval_len(trimmed).unwrap_or(0);
+        let end = skipped + comma_position(&rest[skipped..]);
+
+        parts.push(rest[..end].trim());
+        if end >= rest.len() {
+            return parts;
+        }
+        rest = &rest[end + 1..];
+    }
+}
+
+/// Index of the first comma outside of strings and parentheses, or the length of `text`
+fn comma_position(text: &str) -> usize {
+    let mut depth = 0;
+    let mut in_string = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => return index,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+fn find_outside_strings(text: &str, pattern: &str) -> Option<usize> {
+    let mut in_string = false;
+    for (index, c) in text.char_indices() {
+        if c == '"' {
+            in_string = !in_string;
+        } else if !in_string && text[index..].starts_with(pattern) {
+            return Some(index);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression_parser::environment::Environment;
+    use crate::expression_parser::executor::interpret;
+    use serde_json::json;
+
+    fn matches(cell: &str, input: Value) -> Result<bool, String> {
+        let expression = input_test(cell, &Operand::Primitive(input))?;
+        let result = interpret(&expression, &Value::Null, &Environment::default())?;
+        Ok(result.first() == Some(&Operand::Primitive(Value::Bool(true))))
+    }
+
+    #[test]
+    fn wildcard_matches_anything() -> Result<(), String> {
+        assert!(matches("-", json!("Summer"))?);
+        assert!(matches(" - ", json!(3))?);
+        assert!(matches("", Value::Null)?);
+        Ok(())
+    }
+
+    #[test]
+    fn matches_intervals() -> Result<(), String> {
+        assert!(matches("[1..10]", json!(1))?);
+        assert!(matches("[1..10]", json!(10))?);
+        assert!(!matches("[1..10]", json!(11))?);
+
+        assert!(!matches("(5..20]", json!(5))?);
+        assert!(matches("(5..20]", json!(20))?);
+        assert!(!matches("]5..20[", json!(20))?);
+        assert!(matches("[5..20)", json!(19))?);
+        assert!(matches("[2 * 3..10]", json!(6))?);
+        assert!(matches("[min(2, 3)..max(4, 10)]", json!(10))?);
+        Ok(())
+    }
+
+    #[test]
+    fn matches_any_test_of_a_list() -> Result<(), String> {
+        assert!(matches(r#""Fall","Winter""#, json!("Winter"))?);
+        assert!(!matches(r#""Fall", "Winter""#, json!("Summer"))?);
+        assert!(matches(r#""a, b", "c""#, json!("a, b"))?);
+        assert!(matches("< 0, [1..3], > 10", json!(2))?);
+        assert!(!matches("< 0, [1..3], > 10", json!(5))?);
+        assert!(matches(r#"upper case("fall"), "WINTER""#, json!("FALL"))?);
+        assert!(matches(r#"starts with(?, "Fa"), "Winter""#, json!("Fall"))?);
+        assert!(!matches("string length(?) > 4", json!("Fall"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn negates_whole_lists() -> Result<(), String> {
+        assert!(!matches(r#"not("Summer")"#, json!("Summer"))?);
+        assert!(matches(r#"not("Summer")"#, json!("Fall"))?);
+        assert!(!matches(r#"not("Fall", "Winter")"#, json!("Winter"))?);
+        assert!(matches("not([1..5))", json!(5))?);
+        assert!(matches("not(< 3)", json!(4))?);
+        assert!(matches(r#"not("Fall") or not("Winter")"#, json!("Fall"))?);
+        assert!(!matches(r#"not("Fall") and not("Winter")"#, json!("Fall"))?);
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_plain_expressions() -> Result<(), String> {
+        assert!(matches(">= 5 and < 10", json!(7))?);
+        assert!(matches("(1 + 1)", json!(2))?);
+        assert!(matches("(1 + 1) * 2", json!(4))?);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_intervals_without_endpoints() {
+        let input = Operand::Primitive(json!(1));
+        assert!(input_test("[..10]", &input).is_err());
+    }
+}
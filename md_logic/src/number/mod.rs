@@ -0,0 +1,450 @@
+use serde::{Serialize, Serializer};
+use serde_json::{Number, Value};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Most decimals printed, fractions with a larger denominator than
+/// `10^MAX_SCALE` are rounded to it
+pub const MAX_SCALE: u32 = 18;
+
+/// Exact number `numerator / denominator`, reduced and with a positive
+/// denominator, so `10.0` and `10` are the same number. Quotients like
+/// `2 / 3` stay exact through later operations, so `2 / 3 * 3` is `2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    numerator: i128,
+    denominator: i128,
+}
+
+type Division = fn(i128, i128) -> Option<i128>;
+
+fn pow10(exponent: u32) -> Option<i128> {
+    10i128.checked_pow(exponent)
+}
+
+/// Greatest common divisor, `b` is positive so it fits an `i128`
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as i128
+}
+
+/// `n / d` rounded half away from zero
+fn divide_rounded(n: i128, d: i128) -> Option<i128> {
+    let (q, r) = (n.checked_div(d)?, n.checked_rem(d)?);
+    if r != 0 && r.unsigned_abs() >= d.unsigned_abs() - r.unsigned_abs() {
+        q.checked_add(n.signum() * d.signum())
+    } else {
+        Some(q)
+    }
+}
+
+fn divide_floor(n: i128, d: i128) -> Option<i128> {
+    n.checked_div_euclid(d)
+}
+
+fn divide_ceil(n: i128, d: i128) -> Option<i128> {
+    let q = n.checked_div_euclid(d)?;
+    if n.checked_rem_euclid(d)? != 0 {
+        q.checked_add(1)
+    } else {
+        Some(q)
+    }
+}
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal {
+        numerator: 0,
+        denominator: 1,
+    };
+
+    /// Reduced fraction, rounded to `MAX_SCALE` decimals (or fewer for very
+    /// large numbers) when the denominator is larger than `10^MAX_SCALE`
+    fn new(numerator: i128, denominator: i128) -> Option<Decimal> {
+        if denominator == 0 {
+            return None;
+        }
+        let (numerator, denominator) = if denominator < 0 {
+            (numerator.checked_neg()?, denominator.checked_neg()?)
+        } else {
+            (numerator, denominator)
+        };
+        let common = gcd(numerator, denominator);
+        let decimal = Decimal {
+            numerator: numerator / common,
+            denominator: denominator / common,
+        };
+        if decimal.denominator <= pow10(MAX_SCALE)? {
+            return Some(decimal);
+        }
+        (0..=MAX_SCALE)
+            .rev()
+            .find_map(|scale| decimal.round(i64::from(scale)))
+    }
+
+    /// `12`, `-3.25` or `1.5e3`, `None` for other text or numbers out of range
+    pub fn parse(text: &str) -> Option<Decimal> {
+        let (text, exponent) = match text.find(['e', 'E']) {
+            Some(at) => (&text[..at], text[at + 1..].parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        let (integer, fraction) = match digits.split_once('.') {
+            Some((integer, fraction)) if !fraction.is_empty() => (integer, fraction),
+            Some(_) => return None,
+            None => (digits, ""),
+        };
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if integer.is_empty() || !all_digits(integer) || !all_digits(fraction) {
+            return None;
+        }
+
+        let mut mantissa: i128 = 0;
+        for digit in integer.bytes().chain(fraction.bytes()) {
+            mantissa = mantissa
+                .checked_mul(10)?
+                .checked_add(i128::from(digit - b'0'))?;
+        }
+        if negative {
+            mantissa = -mantissa;
+        }
+        let scale = (fraction.len() as i64).checked_sub(exponent)?;
+        if scale >= 0 {
+            Decimal::new(mantissa, pow10(u32::try_from(scale).ok()?)?)
+        } else if mantissa == 0 {
+            Some(Decimal::ZERO)
+        } else {
+            let factor = pow10(u32::try_from(-scale).ok()?)?;
+            Decimal::new(mantissa.checked_mul(factor)?, 1)
+        }
+    }
+
+    /// Exact value of a json number, floats are taken as the decimal they print as
+    pub fn from_number(n: &Number) -> Option<Decimal> {
+        if let Some(n) = n.as_i64() {
+            return Some(Decimal::from(n));
+        }
+        if let Some(n) = n.as_u64() {
+            return Decimal::new(i128::from(n), 1);
+        }
+        n.as_f64()
+            .filter(|n| n.is_finite())
+            .and_then(|n| Decimal::parse(&n.to_string()))
+    }
+
+    /// Integers stay json integers, other numbers become the closest float
+    pub fn to_number(self) -> Option<Number> {
+        if let Some(n) = self.to_integer() {
+            return Some(Number::from(n));
+        }
+        self.to_string()
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+    }
+
+    pub fn to_value(self) -> Value {
+        self.to_number().map_or(Value::Null, Value::Number)
+    }
+
+    /// Value as an `i64` when it has no decimals
+    pub fn to_integer(self) -> Option<i64> {
+        match self.denominator {
+            1 => i64::try_from(self.numerator).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn checked_add(self, other: Decimal) -> Option<Decimal> {
+        let common = gcd(self.denominator, other.denominator);
+        let (a, b) = (self.denominator / common, other.denominator / common);
+        Decimal::new(
+            self.numerator
+                .checked_mul(b)?
+                .checked_add(other.numerator.checked_mul(a)?)?,
+            self.denominator.checked_mul(b)?,
+        )
+    }
+
+    pub fn checked_sub(self, other: Decimal) -> Option<Decimal> {
+        self.checked_add(other.checked_neg()?)
+    }
+
+    pub fn checked_mul(self, other: Decimal) -> Option<Decimal> {
+        // reduce across first so the products stay small
+        let (a, b) = (
+            gcd(self.numerator, other.denominator),
+            gcd(other.numerator, self.denominator),
+        );
+        Decimal::new(
+            (self.numerator / a).checked_mul(other.numerator / b)?,
+            (self.denominator / b).checked_mul(other.denominator / a)?,
+        )
+    }
+
+    /// `None` when dividing by zero
+    pub fn checked_div(self, other: Decimal) -> Option<Decimal> {
+        if other.numerator == 0 {
+            return None;
+        }
+        let inverse = Decimal {
+            numerator: other.denominator * other.numerator.signum(),
+            denominator: other.numerator.checked_abs()?,
+        };
+        self.checked_mul(inverse)
+    }
+
+    pub fn checked_neg(self) -> Option<Decimal> {
+        Decimal::new(self.numerator.checked_neg()?, self.denominator)
+    }
+
+    pub fn abs(self) -> Option<Decimal> {
+        Decimal::new(self.numerator.checked_abs()?, self.denominator)
+    }
+
+    /// Rounded half away from zero to `scale` decimals, to tens for -1
+    pub fn round(self, scale: i64) -> Option<Decimal> {
+        self.round_with(scale, divide_rounded)
+    }
+
+    pub fn floor(self) -> Option<Decimal> {
+        self.round_with(0, divide_floor)
+    }
+
+    pub fn ceil(self) -> Option<Decimal> {
+        self.round_with(0, divide_ceil)
+    }
+
+    fn round_with(self, scale: i64, divide: Division) -> Option<Decimal> {
+        match u32::try_from(scale) {
+            Ok(scale) => {
+                let unit = pow10(scale.min(MAX_SCALE))?;
+                if unit % self.denominator == 0 {
+                    return Some(self);
+                }
+                let numerator = self.numerator.checked_mul(unit)?;
+                Decimal::new(divide(numerator, self.denominator)?, unit)
+            }
+            Err(_) => {
+                let unit = pow10(u32::try_from(scale.checked_neg()?).ok()?)?;
+                let tens = divide(self.numerator, self.denominator.checked_mul(unit)?)?;
+                Decimal::new(tens.checked_mul(unit)?, 1)
+            }
+        }
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(n: i64) -> Self {
+        Decimal {
+            numerator: i128::from(n),
+            denominator: 1,
+        }
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // integer parts first, the remainders are below the denominators so
+        // their cross products fit
+        let parts = |d: &Decimal| {
+            (
+                d.numerator.div_euclid(d.denominator),
+                d.numerator.rem_euclid(d.denominator),
+            )
+        };
+        let ((a_int, a_rest), (b_int, b_rest)) = (parts(self), parts(other));
+        a_int
+            .cmp(&b_int)
+            .then_with(|| (a_rest * other.denominator).cmp(&(b_rest * self.denominator)))
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Decimal {
+    /// Fractions which don't terminate are printed rounded to `MAX_SCALE` decimals
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.numerator < 0 { "-" } else { "" };
+        let (numerator, denominator) = (
+            self.numerator.unsigned_abs(),
+            self.denominator.unsigned_abs(),
+        );
+        let mut integer = numerator / denominator;
+        // the remainder is below `10^MAX_SCALE`, so this fits
+        let unit = 10u128.pow(MAX_SCALE);
+        let remainder = numerator % denominator;
+        let mut fraction = (2 * remainder * unit + denominator) / (2 * denominator);
+        if fraction == unit {
+            integer += 1;
+            fraction = 0;
+        }
+        let fraction = format!("{:0>width$}", fraction, width = MAX_SCALE as usize);
+        match fraction.trim_end_matches('0') {
+            "" => write!(f, "{}{}", sign, integer),
+            fraction => write!(f, "{}{}.{}", sign, integer, fraction),
+        }
+    }
+}
+
+/// Serialized as the json number of `to_value`
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+/// Ordering of two json numbers by exact value
+pub fn compare(a: &Number, b: &Number) -> Option<Ordering> {
+    match (Decimal::from_number(a), Decimal::from_number(b)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        // floats beyond the range of `Decimal`
+        _ => a.as_f64()?.partial_cmp(&b.as_f64()?),
+    }
+}
+
+/// Numbers are equal by value, so `10.0` equals `10`, other values as json
+pub fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => compare(a, b) == Some(Ordering::Equal),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| equal(a, b))
+        }
+        _ => a == b,
+    }
+}
+
+/// `f` of two json numbers, null when one is out of range or `f` gives `None`
+pub fn calculate(a: &Number, b: &Number, f: fn(Decimal, Decimal) -> Option<Decimal>) -> Value {
+    match (Decimal::from_number(a), Decimal::from_number(b)) {
+        (Some(a), Some(b)) => f(a, b).map_or(Value::Null, Decimal::to_value),
+        _ => Value::Null,
+    }
+}
+
+/// `n` with integer values as json integers, so `10.0` becomes `10`
+pub fn normalize(n: &Number) -> Value {
+    Decimal::from_number(n).map_or(Value::Number(n.clone()), Decimal::to_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn decimal(text: &str) -> Result<Decimal, String> {
+        Decimal::parse(text).ok_or(format!("{} is no number", text))
+    }
+
+    #[test]
+    fn parses_and_prints_decimals() -> Result<(), String> {
+        let cases = vec![
+            ("12", "12"),
+            ("10.0", "10"),
+            ("-3.250", "-3.25"),
+            ("0.05", "0.05"),
+            ("1.5e3", "1500"),
+            ("25E-3", "0.025"),
+            ("-0.0", "0"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(decimal(text)?.to_string(), expected, "{}", text);
+        }
+
+        for text in ["", "-", "1.", ".5", "1.2.3", "1e", "abc", "1e99"] {
+            assert_eq!(Decimal::parse(text), None, "{}", text);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn calculates_exactly() -> Result<(), String> {
+        let (a, b) = (decimal("0.1")?, decimal("0.2")?);
+        assert_eq!(a.checked_add(b), Some(decimal("0.3")?));
+        assert_eq!(
+            decimal("19.99")?.checked_mul(Decimal::from(3)),
+            Some(decimal("59.97")?)
+        );
+        assert_eq!(
+            decimal("1")?.checked_sub(decimal("0.9")?),
+            Some(decimal("0.1")?)
+        );
+        assert_eq!(
+            Decimal::from(1).checked_div(Decimal::from(8)),
+            Some(decimal("0.125")?)
+        );
+        let two_thirds = Decimal::from(2)
+            .checked_div(Decimal::from(3))
+            .ok_or("2 / 3")?;
+        assert_eq!(two_thirds.to_string(), "0.666666666666666667");
+        assert_eq!(
+            two_thirds.checked_mul(Decimal::from(3)),
+            Some(Decimal::from(2))
+        );
+        assert!(two_thirds < decimal("0.666666666666666667")?);
+        assert_eq!(Decimal::from(1).checked_div(Decimal::ZERO), None);
+        assert_eq!(
+            Decimal::from(i64::MAX).checked_mul(Decimal::from(i64::MAX)),
+            Some(decimal("85070591730234615847396907784232501249")?)
+        );
+        assert_eq!(
+            decimal("1e37")?.checked_mul(Decimal::from(100)),
+            None,
+            "overflow"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rounds_half_away_from_zero() -> Result<(), String> {
+        let cases = vec![
+            ("2.5", 0, "3"),
+            ("-2.5", 0, "-3"),
+            ("1.005", 2, "1.01"),
+            ("1234", -2, "1200"),
+            ("1.5", 3, "1.5"),
+        ];
+        for (n, scale, expected) in cases {
+            assert_eq!(decimal(n)?.round(scale), Some(decimal(expected)?), "{}", n);
+        }
+        assert_eq!(decimal("-2.5")?.floor(), Some(Decimal::from(-3)));
+        assert_eq!(decimal("-2.5")?.ceil(), Some(Decimal::from(-2)));
+        assert_eq!(decimal("2.1")?.ceil(), Some(Decimal::from(3)));
+        Ok(())
+    }
+
+    #[test]
+    fn compares_json_numbers_by_value() -> Result<(), String> {
+        assert!(equal(&json!(10.0), &json!(10)));
+        assert!(equal(&json!([1, 2.5]), &json!([1.0, 2.5])));
+        assert!(!equal(&json!(10), &json!("10")));
+        assert!(decimal("-1.5")? < decimal("-1.25")?);
+        assert!(decimal("0.1")? < Decimal::from(1));
+        assert_eq!(
+            compare(&Number::from(u64::MAX), &Number::from(-1)),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            normalize(&Number::from_f64(12.0).ok_or("float")?),
+            json!(12)
+        );
+        assert_eq!(
+            calculate(
+                &Number::from(3),
+                &Number::from_f64(0.5).ok_or("float")?,
+                Decimal::checked_mul
+            ),
+            json!(1.5)
+        );
+        Ok(())
+    }
+}